@@ -0,0 +1,6 @@
+DROP INDEX contract_deployer_ind;
+DROP TABLE contract_owners;
+
+ALTER TABLE token_contracts DROP COLUMN deployment_tx_index;
+ALTER TABLE token_contracts DROP COLUMN deployment_block;
+ALTER TABLE token_contracts DROP COLUMN deployer;
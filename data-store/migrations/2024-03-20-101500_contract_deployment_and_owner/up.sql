@@ -0,0 +1,17 @@
+ALTER TABLE token_contracts ADD COLUMN deployer bytea;
+ALTER TABLE token_contracts ADD COLUMN deployment_block int8;
+ALTER TABLE token_contracts ADD COLUMN deployment_tx_index int8;
+
+-- Current owner of Ownable contracts (as per OwnershipTransferred events).
+-- Not restricted to token_contracts, since ownership is often set before the first token event.
+CREATE TABLE contract_owners
+(
+    contract_address      bytea primary key,
+    owner                 bytea not null,
+    last_update_block     int8  not null,
+    last_update_tx        int8  not null,
+    last_update_log_index int8  not null
+);
+
+CREATE INDEX contract_owner_ind ON contract_owners (owner);
+CREATE INDEX contract_deployer_ind ON token_contracts (deployer);
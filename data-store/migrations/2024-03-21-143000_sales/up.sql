@@ -0,0 +1,23 @@
+CREATE TABLE sales
+(
+    block_number       int8           not null,
+    transaction_index  int8           not null,
+    -- Log index of the token transfer settled by the sale
+    transfer_log_index int8           not null,
+    -- Log index of the marketplace fill event
+    sale_log_index     int8           not null,
+    marketplace        text           not null,
+    contract_address   bytea          not null,
+    token_id           numeric(78, 0) not null,
+    amount             numeric(78, 0) not null,
+    seller             bytea          not null,
+    buyer              bytea          not null,
+    -- Denominated in the smallest unit of currency
+    price              numeric(78, 0) not null,
+    -- Zero address for native ETH, null when unknown
+    currency           bytea,
+    fees               numeric(78, 0),
+    primary key (block_number, transfer_log_index, contract_address, token_id)
+);
+
+CREATE INDEX sale_token_ind ON sales (contract_address, token_id);
@@ -0,0 +1,4 @@
+ALTER TABLE transactions DROP COLUMN status;
+ALTER TABLE transactions DROP COLUMN effective_gas_price;
+ALTER TABLE transactions DROP COLUMN gas_used;
+ALTER TABLE transactions DROP COLUMN value;
@@ -0,0 +1,5 @@
+-- Null where the source could not provide them (i.e. no receipt).
+ALTER TABLE transactions ADD COLUMN value numeric(78, 0);
+ALTER TABLE transactions ADD COLUMN gas_used int8;
+ALTER TABLE transactions ADD COLUMN effective_gas_price numeric(78, 0);
+ALTER TABLE transactions ADD COLUMN status bool;
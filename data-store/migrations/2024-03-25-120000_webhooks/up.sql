@@ -0,0 +1,35 @@
+-- Webhooks registered for activity of a wallet or token contract.
+CREATE TABLE webhook_subscriptions
+(
+    id         bigserial primary key,
+    url        text      not null,
+    -- Key of the HMAC-SHA256 signature sent with every delivery
+    secret     text      not null,
+    -- received (address is a wallet) or minted (address is a token contract)
+    kind       text      not null,
+    address    bytea     not null,
+    active     bool      not null default true,
+    created_at timestamp not null default now()
+);
+
+CREATE INDEX webhook_subscription_ind ON webhook_subscriptions (kind, address) WHERE active;
+
+-- Delivery log: one row per matched event, updated on every attempt.
+CREATE TABLE webhook_deliveries
+(
+    id              bigserial primary key,
+    subscription_id int8      not null references webhook_subscriptions (id),
+    block_number    int8      not null,
+    payload         jsonb     not null,
+    -- pending, delivered or failed (once out of retries)
+    status          text      not null default 'pending',
+    attempts        int4      not null default 0,
+    next_attempt_at timestamp not null default now(),
+    -- Outcome of the last attempt
+    response_status int4,
+    last_error      text,
+    created_at      timestamp not null default now(),
+    delivered_at    timestamp
+);
+
+CREATE INDEX webhook_delivery_due_ind ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,11 @@
+-- Metadata requests written along with the updates they were built from,
+-- and published (at least once) by the outbox publisher.
+CREATE TABLE message_outbox
+(
+    id         bigserial primary key,
+    message    jsonb     not null,
+    created_at timestamp not null default now(),
+    sent_at    timestamp
+);
+
+CREATE INDEX message_outbox_unsent_ind ON message_outbox (id) WHERE sent_at IS NULL;
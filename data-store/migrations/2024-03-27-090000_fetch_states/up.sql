@@ -0,0 +1,17 @@
+-- Progress of metadata (token) and ABI (contract) fetches, so that each is only requested
+-- again when due according to the event handler's refresh policy.
+CREATE TABLE fetch_states
+(
+    -- token or contract
+    kind             text      not null,
+    contract_address bytea     not null,
+    -- Zero for contracts
+    token_id         numeric   not null,
+    -- pending, in_flight, done or failed
+    status           text      not null default 'pending',
+    attempts         int4      not null default 0,
+    last_attempt     timestamp,
+    last_error       text,
+    updated_at       timestamp not null default now(),
+    primary key (kind, contract_address, token_id)
+);
@@ -0,0 +1 @@
+DROP TABLE uri_cache;
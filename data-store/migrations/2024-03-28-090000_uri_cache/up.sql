@@ -0,0 +1,14 @@
+-- Metadata fetched per token URI, so that tokens sharing a URI are linked to the same
+-- nft_metadata row without fetching it again (until expired).
+CREATE TABLE uri_cache
+(
+    -- md5 of the uri (data URIs exceed the size of index entries)
+    uri_hash      bytea primary key,
+    uri           text      not null,
+    metadata_id   bytea     not null,
+    -- HTTP validators of the fetched document, for conditional refreshes
+    etag          text,
+    last_modified text,
+    fetched_at    timestamp not null default now(),
+    expires_at    timestamp not null
+);
@@ -0,0 +1,16 @@
+-- Metadata documents of tokens over time: a row per change of a token's metadata (hash),
+-- whose last_seen is bumped as long as fetches return the same document.
+CREATE TABLE token_metadata_history
+(
+    id               bigserial primary key,
+    contract_address bytea     not null,
+    token_id         numeric   not null,
+    metadata_id      bytea     not null,
+    -- token uri at the time of the fetch
+    source_uri       text,
+    first_seen       timestamp not null,
+    last_seen        timestamp not null
+);
+
+CREATE INDEX token_metadata_history_token_idx
+    ON token_metadata_history (contract_address, token_id, first_seen);
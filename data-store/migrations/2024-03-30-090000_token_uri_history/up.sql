@@ -0,0 +1,23 @@
+-- Every observed token uri: emitted by ERC1155 URI events or read from the node
+-- (tokenURI/uri calls), so that metadata changes can be traced back to their uri.
+CREATE TABLE token_uri_history
+(
+    id                bigserial primary key,
+    contract_address  bytea     not null,
+    token_id          numeric   not null,
+    token_uri         text      not null,
+    -- block of the event or of the node state read
+    block_number      int8      not null,
+    -- null for node reads
+    transaction_index int8,
+    log_index         int8,
+    -- event or node
+    source            text      not null,
+    recorded_at       timestamp not null default now()
+);
+
+-- Replayed events are ignored.
+CREATE UNIQUE INDEX token_uri_history_event_idx
+    ON token_uri_history (contract_address, token_id, block_number, transaction_index, log_index, source);
+CREATE INDEX token_uri_history_token_idx
+    ON token_uri_history (contract_address, token_id, block_number);
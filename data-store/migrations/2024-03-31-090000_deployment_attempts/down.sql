@@ -0,0 +1 @@
+ALTER TABLE token_contracts DROP COLUMN deployment_attempts;
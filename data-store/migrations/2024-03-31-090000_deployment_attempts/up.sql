@@ -0,0 +1,3 @@
+-- Failed deployment lookups, which are retried (a limited number of times) while
+-- deployment_block is null.
+ALTER TABLE token_contracts ADD COLUMN deployment_attempts int4 not null default 0;
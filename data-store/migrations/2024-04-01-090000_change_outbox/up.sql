@@ -0,0 +1,11 @@
+-- State change batches written along with the updates they describe,
+-- and published (in order, at least once) by the change feed publisher.
+CREATE TABLE change_outbox
+(
+    id           bigserial primary key,
+    batch        jsonb     not null,
+    created_at   timestamp not null default now(),
+    published_at timestamp
+);
+
+CREATE INDEX change_outbox_unpublished_ind ON change_outbox (id) WHERE published_at IS NULL;
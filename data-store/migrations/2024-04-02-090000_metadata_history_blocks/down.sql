@@ -0,0 +1,2 @@
+ALTER TABLE token_metadata_history
+    DROP COLUMN first_seen_block;
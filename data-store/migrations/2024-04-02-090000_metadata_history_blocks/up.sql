@@ -0,0 +1,4 @@
+-- Latest processed block when a metadata document was first seen, for lookups by block
+-- (null for entries recorded before it was tracked).
+ALTER TABLE token_metadata_history
+    ADD COLUMN first_seen_block bigint;
@@ -0,0 +1,277 @@
+//! Fetch state of requested metadata and ABIs: written as pending along with the outbox
+//! messages requesting them and updated by the metadata-retriever as it fetches them.
+//! The event handler only requests a token or contract again when due by its [FetchPolicy].
+use crate::{
+    models::{FetchKey, FetchState, FetchStatus},
+    schema::*,
+    store::{handle_insert_result, handle_query_result, Connexion, DataStore},
+};
+use diesel::{
+    internal::derives::multiconnection::chrono::{NaiveDateTime, Utc},
+    prelude::*,
+    upsert::excluded,
+};
+use eth::types::Message;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FetchPolicy {
+    /// Pending or in-flight fetches not completed after this many seconds are requested again
+    /// (i.e. when the retriever crashed or the message was lost).
+    pub stale_secs: i64,
+    /// Wait time before the first retry of a failed fetch (doubled on every further attempt).
+    pub retry_secs: i64,
+    /// Failed fetches are given up on after this many attempts.
+    pub max_attempts: i32,
+    /// Completed fetches are requested again after this many seconds (never if unset).
+    pub refresh_secs: Option<i64>,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            stale_secs: 3600,
+            retry_secs: 600,
+            max_attempts: 5,
+            refresh_secs: None,
+        }
+    }
+}
+
+impl FetchPolicy {
+    /// True if the fetch in `state` should be requested (again) at time `now`.
+    pub fn is_due(&self, state: &FetchState, now: NaiveDateTime) -> bool {
+        let elapsed = |time: NaiveDateTime, secs: i64| (now - time).num_seconds() >= secs;
+        match state.status() {
+            Some(FetchStatus::Pending) | Some(FetchStatus::InFlight) => {
+                elapsed(state.updated_at, self.stale_secs)
+            }
+            Some(FetchStatus::Failed) => {
+                let backoff = self.retry_secs << (state.attempts - 1).clamp(0, 16);
+                state.attempts < self.max_attempts
+                    && elapsed(state.last_attempt.unwrap_or(state.updated_at), backoff)
+            }
+            Some(FetchStatus::Done) => self
+                .refresh_secs
+                .is_some_and(|secs| elapsed(state.updated_at, secs)),
+            None => true,
+        }
+    }
+
+    /// Single requests (of `messages`) without known `states` or whose fetch is due.
+    pub fn due_messages(&self, messages: Vec<Message>, states: &[FetchState]) -> Vec<Message> {
+        let now = Utc::now().naive_utc();
+        let states: HashMap<_, _> = states.iter().map(|state| (state.key(), state)).collect();
+        messages
+            .into_iter()
+            .flat_map(Message::split)
+            .filter(|message| {
+                FetchKey::keys(message)
+                    .iter()
+                    .all(|key| states.get(key).is_none_or(|state| self.is_due(state, now)))
+            })
+            .collect()
+    }
+}
+
+impl DataStore {
+    /// Marks the fetches of `keys` as pending.
+    pub(crate) fn save_fetch_requests(conn: &mut Connexion, keys: &[FetchKey]) {
+        let now = Utc::now().naive_utc();
+        // A row can't be upserted twice in one statement.
+        let keys: HashSet<_> = keys.iter().collect();
+        let rows: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                (
+                    fetch_states::kind.eq(key.kind()),
+                    fetch_states::contract_address.eq(key.db_address()),
+                    fetch_states::token_id.eq(key.db_token_id()),
+                    fetch_states::status.eq(FetchStatus::Pending.as_str()),
+                    fetch_states::updated_at.eq(now),
+                )
+            })
+            .collect();
+        let result = diesel::insert_into(fetch_states::table)
+            .values(rows)
+            .on_conflict((
+                fetch_states::kind,
+                fetch_states::contract_address,
+                fetch_states::token_id,
+            ))
+            .do_update()
+            .set((
+                fetch_states::status.eq(excluded(fetch_states::status)),
+                fetch_states::updated_at.eq(excluded(fetch_states::updated_at)),
+            ))
+            .execute(conn);
+        handle_insert_result(result, keys.len(), "save_fetch_requests".into())
+    }
+
+    pub fn load_fetch_states(&mut self, keys: &[FetchKey]) -> Vec<FetchState> {
+        if keys.is_empty() {
+            return vec![];
+        }
+        let mut query = fetch_states::table.into_boxed();
+        for key in keys {
+            query = query.or_filter(
+                fetch_states::kind
+                    .eq(key.kind())
+                    .and(fetch_states::contract_address.eq(key.db_address()))
+                    .and(fetch_states::token_id.eq(key.db_token_id())),
+            );
+        }
+        let result = query
+            .select(FetchState::as_select())
+            .load(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    /// Marks the fetches of `keys` as in flight, counting an attempt.
+    /// Fetches requested directly (without the event handler) are tracked from here.
+    pub fn start_fetches(&mut self, keys: &[FetchKey]) {
+        let now = Utc::now().naive_utc();
+        let keys: HashSet<_> = keys.iter().collect();
+        let rows: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                (
+                    fetch_states::kind.eq(key.kind()),
+                    fetch_states::contract_address.eq(key.db_address()),
+                    fetch_states::token_id.eq(key.db_token_id()),
+                    fetch_states::status.eq(FetchStatus::InFlight.as_str()),
+                    fetch_states::attempts.eq(1),
+                    fetch_states::last_attempt.eq(Some(now)),
+                    fetch_states::updated_at.eq(now),
+                )
+            })
+            .collect();
+        let result = diesel::insert_into(fetch_states::table)
+            .values(rows)
+            .on_conflict((
+                fetch_states::kind,
+                fetch_states::contract_address,
+                fetch_states::token_id,
+            ))
+            .do_update()
+            .set((
+                fetch_states::status.eq(excluded(fetch_states::status)),
+                fetch_states::attempts.eq(fetch_states::attempts + 1),
+                fetch_states::last_attempt.eq(excluded(fetch_states::last_attempt)),
+                fetch_states::updated_at.eq(excluded(fetch_states::updated_at)),
+            ))
+            .execute(&mut self.get_connection());
+        handle_insert_result(result, keys.len(), "start_fetches".into())
+    }
+
+    /// Records the outcome of fetches: done without an error, failed otherwise.
+    pub fn finish_fetches(&mut self, outcomes: &[(FetchKey, Option<String>)]) {
+        let now = Utc::now().naive_utc();
+        let mut conn = self.get_connection();
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for (key, error) in outcomes {
+                let status = match error {
+                    None => FetchStatus::Done,
+                    Some(_) => FetchStatus::Failed,
+                };
+                let result = diesel::update(fetch_states::table.find((
+                    key.kind(),
+                    key.db_address(),
+                    key.db_token_id(),
+                )))
+                .set((
+                    fetch_states::status.eq(status.as_str()),
+                    fetch_states::last_error.eq(error),
+                    fetch_states::updated_at.eq(now),
+                ))
+                .execute(conn);
+                handle_insert_result(result, 1, "finish_fetches".into());
+            }
+            Ok(())
+        })
+        .expect("failed finish_fetches");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::internal::derives::multiconnection::chrono::Duration;
+    use eth::types::{Address, NftId, U256};
+
+    fn state(status: FetchStatus, attempts: i32, updated_at: NaiveDateTime) -> FetchState {
+        FetchState {
+            kind: "token".to_string(),
+            contract_address: Address::from(1),
+            token_id: U256::from(2).into(),
+            status: status.as_str().to_string(),
+            attempts,
+            last_attempt: Some(updated_at),
+            last_error: None,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn due_fetches() {
+        let now = Utc::now().naive_utc();
+        let ago = |secs| now - Duration::seconds(secs);
+        let policy = FetchPolicy::default();
+
+        // Requested fetches are repeated once stale.
+        assert!(!policy.is_due(&state(FetchStatus::Pending, 0, ago(60)), now));
+        assert!(!policy.is_due(&state(FetchStatus::InFlight, 1, ago(60)), now));
+        assert!(policy.is_due(&state(FetchStatus::InFlight, 1, ago(3600)), now));
+
+        // Failed fetches are retried with exponential backoff, up to max_attempts.
+        assert!(policy.is_due(&state(FetchStatus::Failed, 1, ago(600)), now));
+        assert!(!policy.is_due(&state(FetchStatus::Failed, 2, ago(600)), now));
+        assert!(policy.is_due(&state(FetchStatus::Failed, 2, ago(1200)), now));
+        assert!(!policy.is_due(&state(FetchStatus::Failed, 5, ago(100_000)), now));
+
+        // Completed fetches are only refreshed when configured.
+        assert!(!policy.is_due(&state(FetchStatus::Done, 1, ago(100_000)), now));
+        let policy = FetchPolicy {
+            refresh_secs: Some(86_400),
+            ..policy
+        };
+        assert!(policy.is_due(&state(FetchStatus::Done, 1, ago(100_000)), now));
+        assert!(!policy.is_due(&state(FetchStatus::Done, 1, ago(60)), now));
+    }
+
+    #[test]
+    fn due_messages() {
+        let token = |id: u64| Message::Token {
+            address: Address::from(1),
+            token_id: U256::from(id),
+            token_uri: Some("uri".to_string()),
+        };
+        let contract = Message::Contract {
+            address: Address::from(1),
+        };
+        let pending = state(FetchStatus::Pending, 0, Utc::now().naive_utc());
+        assert_eq!(
+            pending.key(),
+            FetchKey::Token(NftId {
+                address: Address::from(1),
+                token_id: U256::from(2),
+            })
+        );
+        // Token 2 is already requested, while token 3 and the contract (of the same address) are not.
+        assert_eq!(
+            FetchPolicy::default()
+                .due_messages(vec![token(2), token(3), contract.clone()], &[pending]),
+            vec![token(3), contract.clone()]
+        );
+        // Batches are split into their single requests.
+        let batch = Message::ContractBatch {
+            addresses: vec![Address::from(1)],
+        };
+        assert_eq!(
+            FetchPolicy::default().due_messages(vec![batch], &[]),
+            vec![contract]
+        );
+    }
+}
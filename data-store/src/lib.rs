@@ -1,4 +1,13 @@
+pub mod fetch_state;
+pub mod memory_store;
+pub mod metadata_history;
 pub mod models;
+pub mod outbox;
+pub mod queries;
 mod schema;
+pub mod snapshot;
+pub mod storage;
 pub mod store;
 pub mod update_cache;
+pub mod uri_cache;
+pub mod webhooks;
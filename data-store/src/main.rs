@@ -0,0 +1,68 @@
+extern crate data_store;
+
+use anyhow::Result;
+use clap::Parser;
+use data_store::{snapshot::SnapshotFormat, store::DataStore};
+use std::path::PathBuf;
+use url::Url;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Store database connection string.
+    #[clap(long, env)]
+    store_url: Url,
+
+    /// DB schema
+    #[clap(long, env)]
+    db_schema: String,
+
+    /// The log filter.
+    #[clap(long, env, default_value = "info")]
+    log: String,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Write a consistent snapshot of the token tables (nfts, erc1155s, erc1155_owners,
+    /// token_contracts and nft_metadata) to a directory, one file per table plus manifest.json.
+    Export {
+        #[clap(long)]
+        output: PathBuf,
+        /// csv or parquet (requires the `parquet` feature).
+        #[clap(long, default_value = "csv")]
+        format: SnapshotFormat,
+        /// Only export rows changed after this block.
+        #[clap(long)]
+        since_block: Option<i64>,
+    },
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse_from(std::env::args());
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_env_filter(args.log)
+        .with_ansi(false)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+
+    let mut store = DataStore::new(args.store_url.as_str(), &args.db_schema)?;
+    match args.command {
+        Command::Export {
+            output,
+            format,
+            since_block,
+        } => {
+            let manifest = store.export_snapshot(&output, format, since_block)?;
+            tracing::info!(
+                "exported snapshot at block {} to {}: {:?}",
+                manifest.processed_block,
+                output.display(),
+                manifest.tables
+            );
+        }
+    }
+    Ok(())
+}
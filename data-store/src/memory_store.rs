@@ -0,0 +1,381 @@
+use crate::{
+    models::{
+        ApprovalForAll, ApprovalId, ContractOwner, Erc1155, Erc1155Owner, FetchKey, FetchState,
+        FetchStatus, NewWebhookDelivery, Nft, Sale, TokenContract, TokenUriObservation,
+        Transaction, WebhookSubscription,
+    },
+    storage::Storage,
+    update_cache::UpdateCache,
+};
+use diesel::internal::derives::multiconnection::chrono::{Duration, NaiveDateTime, Utc};
+use eth::types::{Address, BlockData, Message, NftId};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Storage without a database (i.e. for testing event handling).
+/// Writes follow the conflict rules of [DataStore](crate::store::DataStore):
+/// tokens (except for their metadata ids), owners and approvals are replaced, while
+/// contracts, blocks, transactions, sales and uri events are only inserted once.
+#[derive(Default, Debug)]
+pub struct MemoryStore {
+    nfts: HashMap<NftId, Nft>,
+    multi_tokens: HashMap<NftId, Erc1155>,
+    multi_token_owners: HashMap<(NftId, Address), Erc1155Owner>,
+    approval_for_alls: HashMap<ApprovalId, ApprovalForAll>,
+    contracts: HashMap<Address, TokenContract>,
+    contract_owners: HashMap<Address, ContractOwner>,
+    sales: Vec<Sale>,
+    uri_observations: Vec<TokenUriObservation>,
+    transactions: HashSet<Transaction>,
+    blocks: HashMap<u64, BlockData>,
+    webhook_subscriptions: Vec<WebhookSubscription>,
+    webhook_deliveries: Vec<NewWebhookDelivery>,
+    outbox: Vec<Message>,
+    fetch_states: HashMap<FetchKey, FetchState>,
+    /// Change batches by id, along with their publication time.
+    change_outbox: BTreeMap<i64, (Value, Option<NaiveDateTime>)>,
+}
+
+impl MemoryStore {
+    pub fn sales(&self) -> &[Sale] {
+        &self.sales
+    }
+
+    pub fn uri_observations(&self) -> &[TokenUriObservation] {
+        &self.uri_observations
+    }
+
+    pub fn transactions(&self) -> &HashSet<Transaction> {
+        &self.transactions
+    }
+
+    pub fn block(&self, number: u64) -> Option<&BlockData> {
+        self.blocks.get(&number)
+    }
+
+    pub fn add_webhook_subscription(&mut self, subscription: WebhookSubscription) {
+        self.webhook_subscriptions.push(subscription);
+    }
+
+    pub fn webhook_deliveries(&self) -> &[NewWebhookDelivery] {
+        &self.webhook_deliveries
+    }
+
+    /// Messages written to the outbox (there is no publisher for memory stores).
+    pub fn outbox(&self) -> &[Message] {
+        &self.outbox
+    }
+}
+
+fn same_sale(a: &Sale, b: &Sale) -> bool {
+    (
+        a.block_number,
+        a.transfer_log_index,
+        a.contract_address,
+        &a.token_id,
+    ) == (
+        b.block_number,
+        b.transfer_log_index,
+        b.contract_address,
+        &b.token_id,
+    )
+}
+
+impl Storage for MemoryStore {
+    fn load_nft(&mut self, token: &NftId) -> Option<Nft> {
+        self.nfts.get(token).cloned()
+    }
+
+    fn load_approval(&mut self, id: &ApprovalId) -> Option<ApprovalForAll> {
+        self.approval_for_alls.get(id).cloned()
+    }
+
+    fn load_erc1155(&mut self, token: &NftId) -> Option<Erc1155> {
+        self.multi_tokens.get(token).cloned()
+    }
+
+    fn load_erc1155_owner(&mut self, token: &NftId, address: Address) -> Option<Erc1155Owner> {
+        self.multi_token_owners.get(&(*token, address)).cloned()
+    }
+
+    fn load_contract(&mut self, address: Address) -> Option<TokenContract> {
+        self.contracts.get(&address).cloned()
+    }
+
+    fn load_contract_owner(&mut self, address: Address) -> Option<ContractOwner> {
+        self.contract_owners.get(&address).cloned()
+    }
+
+    fn load_deployment_retries(&mut self, max_attempts: i32, limit: i64) -> Vec<TokenContract> {
+        let mut contracts: Vec<_> = self
+            .contracts
+            .values()
+            .filter(|contract| {
+                contract.deployment_block.is_none()
+                    && (1..max_attempts).contains(&contract.deployment_attempts)
+            })
+            .cloned()
+            .collect();
+        contracts.sort_by_key(|contract| contract.created_block);
+        contracts.truncate(limit as usize);
+        contracts
+    }
+
+    fn get_processed_block(&mut self) -> i64 {
+        self.blocks.keys().max().copied().unwrap_or(0) as i64
+    }
+
+    fn mass_update(
+        &mut self,
+        UpdateCache {
+            nfts,
+            multi_tokens,
+            multi_token_owners,
+            approval_for_alls,
+            contracts,
+            deployment_retries,
+            contract_owners,
+            sales,
+            uri_observations,
+            blocks,
+            transactions,
+            webhook_deliveries,
+            outbox,
+            change_batch,
+        }: UpdateCache,
+    ) {
+        // Already stored values are kept by HashSet::extend.
+        self.transactions.extend(transactions);
+        for block in blocks {
+            self.blocks.entry(block.number).or_insert(block);
+        }
+        for (address, contract) in contracts {
+            self.contracts.entry(address).or_insert(contract);
+        }
+        for contract in deployment_retries {
+            self.contracts.insert(contract.address, contract);
+        }
+        // Stored metadata ids (written by the metadata-retriever) are kept.
+        for (id, mut nft) in nfts {
+            if let Some(stored) = self.nfts.get(&id) {
+                nft.metadata_id = stored.metadata_id.clone();
+            }
+            self.nfts.insert(id, nft);
+        }
+        for (id, mut token) in multi_tokens {
+            if let Some(stored) = self.multi_tokens.get(&id) {
+                token.metadata_id = stored.metadata_id.clone();
+            }
+            self.multi_tokens.insert(id, token);
+        }
+        self.multi_token_owners.extend(
+            multi_token_owners
+                .into_iter()
+                .map(|((token, _, owner), ownership)| ((token, owner), ownership)),
+        );
+        self.approval_for_alls.extend(approval_for_alls);
+        self.contract_owners.extend(contract_owners);
+        for sale in sales {
+            if !self.sales.iter().any(|stored| same_sale(stored, &sale)) {
+                self.sales.push(sale);
+            }
+        }
+        for observation in uri_observations {
+            // Node reads have no log index, so they are always inserted.
+            if observation.log_index.is_none() || !self.uri_observations.contains(&observation) {
+                self.uri_observations.push(observation);
+            }
+        }
+        self.webhook_deliveries.extend(webhook_deliveries);
+        let now = Utc::now().naive_utc();
+        for key in outbox.iter().flat_map(FetchKey::keys) {
+            let state = self.fetch_states.entry(key).or_insert_with(|| FetchState {
+                kind: key.kind().to_string(),
+                contract_address: key.db_address().into(),
+                token_id: key.db_token_id(),
+                status: String::new(),
+                attempts: 0,
+                last_attempt: None,
+                last_error: None,
+                updated_at: now,
+            });
+            state.status = FetchStatus::Pending.as_str().to_string();
+            state.updated_at = now;
+        }
+        self.outbox.extend(outbox);
+        if let Some(batch) = change_batch {
+            let id = self
+                .change_outbox
+                .keys()
+                .next_back()
+                .map_or(1, |last| last + 1);
+            self.change_outbox.insert(id, (batch, None));
+        }
+    }
+
+    fn repair_block_times(&mut self, blocks: &[BlockData]) -> usize {
+        let mut updated = 0;
+        for block in blocks {
+            if let Some(stored) = self.blocks.get_mut(&block.number) {
+                stored.time = block.time;
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    fn load_webhook_subscriptions(&mut self) -> Vec<WebhookSubscription> {
+        self.webhook_subscriptions
+            .iter()
+            .filter(|subscription| subscription.active)
+            .cloned()
+            .collect()
+    }
+
+    fn load_fetch_states(&mut self, keys: &[FetchKey]) -> Vec<FetchState> {
+        keys.iter()
+            .filter_map(|key| self.fetch_states.get(key).cloned())
+            .collect()
+    }
+
+    fn load_unpublished_change_batches(&mut self, limit: i64) -> Vec<(i64, Value)> {
+        self.change_outbox
+            .iter()
+            .filter(|(_, (_, published_at))| published_at.is_none())
+            .take(limit as usize)
+            .map(|(id, (batch, _))| (*id, batch.clone()))
+            .collect()
+    }
+
+    fn mark_change_batches_published(&mut self, ids: &[i64]) {
+        let now = Utc::now().naive_utc();
+        for id in ids {
+            if let Some((_, published_at)) = self.change_outbox.get_mut(id) {
+                *published_at = Some(now);
+            }
+        }
+    }
+
+    fn prune_published_change_batches(&mut self, retention_secs: i64) -> usize {
+        let cutoff = Utc::now().naive_utc() - Duration::seconds(retention_secs);
+        let before = self.change_outbox.len();
+        self.change_outbox
+            .retain(|_, (_, published_at)| published_at.is_none_or(|at| at >= cutoff));
+        before - self.change_outbox.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use eth::types::{Bytes32, TxDetails, U256};
+    use event_retriever::db_reader::models::EventBase;
+
+    fn test_event_base() -> EventBase {
+        EventBase {
+            block_number: 1,
+            log_index: 2,
+            transaction_index: 3,
+            contract_address: Address::from(4),
+        }
+    }
+
+    #[test]
+    fn mass_update_and_load() {
+        let mut store = MemoryStore::default();
+        let base = test_event_base();
+        let token = NftId {
+            address: base.contract_address,
+            token_id: U256::from(123),
+        };
+        let tx = TxDetails {
+            hash: Bytes32::from(1),
+            from: Address::from(1),
+            ..Default::default()
+        };
+        assert_eq!(store.get_processed_block(), 0);
+        assert_eq!(
+            store.load_or_initialize_nft(&base, &token, &tx),
+            Nft::new(&base, &token, &tx)
+        );
+
+        let block = BlockData {
+            number: 5,
+            time: 100,
+            ..Default::default()
+        };
+        let mut nft = Nft::new(&base, &token, &tx);
+        let mut updates = UpdateCache::default();
+        updates.nfts.insert(token, nft.clone());
+        updates
+            .contracts
+            .insert(base.contract_address, TokenContract::from_event_base(&base));
+        updates.add_block_tx(&block, &Transaction::new(5, 0, &tx));
+        store.mass_update(updates);
+
+        assert_eq!(store.load_nft(&token), Some(nft.clone()));
+        assert!(store.load_contract(base.contract_address).is_some());
+        assert_eq!(store.get_processed_block(), 5);
+        assert_eq!(store.transactions().len(), 1);
+
+        // Tokens are replaced, contracts and blocks are not.
+        nft.owner = Address::from(9);
+        let mut contract = TokenContract::from_event_base(&base);
+        contract.name = Some("Replaced".to_string());
+        let mut updates = UpdateCache::default();
+        updates.nfts.insert(token, nft.clone());
+        updates.contracts.insert(base.contract_address, contract);
+        updates.blocks.insert(BlockData {
+            number: 5,
+            time: 200,
+            ..Default::default()
+        });
+        store.mass_update(updates);
+        assert_eq!(store.load_nft(&token).unwrap().owner, Address::from(9));
+        assert_eq!(
+            store.load_contract(base.contract_address).unwrap().name,
+            None
+        );
+        assert_eq!(store.block(5).unwrap().time, 100);
+
+        assert_eq!(
+            store.repair_block_times(&[
+                BlockData {
+                    number: 5,
+                    time: 200,
+                    ..Default::default()
+                },
+                BlockData {
+                    number: 6,
+                    ..Default::default()
+                }
+            ]),
+            1
+        );
+        assert_eq!(store.block(5).unwrap().time, 200);
+        assert_eq!(store.get_processed_block(), 5);
+    }
+
+    #[test]
+    fn erc1155_owners() {
+        let mut store = MemoryStore::default();
+        let base = test_event_base();
+        let token = NftId {
+            address: base.contract_address,
+            token_id: U256::from(1),
+        };
+        let owner = Address::from(2);
+        let mut ownership = store.load_or_initialize_erc1155_owner(&base, &token, owner);
+        assert_eq!(ownership.balance, BigDecimal::from(0));
+        ownership.increase_balance(U256::from(3));
+
+        let mut updates = UpdateCache::default();
+        updates
+            .multi_token_owners
+            .insert((token, base.contract_address, owner), ownership.clone());
+        store.mass_update(updates);
+        assert_eq!(store.load_erc1155_owner(&token, owner), Some(ownership));
+        assert_eq!(store.load_erc1155_owner(&token, Address::from(3)), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! History of token metadata: written along with every metadata update of a token, so that
+//! replaced documents (i.e. before a reveal or a metadata swap) remain known.
+use crate::{
+    models::TokenMetadataHistory,
+    schema::*,
+    store::{handle_insert_result, handle_query_result, Connexion, DataStore},
+};
+use diesel::{
+    internal::derives::multiconnection::chrono::{NaiveDateTime, Utc},
+    prelude::*,
+};
+use eth::types::NftId;
+
+/// Point in time of a history lookup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AsOf {
+    Time(NaiveDateTime),
+    /// Entries are attributed to the latest processed block when first seen: metadata is only
+    /// fetched once the blocks of its token were processed, and a change of the document behind
+    /// a token uri isn't observable on chain (so it lags the block it happened in).
+    Block(i64),
+}
+
+impl DataStore {
+    /// Bumps `last_seen` of the token's current entry if its metadata is `uid`,
+    /// starting a new entry otherwise.
+    pub(crate) fn record_metadata_history(
+        conn: &mut Connexion,
+        token: &NftId,
+        uid: &[u8],
+        source_uri: Option<&str>,
+    ) {
+        let now = Utc::now().naive_utc();
+        let processed_block = blocks::table
+            .select(diesel::dsl::max(blocks::number))
+            .get_result::<Option<i64>>(conn);
+        let processed_block = handle_query_result(processed_block);
+        let latest = token_metadata_history::table
+            .filter(token_metadata_history::contract_address.eq(token.db_address()))
+            .filter(token_metadata_history::token_id.eq(token.db_token_id()))
+            .order(token_metadata_history::first_seen.desc())
+            .select((
+                token_metadata_history::id,
+                token_metadata_history::metadata_id,
+            ))
+            .first::<(i64, Vec<u8>)>(conn)
+            .optional();
+        let result = match handle_query_result(latest) {
+            Some((id, metadata_id)) if metadata_id == uid => {
+                diesel::update(token_metadata_history::table.find(id))
+                    .set(token_metadata_history::last_seen.eq(now))
+                    .execute(conn)
+            }
+            _ => diesel::insert_into(token_metadata_history::table)
+                .values((
+                    token_metadata_history::contract_address.eq(token.db_address()),
+                    token_metadata_history::token_id.eq(token.db_token_id()),
+                    token_metadata_history::metadata_id.eq(uid),
+                    token_metadata_history::source_uri.eq(source_uri),
+                    token_metadata_history::first_seen.eq(now),
+                    token_metadata_history::last_seen.eq(now),
+                    token_metadata_history::first_seen_block.eq(processed_block),
+                ))
+                .execute(conn),
+        };
+        handle_insert_result(result, 1, format!("record_metadata_history: {token}"))
+    }
+
+    /// Metadata entries of `token`, oldest first.
+    pub fn load_metadata_history(&mut self, token: &NftId) -> Vec<TokenMetadataHistory> {
+        let result = token_metadata_history::table
+            .filter(token_metadata_history::contract_address.eq(token.db_address()))
+            .filter(token_metadata_history::token_id.eq(token.db_token_id()))
+            .order(token_metadata_history::first_seen.asc())
+            .select(TokenMetadataHistory::as_select())
+            .load(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    /// The metadata entry of `token` current at `as_of` (if any was seen by then).
+    pub fn load_metadata_as_of(
+        &mut self,
+        token: &NftId,
+        as_of: AsOf,
+    ) -> Option<TokenMetadataHistory> {
+        let query = token_metadata_history::table
+            .filter(token_metadata_history::contract_address.eq(token.db_address()))
+            .filter(token_metadata_history::token_id.eq(token.db_token_id()))
+            .select(TokenMetadataHistory::as_select());
+        let result = match as_of {
+            AsOf::Time(time) => query
+                .filter(token_metadata_history::first_seen.le(time))
+                .order(token_metadata_history::first_seen.desc())
+                .first(&mut self.get_connection()),
+            AsOf::Block(number) => query
+                .filter(token_metadata_history::first_seen_block.le(number))
+                .order((
+                    token_metadata_history::first_seen_block.desc(),
+                    token_metadata_history::first_seen.desc(),
+                ))
+                .first(&mut self.get_connection()),
+        };
+        handle_query_result(result.optional())
+    }
+}
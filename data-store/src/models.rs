@@ -2,7 +2,9 @@ use crate::schema::*;
 use bigdecimal::{BigDecimal, Zero};
 use diesel::internal::derives::multiconnection::chrono::NaiveDateTime;
 use diesel::{AsChangeset, Insertable, Queryable, Selectable};
-use eth::types::{Address, BlockData, Bytes32, NftId, TxDetails, U256};
+use eth::types::{
+    Address, BlockData, Bytes32, ContractDeployment, Message, NftId, TxDetails, U256,
+};
 use event_retriever::db_reader::models::{ApprovalForAll as ApprovalEvent, EventBase};
 use serde::Serialize;
 use serde_json::Value;
@@ -267,13 +269,22 @@ pub struct TokenContract {
     // token_type: TokenType,
     pub name: Option<String>,
     pub symbol: Option<String>,
-    created_block: i64,
-    created_tx_index: i64,
+    /// Block and transaction index of the first event seen for this contract.
+    pub created_block: i64,
+    pub created_tx_index: i64,
     /// This is generally non-null for Erc1155s.
-    base_uri: Option<String>,
+    pub(crate) base_uri: Option<String>,
     /// The md5-hash of the raw document (if available).
-    pub abi_id: Option<Vec<u8>>, // content_flags -> Nullable<Array<Nullable<ContentFlag>>>,
-                                 // content_category -> Nullable<Array<Nullable<ContentCategory>>>
+    pub abi_id: Option<Vec<u8>>,
+    // content_flags -> Nullable<Array<Nullable<ContentFlag>>>,
+    // content_category -> Nullable<Array<Nullable<ContentCategory>>>
+    /// Sender of the contract creation transaction (null for factory deployments).
+    pub deployer: Option<Vec<u8>>,
+    /// Actual deployment location (populated from the Node).
+    pub deployment_block: Option<i64>,
+    pub deployment_tx_index: Option<i64>,
+    /// Failed deployment lookups (retried while below a limit).
+    pub deployment_attempts: i32,
 }
 
 impl TokenContract {
@@ -291,8 +302,82 @@ impl TokenContract {
             //  (try-fetch) https://github.com/Mintbase/evm-indexer/issues/26
             base_uri: None,
             abi_id: None,
+            deployer: None,
+            deployment_block: None,
+            deployment_tx_index: None,
+            deployment_attempts: 0,
         }
     }
+
+    pub fn set_deployment(&mut self, deployment: &ContractDeployment) {
+        self.deployer = deployment.deployer.map(Address::into);
+        self.deployment_block = Some(deployment.block as i64);
+        self.deployment_tx_index = deployment.tx_index.map(|index| index as i64);
+    }
+
+    /// Sets the deployment if it was located, or counts the failed lookup otherwise.
+    pub fn record_deployment_lookup(&mut self, deployment: Option<&ContractDeployment>) {
+        match deployment {
+            Some(deployment) => self.set_deployment(deployment),
+            None => self.deployment_attempts += 1,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, PartialEq, Clone)]
+#[diesel(table_name = contract_owners)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ContractOwner {
+    #[diesel(serialize_as = Vec<u8>)]
+    pub contract_address: Address,
+    #[diesel(serialize_as = Vec<u8>)]
+    pub owner: Address,
+    pub last_update_block: i64,
+    pub last_update_tx: i64,
+    pub last_update_log_index: i64,
+}
+
+impl ContractOwner {
+    pub fn new(contract_address: Address) -> Self {
+        Self {
+            contract_address,
+            owner: Address::zero(),
+            last_update_block: 0,
+            last_update_tx: 0,
+            last_update_log_index: 0,
+        }
+    }
+
+    pub fn event_applied(&self, base: &EventBase) -> bool {
+        (base.block_number as i64, base.log_index as i64)
+            <= (self.last_update_block, self.last_update_log_index)
+    }
+}
+
+/// A marketplace fill matched to the token transfer it settled.
+#[derive(Queryable, Selectable, Insertable, Debug, PartialEq, Clone, Serialize)]
+#[diesel(table_name = sales)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Sale {
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub transfer_log_index: i64,
+    pub sale_log_index: i64,
+    pub marketplace: String,
+    #[diesel(serialize_as = Vec<u8>)]
+    pub contract_address: Address,
+    pub token_id: BigDecimal,
+    pub amount: BigDecimal,
+    #[diesel(serialize_as = Vec<u8>)]
+    pub seller: Address,
+    #[diesel(serialize_as = Vec<u8>)]
+    pub buyer: Address,
+    /// Price paid for `amount` tokens in the smallest unit of `currency`.
+    pub price: BigDecimal,
+    /// Zero address for native ETH (None when the marketplace doesn't emit it).
+    pub currency: Option<Vec<u8>>,
+    /// Marketplace and royalty fees deducted from the price (when emitted).
+    pub fees: Option<BigDecimal>,
 }
 
 #[derive(Queryable, Selectable, Insertable, AsChangeset, Clone, Debug, PartialEq)]
@@ -306,6 +391,10 @@ pub struct Transaction {
     #[diesel(serialize_as = Vec<u8>)]
     from: Address,
     to: Option<Vec<u8>>,
+    value: Option<BigDecimal>,
+    gas_used: Option<i64>,
+    effective_gas_price: Option<BigDecimal>,
+    status: Option<bool>,
 }
 
 impl Eq for Transaction {}
@@ -325,6 +414,10 @@ impl Transaction {
             hash: details.hash,
             from: details.from,
             to: details.to.map(Address::into),
+            value: details.value.map(BigDecimal::from),
+            gas_used: details.gas_used.map(|gas| gas as i64),
+            effective_gas_price: details.effective_gas_price.map(BigDecimal::from),
+            status: details.status,
         }
     }
 }
@@ -346,6 +439,304 @@ impl Block {
     }
 }
 
+/// Activity a webhook subscription is notified of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookKind {
+    /// Tokens received by the subscribed wallet.
+    Received,
+    /// Tokens minted by the subscribed contract.
+    Minted,
+}
+
+impl WebhookKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Minted => "minted",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "received" => Some(Self::Received),
+            "minted" => Some(Self::Minted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = webhook_subscriptions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookSubscription {
+    pub id: i64,
+    pub url: String,
+    /// Key of the HMAC signature sent with every delivery.
+    pub secret: String,
+    pub kind: String,
+    pub address: Address,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl WebhookSubscription {
+    /// None for kinds unknown to this version.
+    pub fn kind(&self) -> Option<WebhookKind> {
+        WebhookKind::parse(&self.kind)
+    }
+}
+
+/// Delivery created for a matched event (attempted later by the delivery worker).
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewWebhookDelivery {
+    pub subscription_id: i64,
+    pub block_number: i64,
+    pub payload: Value,
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub subscription_id: i64,
+    pub block_number: i64,
+    pub payload: Value,
+    /// pending, delivered or failed.
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+/// Result of a delivery attempt, as recorded in the delivery log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookOutcome {
+    Delivered {
+        response_status: i32,
+    },
+    Retry {
+        response_status: Option<i32>,
+        error: String,
+        /// Delay (from now) until the next attempt.
+        retry_in_secs: i64,
+    },
+    /// Out of retries.
+    Failed {
+        response_status: Option<i32>,
+        error: String,
+    },
+}
+
+/// Token (metadata) or contract (ABI) requested from the metadata-retriever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FetchKey {
+    Token(NftId),
+    Contract(Address),
+}
+
+impl FetchKey {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Token(_) => "token",
+            Self::Contract(_) => "contract",
+        }
+    }
+
+    pub fn db_address(&self) -> Vec<u8> {
+        match self {
+            Self::Token(token) => token.db_address(),
+            Self::Contract(address) => (*address).into(),
+        }
+    }
+
+    /// Zero for contracts.
+    pub fn db_token_id(&self) -> BigDecimal {
+        match self {
+            Self::Token(token) => token.db_token_id(),
+            Self::Contract(_) => BigDecimal::zero(),
+        }
+    }
+}
+
+impl FetchKey {
+    /// Keys of the tokens and contracts requested by `message`.
+    pub fn keys(message: &Message) -> Vec<Self> {
+        let token = |address, token_id| Self::Token(NftId { address, token_id });
+        match message {
+            Message::Contract { address } => vec![Self::Contract(*address)],
+            Message::Token {
+                address, token_id, ..
+            } => vec![token(*address, *token_id)],
+            Message::ContractBatch { addresses } => {
+                addresses.iter().copied().map(Self::Contract).collect()
+            }
+            Message::TokenBatch { tokens } => tokens
+                .iter()
+                .map(|request| token(request.address, request.token_id))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchStatus {
+    /// Requested (i.e. written to the outbox).
+    Pending,
+    /// Picked up by the metadata-retriever.
+    InFlight,
+    Done,
+    Failed,
+}
+
+impl FetchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::InFlight => "in_flight",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "pending" => Some(Self::Pending),
+            "in_flight" => Some(Self::InFlight),
+            "done" => Some(Self::Done),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = fetch_states)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct FetchState {
+    /// token or contract.
+    pub kind: String,
+    pub contract_address: Address,
+    pub token_id: BigDecimal,
+    /// pending, in_flight, done or failed.
+    pub status: String,
+    /// Number of fetches started by the metadata-retriever.
+    pub attempts: i32,
+    pub last_attempt: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl FetchState {
+    pub fn key(&self) -> FetchKey {
+        match self.kind.as_str() {
+            "contract" => FetchKey::Contract(self.contract_address),
+            _ => FetchKey::Token(NftId {
+                address: self.contract_address,
+                token_id: self.token_id.clone().into(),
+            }),
+        }
+    }
+
+    pub fn status(&self) -> Option<FetchStatus> {
+        FetchStatus::parse(&self.status)
+    }
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = uri_cache)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UriCacheEntry {
+    pub uri_hash: Vec<u8>,
+    pub uri: String,
+    /// Metadata (nft_metadata.uid) last fetched from the uri.
+    pub metadata_id: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Metadata of a token from `first_seen` (until replaced by a later entry).
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = token_metadata_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TokenMetadataHistory {
+    pub id: i64,
+    pub contract_address: Address,
+    pub token_id: BigDecimal,
+    pub metadata_id: Vec<u8>,
+    pub source_uri: Option<String>,
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    /// Latest processed block when first seen (unknown for entries recorded before it was).
+    pub first_seen_block: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UriSource {
+    /// ERC1155 URI event.
+    Event,
+    /// tokenURI (or uri) call to the node.
+    Node,
+}
+
+impl UriSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Event => "event",
+            Self::Node => "node",
+        }
+    }
+}
+
+/// A token uri as observed at some block (see [UriSource]).
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = token_uri_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TokenUriObservation {
+    #[diesel(serialize_as = Vec<u8>)]
+    pub contract_address: Address,
+    pub token_id: BigDecimal,
+    pub token_uri: String,
+    pub block_number: i64,
+    pub transaction_index: Option<i64>,
+    pub log_index: Option<i64>,
+    /// event or node.
+    pub source: String,
+}
+
+impl TokenUriObservation {
+    pub fn event(base: &EventBase, token_id: U256, token_uri: &str) -> Self {
+        Self {
+            contract_address: base.contract_address,
+            token_id: token_id.into(),
+            token_uri: token_uri.to_string(),
+            block_number: base.block_number as i64,
+            transaction_index: Some(base.transaction_index as i64),
+            log_index: Some(base.log_index as i64),
+            source: UriSource::Event.as_str().to_string(),
+        }
+    }
+
+    pub fn node(token: &NftId, token_uri: &str, block_number: u64) -> Self {
+        Self {
+            contract_address: token.address,
+            token_id: token.token_id.into(),
+            token_uri: token_uri.to_string(),
+            block_number: block_number as i64,
+            transaction_index: None,
+            log_index: None,
+            source: UriSource::Node.as_str().to_string(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -371,9 +762,29 @@ mod tests {
                 created_block: base.block_number.try_into().unwrap(),
                 created_tx_index: base.transaction_index.try_into().unwrap(),
                 base_uri: None,
-                abi_id: None
+                abi_id: None,
+                deployer: None,
+                deployment_block: None,
+                deployment_tx_index: None,
+                deployment_attempts: 0,
             }
-        )
+        );
+
+        let mut contract = TokenContract::from_event_base(&base);
+        contract.record_deployment_lookup(None);
+        assert_eq!(contract.deployment_attempts, 1);
+        assert_eq!(contract.deployment_block, None);
+
+        let deployer = Address::from(5);
+        contract.record_deployment_lookup(Some(&ContractDeployment {
+            address: base.contract_address,
+            block: 1,
+            tx_index: Some(2),
+            deployer: Some(deployer),
+        }));
+        assert_eq!(contract.deployer, Some(deployer.into()));
+        assert_eq!(contract.deployment_block, Some(1));
+        assert_eq!(contract.deployment_tx_index, Some(2));
     }
 
     #[test]
@@ -394,6 +805,7 @@ mod tests {
             hash: Bytes32::from(1),
             from,
             to: Some(Address::from(2)),
+            ..Default::default()
         };
         let nft = Nft::new(&base, &nft_id, &tx);
         assert_eq!(
@@ -468,6 +880,7 @@ mod tests {
             hash: Bytes32::from(1),
             from,
             to: Some(Address::from(2)),
+            ..Default::default()
         };
         let nft = Nft::new(&base, &nft_id, &tx);
 
@@ -482,4 +895,25 @@ mod tests {
         avoid_list.insert(contract_address);
         assert!(!nft.is_fetch_worthy(&avoid_list, &1));
     }
+
+    #[test]
+    fn transaction_new() {
+        let mut details = TxDetails {
+            hash: Bytes32::from(1),
+            from: Address::from(1),
+            to: None,
+            value: Some(U256::from(5)),
+            ..Default::default()
+        };
+        let tx = Transaction::new(1, 2, &details);
+        assert_eq!(tx.value, Some(BigDecimal::from(5)));
+        assert_eq!(tx.gas_used, None);
+        assert_eq!(tx.status, None);
+
+        details.add_receipt(21_000, Some(U256::from(7)), Some(false));
+        let tx = Transaction::new(1, 2, &details);
+        assert_eq!(tx.gas_used, Some(21_000));
+        assert_eq!(tx.effective_gas_price, Some(BigDecimal::from(7)));
+        assert_eq!(tx.status, Some(false));
+    }
 }
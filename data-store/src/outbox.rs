@@ -0,0 +1,108 @@
+//! Outboxes of metadata requests and state change batches: written in the transaction of the
+//! updates they refer to, and marked as sent (or published) once published.
+use crate::{
+    schema::*,
+    store::{handle_insert_result, handle_query_result, Connexion, DataStore},
+};
+use diesel::{
+    dsl::{now, IntervalDsl},
+    prelude::*,
+};
+use eth::types::Message;
+use serde_json::Value;
+
+impl DataStore {
+    pub(crate) fn save_outbox_messages(conn: &mut Connexion, messages: Vec<Message>) {
+        let expected_inserts = messages.len();
+        tracing::info!("saving {} outbox messages", expected_inserts);
+        let rows: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                message_outbox::message
+                    .eq(serde_json::to_value(message).expect("message is JSON serializable"))
+            })
+            .collect();
+        let result = diesel::insert_into(message_outbox::table)
+            .values(rows)
+            .execute(conn);
+        handle_insert_result(result, expected_inserts, "save_outbox_messages".into())
+    }
+
+    /// Oldest unsent messages (along with their ids). Rows that don't decode as messages are
+    /// logged and marked as sent, so that they neither block nor crash the publisher (they are
+    /// kept for inspection until pruned).
+    pub fn load_unsent_messages(&mut self, limit: i64) -> Vec<(i64, Message)> {
+        let result = message_outbox::table
+            .filter(message_outbox::sent_at.is_null())
+            .order(message_outbox::id)
+            .limit(limit)
+            .select((message_outbox::id, message_outbox::message))
+            .load::<(i64, Value)>(&mut self.get_connection());
+        let mut undecodable = vec![];
+        let messages = handle_query_result(result)
+            .into_iter()
+            .filter_map(|(id, message)| match serde_json::from_value(message) {
+                Ok(message) => Some((id, message)),
+                Err(err) => {
+                    tracing::error!("skipping undecodable outbox message {id}: {err}");
+                    undecodable.push(id);
+                    None
+                }
+            })
+            .collect();
+        if !undecodable.is_empty() {
+            self.mark_messages_sent(&undecodable);
+        }
+        messages
+    }
+
+    pub fn mark_messages_sent(&mut self, ids: &[i64]) {
+        let result = diesel::update(message_outbox::table)
+            .filter(message_outbox::id.eq_any(ids))
+            .set(message_outbox::sent_at.eq(now.nullable()))
+            .execute(&mut self.get_connection());
+        handle_insert_result(result, ids.len(), "mark_messages_sent".into())
+    }
+
+    /// Deletes messages sent more than `retention_secs` ago, returning their number.
+    pub fn prune_sent_messages(&mut self, retention_secs: i64) -> usize {
+        let result = diesel::delete(message_outbox::table)
+            .filter(message_outbox::sent_at.lt((now - retention_secs.seconds()).nullable()))
+            .execute(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub(crate) fn save_change_batch(conn: &mut Connexion, batch: Value) {
+        let result = diesel::insert_into(change_outbox::table)
+            .values(change_outbox::batch.eq(batch))
+            .execute(conn);
+        handle_insert_result(result, 1, "save_change_batch".into())
+    }
+
+    /// Oldest unpublished change batches (along with their ids).
+    pub fn load_unpublished_change_batches(&mut self, limit: i64) -> Vec<(i64, Value)> {
+        let result = change_outbox::table
+            .filter(change_outbox::published_at.is_null())
+            .order(change_outbox::id)
+            .limit(limit)
+            .select((change_outbox::id, change_outbox::batch))
+            .load(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn mark_change_batches_published(&mut self, ids: &[i64]) {
+        let result = diesel::update(change_outbox::table)
+            .filter(change_outbox::id.eq_any(ids))
+            .set(change_outbox::published_at.eq(now.nullable()))
+            .execute(&mut self.get_connection());
+        handle_insert_result(result, ids.len(), "mark_change_batches_published".into())
+    }
+
+    /// Deletes change batches published more than `retention_secs` ago, returning their number.
+    pub fn prune_published_change_batches(&mut self, retention_secs: i64) -> usize {
+        let result = diesel::delete(change_outbox::table)
+            .filter(change_outbox::published_at.lt((now - retention_secs.seconds()).nullable()))
+            .execute(&mut self.get_connection());
+        handle_query_result(result)
+    }
+}
@@ -0,0 +1,181 @@
+//! Read-side queries for API consumers: keyset-paginated token listings
+//! and batch loads (by primary key) of the documents they reference.
+use crate::{
+    models::{ContractAbi, Erc1155, Erc1155Owner, Nft, NftMetadata, TokenContract},
+    schema::*,
+    store::{handle_query_result, DataStore},
+};
+use bigdecimal::{BigDecimal, Zero};
+use diesel::prelude::*;
+use eth::types::{Address, NftId};
+
+/// Optional (conjunctive) filters of token listings.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TokenFilter {
+    pub contract: Option<Address>,
+    /// Current owner (ERC721) or holder of a positive balance (ERC1155 ownerships).
+    pub owner: Option<Address>,
+    /// First minter (creator of ERC1155s).
+    pub minter: Option<Address>,
+}
+
+fn db_addresses(addresses: &[Address]) -> Vec<Vec<u8>> {
+    addresses.iter().map(|address| (*address).into()).collect()
+}
+
+impl DataStore {
+    /// Up to `limit` ERC721 tokens ordered by (contract, token id), starting after `after`.
+    pub fn load_nft_page(
+        &mut self,
+        filter: &TokenFilter,
+        after: Option<&NftId>,
+        limit: i64,
+    ) -> Vec<Nft> {
+        let mut query = nfts::table.into_boxed();
+        if let Some(contract) = filter.contract {
+            query = query.filter(nfts::contract_address.eq::<Vec<u8>>(contract.into()));
+        }
+        if let Some(owner) = filter.owner {
+            query = query.filter(nfts::owner.eq::<Vec<u8>>(owner.into()));
+        }
+        if let Some(minter) = filter.minter {
+            query = query.filter(nfts::minter.eq::<Vec<u8>>(minter.into()));
+        }
+        if let Some(after) = after {
+            query = query.filter(
+                nfts::contract_address
+                    .gt(after.db_address())
+                    .or(nfts::contract_address
+                        .eq(after.db_address())
+                        .and(nfts::token_id.gt(after.db_token_id()))),
+            );
+        }
+        let result = query
+            .order((nfts::contract_address, nfts::token_id))
+            .limit(limit)
+            .load::<Nft>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    /// Up to `limit` ERC1155 tokens ordered by (contract, token id), starting after `after`.
+    /// The owner filter does not apply (see [DataStore::load_erc1155_owner_page]).
+    pub fn load_erc1155_page(
+        &mut self,
+        filter: &TokenFilter,
+        after: Option<&NftId>,
+        limit: i64,
+    ) -> Vec<Erc1155> {
+        let mut query = erc1155s::table.into_boxed();
+        if let Some(contract) = filter.contract {
+            query = query.filter(erc1155s::contract_address.eq::<Vec<u8>>(contract.into()));
+        }
+        if let Some(minter) = filter.minter {
+            query = query.filter(erc1155s::creator_address.eq::<Vec<u8>>(minter.into()));
+        }
+        if let Some(after) = after {
+            query = query.filter(
+                erc1155s::contract_address
+                    .gt(after.db_address())
+                    .or(erc1155s::contract_address
+                        .eq(after.db_address())
+                        .and(erc1155s::token_id.gt(after.db_token_id()))),
+            );
+        }
+        let result = query
+            .order((erc1155s::contract_address, erc1155s::token_id))
+            .limit(limit)
+            .load::<Erc1155>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    /// Up to `limit` positive ERC1155 balances ordered by (contract, token id, owner),
+    /// starting after `after`. The minter filter does not apply.
+    pub fn load_erc1155_owner_page(
+        &mut self,
+        filter: &TokenFilter,
+        after: Option<(&NftId, Address)>,
+        limit: i64,
+    ) -> Vec<Erc1155Owner> {
+        let mut query = erc1155_owners::table
+            .filter(erc1155_owners::balance.gt(BigDecimal::zero()))
+            .into_boxed();
+        if let Some(contract) = filter.contract {
+            query = query.filter(erc1155_owners::contract_address.eq::<Vec<u8>>(contract.into()));
+        }
+        if let Some(owner) = filter.owner {
+            query = query.filter(erc1155_owners::owner.eq::<Vec<u8>>(owner.into()));
+        }
+        if let Some((token, owner)) = after {
+            let same_token = erc1155_owners::contract_address
+                .eq(token.db_address())
+                .and(erc1155_owners::token_id.eq(token.db_token_id()));
+            query = query.filter(
+                erc1155_owners::contract_address
+                    .gt(token.db_address())
+                    .or(erc1155_owners::contract_address
+                        .eq(token.db_address())
+                        .and(erc1155_owners::token_id.gt(token.db_token_id())))
+                    .or(same_token.and(erc1155_owners::owner.gt::<Vec<u8>>(owner.into()))),
+            );
+        }
+        let result = query
+            .order((
+                erc1155_owners::contract_address,
+                erc1155_owners::token_id,
+                erc1155_owners::owner,
+            ))
+            .limit(limit)
+            .load::<Erc1155Owner>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    /// Up to `limit` contracts ordered by address, starting after `after`.
+    pub fn load_contract_page(&mut self, after: Option<Address>, limit: i64) -> Vec<TokenContract> {
+        let mut query = token_contracts::table.into_boxed();
+        if let Some(after) = after {
+            query = query.filter(token_contracts::address.gt::<Vec<u8>>(after.into()));
+        }
+        let result = query
+            .order(token_contracts::address)
+            .limit(limit)
+            .load::<TokenContract>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn load_contracts(&mut self, addresses: &[Address]) -> Vec<TokenContract> {
+        let result = token_contracts::table
+            .filter(token_contracts::address.eq_any(db_addresses(addresses)))
+            .load::<TokenContract>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn load_erc1155s(&mut self, tokens: &[NftId]) -> Vec<Erc1155> {
+        if tokens.is_empty() {
+            return vec![];
+        }
+        let mut query = erc1155s::table.into_boxed();
+        for token in tokens {
+            query = query.or_filter(
+                erc1155s::contract_address
+                    .eq(token.db_address())
+                    .and(erc1155s::token_id.eq(token.db_token_id())),
+            );
+        }
+        let result = query.load::<Erc1155>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn load_nft_metadata(&mut self, uids: &[Vec<u8>]) -> Vec<NftMetadata> {
+        let result = nft_metadata::table
+            .filter(nft_metadata::uid.eq_any(uids))
+            .load::<NftMetadata>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn load_contract_abis(&mut self, uids: &[Vec<u8>]) -> Vec<ContractAbi> {
+        let result = contract_abis::table
+            .filter(contract_abis::uid.eq_any(uids))
+            .load::<ContractAbi>(&mut self.get_connection());
+        handle_query_result(result)
+    }
+}
@@ -76,12 +76,44 @@ diesel::table! {
         created_block -> Int8,
         created_tx_index -> Int8,
         base_uri -> Nullable<Text>,
-        abi_id -> Nullable<Bytea>
+        abi_id -> Nullable<Bytea>,
+        deployer -> Nullable<Bytea>,
+        deployment_block -> Nullable<Int8>,
+        deployment_tx_index -> Nullable<Int8>,
+        deployment_attempts -> Int4,
         // content_flags -> Nullable<Array<Nullable<ContentFlag>>>,
         // content_category -> Nullable<Array<Nullable<ContentCategory>>>,
     }
 }
 
+diesel::table! {
+    contract_owners (contract_address) {
+        contract_address -> Bytea,
+        owner -> Bytea,
+        last_update_block -> Int8,
+        last_update_tx -> Int8,
+        last_update_log_index -> Int8,
+    }
+}
+
+diesel::table! {
+    sales (block_number, transfer_log_index, contract_address, token_id) {
+        block_number -> Int8,
+        transaction_index -> Int8,
+        transfer_log_index -> Int8,
+        sale_log_index -> Int8,
+        marketplace -> Text,
+        contract_address -> Bytea,
+        token_id -> Numeric,
+        amount -> Numeric,
+        seller -> Bytea,
+        buyer -> Bytea,
+        price -> Numeric,
+        currency -> Nullable<Bytea>,
+        fees -> Nullable<Numeric>,
+    }
+}
+
 diesel::table! {
     transactions (block_number, index) {
         block_number -> Int8,
@@ -89,6 +121,10 @@ diesel::table! {
         hash -> Bytea,
         from -> Bytea,
         to -> Nullable<Bytea>,
+        value -> Nullable<Numeric>,
+        gas_used -> Nullable<Int8>,
+        effective_gas_price -> Nullable<Numeric>,
+        status -> Nullable<Bool>,
     }
 }
 
@@ -124,13 +160,124 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_subscriptions (id) {
+        id -> Int8,
+        url -> Text,
+        secret -> Text,
+        kind -> Text,
+        address -> Bytea,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int8,
+        subscription_id -> Int8,
+        block_number -> Int8,
+        payload -> Jsonb,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    change_outbox (id) {
+        id -> Int8,
+        batch -> Jsonb,
+        created_at -> Timestamp,
+        published_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    message_outbox (id) {
+        id -> Int8,
+        message -> Jsonb,
+        created_at -> Timestamp,
+        sent_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    fetch_states (kind, contract_address, token_id) {
+        kind -> Text,
+        contract_address -> Bytea,
+        token_id -> Numeric,
+        status -> Text,
+        attempts -> Int4,
+        last_attempt -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    uri_cache (uri_hash) {
+        uri_hash -> Bytea,
+        uri -> Text,
+        metadata_id -> Bytea,
+        etag -> Nullable<Text>,
+        last_modified -> Nullable<Text>,
+        fetched_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    token_metadata_history (id) {
+        id -> Int8,
+        contract_address -> Bytea,
+        token_id -> Numeric,
+        metadata_id -> Bytea,
+        source_uri -> Nullable<Text>,
+        first_seen -> Timestamp,
+        last_seen -> Timestamp,
+        first_seen_block -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    token_uri_history (id) {
+        id -> Int8,
+        contract_address -> Bytea,
+        token_id -> Numeric,
+        token_uri -> Text,
+        block_number -> Int8,
+        transaction_index -> Nullable<Int8>,
+        log_index -> Nullable<Int8>,
+        source -> Text,
+        recorded_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(webhook_deliveries -> webhook_subscriptions (subscription_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     approval_for_all,
     erc1155s,
     erc1155_owners,
     contract_abis,
+    contract_owners,
+    nft_metadata,
     nfts,
+    sales,
     token_contracts,
     transactions,
-    blocks
+    blocks,
+    webhook_deliveries,
+    webhook_subscriptions,
+    message_outbox,
+    change_outbox,
+    fetch_states,
+    uri_cache,
+    token_metadata_history,
+    token_uri_history
 );
@@ -0,0 +1,498 @@
+//! Point-in-time exports of the token tables (for analytics), written to one file per table
+//! alongside a manifest recording the processed block the snapshot corresponds to.
+use crate::{
+    models::{Erc1155, Erc1155Owner, Nft, NftMetadata, TokenContract},
+    schema::*,
+    store::DataStore,
+};
+use anyhow::{Context, Result};
+use diesel::{pg::PgConnection, prelude::*, QueryResult};
+use eth::types::Address;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs::File, path::Path, str::FromStr};
+
+#[cfg(feature = "parquet")]
+mod parquet_file;
+
+/// Rows loaded (and written) at once.
+const PAGE_SIZE: i64 = 50_000;
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotFormat {
+    Csv,
+    Parquet,
+}
+
+impl SnapshotFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(format!(
+                "unknown snapshot format {s} (expected csv or parquet)"
+            )),
+        }
+    }
+}
+
+/// Written as `manifest.json` next to the table files.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SnapshotManifest {
+    /// Last block processed when the snapshot was taken.
+    pub processed_block: i64,
+    /// Incremental snapshots only contain rows changed after this block.
+    pub since_block: Option<i64>,
+    pub format: SnapshotFormat,
+    /// Number of rows exported per table.
+    pub tables: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ColumnType {
+    Int,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Int(Option<i64>),
+    Text(Option<String>),
+}
+
+/// Binary columns are exported as 0x-prefixed hex and numerics as decimal strings.
+trait SnapshotRow {
+    const TABLE: &'static str;
+    const COLUMNS: &'static [(&'static str, ColumnType)];
+
+    fn values(self) -> Vec<Value>;
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{digits}")
+}
+
+fn address(address: Address) -> Value {
+    Value::Text(Some(hex(address.0 .0.as_slice())))
+}
+
+fn bytes(bytes: Option<Vec<u8>>) -> Value {
+    Value::Text(bytes.as_deref().map(hex))
+}
+
+fn text(value: impl ToString) -> Value {
+    Value::Text(Some(value.to_string()))
+}
+
+impl SnapshotRow for Nft {
+    const TABLE: &'static str = "nfts";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("contract_address", ColumnType::Text),
+        ("token_id", ColumnType::Text),
+        ("token_uri", ColumnType::Text),
+        ("owner", ColumnType::Text),
+        ("metadata_id", ColumnType::Text),
+        ("last_update_block", ColumnType::Int),
+        ("last_update_tx", ColumnType::Int),
+        ("last_update_log_index", ColumnType::Int),
+        ("last_transfer_block", ColumnType::Int),
+        ("last_transfer_tx", ColumnType::Int),
+        ("mint_block", ColumnType::Int),
+        ("mint_tx", ColumnType::Int),
+        ("burn_block", ColumnType::Int),
+        ("burn_tx", ColumnType::Int),
+        ("minter", ColumnType::Text),
+        ("approved", ColumnType::Text),
+    ];
+
+    fn values(self) -> Vec<Value> {
+        vec![
+            address(self.contract_address),
+            text(self.token_id),
+            Value::Text(self.token_uri),
+            address(self.owner),
+            bytes(self.metadata_id),
+            Value::Int(Some(self.last_update_block)),
+            Value::Int(Some(self.last_update_tx)),
+            Value::Int(Some(self.last_update_log_index)),
+            Value::Int(self.last_transfer_block),
+            Value::Int(self.last_transfer_tx),
+            Value::Int(Some(self.mint_block)),
+            Value::Int(Some(self.mint_tx)),
+            Value::Int(self.burn_block),
+            Value::Int(self.burn_tx),
+            address(self.minter),
+            bytes(self.approved),
+        ]
+    }
+}
+
+impl SnapshotRow for Erc1155 {
+    const TABLE: &'static str = "erc1155s";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("contract_address", ColumnType::Text),
+        ("token_id", ColumnType::Text),
+        ("token_uri", ColumnType::Text),
+        ("total_supply", ColumnType::Text),
+        ("creator_address", ColumnType::Text),
+        ("metadata_id", ColumnType::Text),
+        ("mint_block", ColumnType::Int),
+        ("mint_tx", ColumnType::Int),
+        ("last_update_block", ColumnType::Int),
+        ("last_update_tx", ColumnType::Int),
+        ("last_update_log_index", ColumnType::Int),
+    ];
+
+    fn values(self) -> Vec<Value> {
+        vec![
+            address(self.contract_address),
+            text(self.token_id),
+            Value::Text(self.token_uri),
+            text(self.total_supply),
+            address(self.creator_address),
+            bytes(self.metadata_id),
+            Value::Int(Some(self.mint_block)),
+            Value::Int(Some(self.mint_tx)),
+            Value::Int(Some(self.last_update_block)),
+            Value::Int(Some(self.last_update_tx)),
+            Value::Int(Some(self.last_update_log_index)),
+        ]
+    }
+}
+
+impl SnapshotRow for Erc1155Owner {
+    const TABLE: &'static str = "erc1155_owners";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("contract_address", ColumnType::Text),
+        ("token_id", ColumnType::Text),
+        ("owner", ColumnType::Text),
+        ("balance", ColumnType::Text),
+    ];
+
+    fn values(self) -> Vec<Value> {
+        vec![
+            address(self.contract_address),
+            text(self.token_id),
+            address(self.owner),
+            text(self.balance),
+        ]
+    }
+}
+
+impl SnapshotRow for TokenContract {
+    const TABLE: &'static str = "token_contracts";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("address", ColumnType::Text),
+        ("name", ColumnType::Text),
+        ("symbol", ColumnType::Text),
+        ("created_block", ColumnType::Int),
+        ("created_tx_index", ColumnType::Int),
+        ("base_uri", ColumnType::Text),
+        ("abi_id", ColumnType::Text),
+        ("deployer", ColumnType::Text),
+        ("deployment_block", ColumnType::Int),
+        ("deployment_tx_index", ColumnType::Int),
+        ("deployment_attempts", ColumnType::Int),
+    ];
+
+    fn values(self) -> Vec<Value> {
+        vec![
+            address(self.address),
+            Value::Text(self.name),
+            Value::Text(self.symbol),
+            Value::Int(Some(self.created_block)),
+            Value::Int(Some(self.created_tx_index)),
+            Value::Text(self.base_uri),
+            bytes(self.abi_id),
+            bytes(self.deployer),
+            Value::Int(self.deployment_block),
+            Value::Int(self.deployment_tx_index),
+            Value::Int(Some(self.deployment_attempts.into())),
+        ]
+    }
+}
+
+impl SnapshotRow for NftMetadata {
+    const TABLE: &'static str = "nft_metadata";
+    const COLUMNS: &'static [(&'static str, ColumnType)] = &[
+        ("uid", ColumnType::Text),
+        ("raw", ColumnType::Text),
+        ("json", ColumnType::Text),
+    ];
+
+    fn values(self) -> Vec<Value> {
+        vec![
+            bytes(Some(self.uid)),
+            Value::Text(self.raw),
+            Value::Text(self.json.map(|json| json.to_string())),
+        ]
+    }
+}
+
+pub(crate) trait TableWriter {
+    fn write(&mut self, rows: Vec<Vec<Value>>) -> Result<()>;
+
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+struct CsvTable(csv::Writer<File>);
+
+impl CsvTable {
+    fn new(file: File, columns: &[(&str, ColumnType)]) -> Result<Self> {
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(columns.iter().map(|(name, _)| *name))?;
+        Ok(Self(writer))
+    }
+}
+
+impl TableWriter for CsvTable {
+    /// Nulls are written as empty fields.
+    fn write(&mut self, rows: Vec<Vec<Value>>) -> Result<()> {
+        for row in rows {
+            self.0
+                .write_record(row.into_iter().map(|value| match value {
+                    Value::Int(value) => value.map(|value| value.to_string()).unwrap_or_default(),
+                    Value::Text(value) => value.unwrap_or_default(),
+                }))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+fn table_writer(
+    format: SnapshotFormat,
+    file: File,
+    table: &str,
+    columns: &'static [(&'static str, ColumnType)],
+) -> Result<Box<dyn TableWriter>> {
+    match format {
+        SnapshotFormat::Csv => Ok(Box::new(CsvTable::new(file, columns)?)),
+        #[cfg(feature = "parquet")]
+        SnapshotFormat::Parquet => Ok(Box::new(parquet_file::ParquetTable::new(
+            file, table, columns,
+        )?)),
+        #[cfg(not(feature = "parquet"))]
+        SnapshotFormat::Parquet => {
+            let _ = (file, table);
+            anyhow::bail!("parquet snapshots require the `parquet` feature")
+        }
+    }
+}
+
+/// Writes all pages returned by `load_page(offset, limit)` to `<dir>/<table>.<ext>`.
+fn export_table<T: SnapshotRow>(
+    dir: &Path,
+    format: SnapshotFormat,
+    mut load_page: impl FnMut(i64, i64) -> QueryResult<Vec<T>>,
+) -> Result<usize> {
+    let path = dir.join(format!("{}.{}", T::TABLE, format.extension()));
+    let file = File::create(&path).with_context(|| format!("create {}", path.display()))?;
+    let mut writer = table_writer(format, file, T::TABLE, T::COLUMNS)?;
+    let mut exported = 0;
+    loop {
+        let page = load_page(exported as i64, PAGE_SIZE)?;
+        let count = page.len();
+        writer.write(page.into_iter().map(SnapshotRow::values).collect())?;
+        exported += count;
+        if (count as i64) < PAGE_SIZE {
+            break;
+        }
+    }
+    writer.finish()?;
+    tracing::info!("exported {exported} rows to {}", path.display());
+    Ok(exported)
+}
+
+fn export_tables(
+    conn: &mut PgConnection,
+    dir: &Path,
+    format: SnapshotFormat,
+    since_block: Option<i64>,
+) -> Result<SnapshotManifest> {
+    let processed_block = blocks::table
+        .select(diesel::dsl::max(blocks::number))
+        .get_result::<Option<i64>>(conn)?
+        .unwrap_or(0);
+    let mut tables = BTreeMap::new();
+
+    let count = export_table::<Nft>(dir, format, |offset, limit| {
+        let mut query = nfts::table
+            .select(Nft::as_select())
+            .order((nfts::contract_address, nfts::token_id))
+            .offset(offset)
+            .limit(limit)
+            .into_boxed();
+        if let Some(block) = since_block {
+            query = query.filter(nfts::last_update_block.gt(block));
+        }
+        query.load(conn)
+    })?;
+    tables.insert(Nft::TABLE.to_string(), count);
+
+    let count = export_table::<Erc1155>(dir, format, |offset, limit| {
+        let mut query = erc1155s::table
+            .select(Erc1155::as_select())
+            .order((erc1155s::contract_address, erc1155s::token_id))
+            .offset(offset)
+            .limit(limit)
+            .into_boxed();
+        if let Some(block) = since_block {
+            query = query.filter(erc1155s::last_update_block.gt(block));
+        }
+        query.load(conn)
+    })?;
+    tables.insert(Erc1155::TABLE.to_string(), count);
+
+    // Balances have no update block, but always change along with their token.
+    let count = export_table::<Erc1155Owner>(dir, format, |offset, limit| {
+        let mut query = erc1155_owners::table
+            .inner_join(
+                erc1155s::table.on(erc1155s::contract_address
+                    .eq(erc1155_owners::contract_address)
+                    .and(erc1155s::token_id.eq(erc1155_owners::token_id))),
+            )
+            .select(Erc1155Owner::as_select())
+            .order((
+                erc1155_owners::contract_address,
+                erc1155_owners::token_id,
+                erc1155_owners::owner,
+            ))
+            .offset(offset)
+            .limit(limit)
+            .into_boxed();
+        if let Some(block) = since_block {
+            query = query.filter(erc1155s::last_update_block.gt(block));
+        }
+        query.load(conn)
+    })?;
+    tables.insert(Erc1155Owner::TABLE.to_string(), count);
+
+    // Contracts have no update block: incremental exports contain those created since.
+    let count = export_table::<TokenContract>(dir, format, |offset, limit| {
+        let mut query = token_contracts::table
+            .select(TokenContract::as_select())
+            .order(token_contracts::address)
+            .offset(offset)
+            .limit(limit)
+            .into_boxed();
+        if let Some(block) = since_block {
+            query = query.filter(token_contracts::created_block.gt(block));
+        }
+        query.load(conn)
+    })?;
+    tables.insert(TokenContract::TABLE.to_string(), count);
+
+    // Metadata is content addressed: incremental exports contain the documents of updated tokens.
+    let count = export_table::<NftMetadata>(dir, format, |offset, limit| {
+        let mut query = nft_metadata::table
+            .select(NftMetadata::as_select())
+            .order(nft_metadata::uid)
+            .offset(offset)
+            .limit(limit)
+            .into_boxed();
+        if let Some(block) = since_block {
+            let erc721_ids = nfts::table
+                .filter(nfts::last_update_block.gt(block))
+                .filter(nfts::metadata_id.is_not_null())
+                .select(nfts::metadata_id.assume_not_null());
+            let erc1155_ids = erc1155s::table
+                .filter(erc1155s::last_update_block.gt(block))
+                .filter(erc1155s::metadata_id.is_not_null())
+                .select(erc1155s::metadata_id.assume_not_null());
+            query = query.filter(
+                nft_metadata::uid
+                    .eq_any(erc721_ids)
+                    .or(nft_metadata::uid.eq_any(erc1155_ids)),
+            );
+        }
+        query.load(conn)
+    })?;
+    tables.insert(NftMetadata::TABLE.to_string(), count);
+
+    Ok(SnapshotManifest {
+        processed_block,
+        since_block,
+        format,
+        tables,
+    })
+}
+
+impl DataStore {
+    /// Exports the token tables to `dir`, from a single read-only transaction so that all
+    /// files (and the processed block) reflect the same point in time. With `since_block`,
+    /// only rows changed after that block are exported (using the `last_update_block` columns).
+    pub fn export_snapshot(
+        &mut self,
+        dir: &Path,
+        format: SnapshotFormat,
+        since_block: Option<i64>,
+    ) -> Result<SnapshotManifest> {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        let manifest = self
+            .get_connection()
+            .build_transaction()
+            .read_only()
+            .repeatable_read()
+            .run(|conn| export_tables(conn, dir, format, since_block))?;
+        let path = dir.join(MANIFEST_FILE);
+        std::fs::write(&path, serde_json::to_vec_pretty(&manifest)?)
+            .with_context(|| format!("write {}", path.display()))?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_table() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.csv", std::process::id()));
+        let mut writer = Box::new(
+            CsvTable::new(
+                File::create(&path).unwrap(),
+                &[("id", ColumnType::Int), ("name", ColumnType::Text)],
+            )
+            .unwrap(),
+        );
+        writer
+            .write(vec![
+                vec![Value::Int(Some(1)), Value::Text(Some("a,b".to_string()))],
+                vec![Value::Int(None), Value::Text(None)],
+            ])
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,name\n1,\"a,b\"\n,\n"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn snapshot_values() {
+        assert_eq!(hex(&[0, 171]), "0x00ab");
+        assert_eq!(address(Address::from(1)), text(format!("0x{:040x}", 1)));
+        assert_eq!(bytes(None), Value::Text(None));
+        assert_eq!("parquet".parse(), Ok(SnapshotFormat::Parquet));
+        assert!("json".parse::<SnapshotFormat>().is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Parquet tables with one (optional) INT64 or UTF8 column per store column,
+//! and a row group per page of exported rows.
+use super::{ColumnType, TableWriter, Value};
+use anyhow::{Context, Result};
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use std::{fs::File, sync::Arc};
+
+pub(super) struct ParquetTable {
+    writer: SerializedFileWriter<File>,
+    columns: &'static [(&'static str, ColumnType)],
+}
+
+impl ParquetTable {
+    pub(super) fn new(
+        file: File,
+        table: &str,
+        columns: &'static [(&'static str, ColumnType)],
+    ) -> Result<Self> {
+        let fields: String = columns
+            .iter()
+            .map(|(name, column_type)| match column_type {
+                ColumnType::Int => format!("OPTIONAL INT64 {name};"),
+                ColumnType::Text => format!("OPTIONAL BYTE_ARRAY {name} (UTF8);"),
+            })
+            .collect();
+        let schema = Arc::new(parse_message_type(&format!(
+            "message {table} {{ {fields} }}"
+        ))?);
+        let writer =
+            SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build()))?;
+        Ok(Self { writer, columns })
+    }
+}
+
+/// Non-null values and the definition level of every row (0 for nulls).
+fn column_values<T>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+    let mut present = vec![];
+    let mut levels = vec![];
+    for value in values {
+        levels.push(value.is_some() as i16);
+        present.extend(value);
+    }
+    (present, levels)
+}
+
+impl TableWriter for ParquetTable {
+    fn write(&mut self, rows: Vec<Vec<Value>>) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for (index, (name, _)) in self.columns.iter().enumerate() {
+            let mut column = row_group
+                .next_column()?
+                .with_context(|| format!("{name} column"))?;
+            let values = rows.iter().map(|row| row[index].clone());
+            match self.columns[index].1 {
+                ColumnType::Int => {
+                    let (values, levels) = column_values(values.map(|value| match value {
+                        Value::Int(value) => value,
+                        Value::Text(_) => unreachable!("{name} is an integer column"),
+                    }));
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                ColumnType::Text => {
+                    let (values, levels) = column_values(values.map(|value| match value {
+                        Value::Text(value) => {
+                            value.map(|value| ByteArray::from(value.into_bytes()))
+                        }
+                        Value::Int(_) => unreachable!("{name} is a text column"),
+                    }));
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+            }
+            column.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::{
+        file::{reader::FileReader, serialized_reader::SerializedFileReader},
+        record::RowAccessor,
+    };
+
+    #[test]
+    fn parquet_table() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.parquet", std::process::id()));
+        let mut writer = Box::new(
+            ParquetTable::new(
+                File::create(&path).unwrap(),
+                "test",
+                &[("id", ColumnType::Int), ("name", ColumnType::Text)],
+            )
+            .unwrap(),
+        );
+        writer
+            .write(vec![
+                vec![Value::Int(Some(1)), Value::Text(Some("a".to_string()))],
+                vec![Value::Int(None), Value::Text(None)],
+            ])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_long(0).unwrap(), 1);
+        assert_eq!(rows[0].get_string(1).unwrap(), "a");
+        assert!(rows[1].get_long(0).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    models::{
+        ApprovalForAll, ApprovalId, ContractOwner, Erc1155, Erc1155Owner, FetchKey, FetchState,
+        Nft, TokenContract, WebhookSubscription,
+    },
+    store::DataStore,
+    update_cache::UpdateCache,
+};
+use bigdecimal::{BigDecimal, Zero};
+use eth::types::{Address, BlockData, NftId, TxDetails};
+use event_retriever::db_reader::models::EventBase;
+use serde_json::Value;
+
+/// Reads and writes of the event handler, implemented by Postgres ([DataStore])
+/// and in memory ([MemoryStore](crate::memory_store::MemoryStore)).
+pub trait Storage: Send {
+    fn load_nft(&mut self, token: &NftId) -> Option<Nft>;
+
+    fn load_approval(&mut self, id: &ApprovalId) -> Option<ApprovalForAll>;
+
+    fn load_erc1155(&mut self, token: &NftId) -> Option<Erc1155>;
+
+    fn load_erc1155_owner(&mut self, token: &NftId, address: Address) -> Option<Erc1155Owner>;
+
+    fn load_contract(&mut self, address: Address) -> Option<TokenContract>;
+
+    fn load_contract_owner(&mut self, address: Address) -> Option<ContractOwner>;
+
+    /// Stored contracts (at most `limit`) whose deployment lookup failed fewer than
+    /// `max_attempts` times.
+    fn load_deployment_retries(&mut self, max_attempts: i32, limit: i64) -> Vec<TokenContract>;
+
+    /// Last block for which data was written.
+    fn get_processed_block(&mut self) -> i64;
+
+    /// Writes all cached updates at once.
+    fn mass_update(&mut self, updates: UpdateCache);
+
+    /// Rewrites the time of already stored blocks, returning the number updated.
+    fn repair_block_times(&mut self, blocks: &[BlockData]) -> usize;
+
+    /// Active webhook subscriptions (matched against every processed range).
+    fn load_webhook_subscriptions(&mut self) -> Vec<WebhookSubscription>;
+
+    /// Known fetch states of (some of) `keys`.
+    fn load_fetch_states(&mut self, keys: &[FetchKey]) -> Vec<FetchState>;
+
+    /// Oldest unpublished change batches (along with their ids).
+    fn load_unpublished_change_batches(&mut self, limit: i64) -> Vec<(i64, Value)>;
+
+    fn mark_change_batches_published(&mut self, ids: &[i64]);
+
+    /// Deletes change batches published more than `retention_secs` ago, returning their number.
+    fn prune_published_change_batches(&mut self, retention_secs: i64) -> usize;
+
+    fn load_or_initialize_nft(&mut self, base: &EventBase, nft_id: &NftId, tx: &TxDetails) -> Nft {
+        match self.load_nft(nft_id) {
+            Some(nft) => nft,
+            None => {
+                tracing::debug!("new nft {:?}", nft_id);
+                Nft::new(base, nft_id, tx)
+            }
+        }
+    }
+
+    fn load_or_initialize_erc1155(
+        &mut self,
+        base: &EventBase,
+        nft_id: &NftId,
+        tx: &TxDetails,
+    ) -> Erc1155 {
+        match self.load_erc1155(nft_id) {
+            Some(nft) => nft,
+            None => {
+                tracing::debug!("new erc1155 {:?}", nft_id);
+                Erc1155::new(base, nft_id, tx)
+            }
+        }
+    }
+
+    fn load_or_initialize_erc1155_owner(
+        &mut self,
+        base: &EventBase,
+        nft_id: &NftId,
+        address: Address,
+    ) -> Erc1155Owner {
+        match self.load_erc1155_owner(nft_id, address) {
+            Some(nft) => nft,
+            None => Erc1155Owner {
+                contract_address: base.contract_address,
+                token_id: nft_id.token_id.into(),
+                owner: address,
+                balance: BigDecimal::zero(),
+            },
+        }
+    }
+
+    fn load_or_initialize_approval(&mut self, approval_id: &ApprovalId) -> ApprovalForAll {
+        match self.load_approval(approval_id) {
+            Some(approval) => approval,
+            None => ApprovalForAll {
+                contract_address: approval_id.contract_address,
+                owner: approval_id.owner,
+                operator: Address::zero(),
+                approved: false,
+                last_update_block: 0,
+                last_update_log_index: 0,
+            },
+        }
+    }
+
+    fn load_or_initialize_contract_owner(&mut self, address: Address) -> ContractOwner {
+        match self.load_contract_owner(address) {
+            Some(owner) => owner,
+            None => ContractOwner::new(address),
+        }
+    }
+}
+
+impl Storage for DataStore {
+    fn load_nft(&mut self, token: &NftId) -> Option<Nft> {
+        DataStore::load_nft(self, token)
+    }
+
+    fn load_approval(&mut self, id: &ApprovalId) -> Option<ApprovalForAll> {
+        DataStore::load_approval(self, id)
+    }
+
+    fn load_erc1155(&mut self, token: &NftId) -> Option<Erc1155> {
+        DataStore::load_erc1155(self, token)
+    }
+
+    fn load_erc1155_owner(&mut self, token: &NftId, address: Address) -> Option<Erc1155Owner> {
+        DataStore::load_erc1155_owner(self, token, address)
+    }
+
+    fn load_contract(&mut self, address: Address) -> Option<TokenContract> {
+        DataStore::load_contract(self, address)
+    }
+
+    fn load_contract_owner(&mut self, address: Address) -> Option<ContractOwner> {
+        DataStore::load_contract_owner(self, address)
+    }
+
+    fn load_deployment_retries(&mut self, max_attempts: i32, limit: i64) -> Vec<TokenContract> {
+        DataStore::load_deployment_retries(self, max_attempts, limit)
+    }
+
+    fn get_processed_block(&mut self) -> i64 {
+        DataStore::get_processed_block(self)
+    }
+
+    fn mass_update(&mut self, updates: UpdateCache) {
+        DataStore::mass_update(self, updates)
+    }
+
+    fn repair_block_times(&mut self, blocks: &[BlockData]) -> usize {
+        DataStore::repair_block_times(self, blocks)
+    }
+
+    fn load_webhook_subscriptions(&mut self) -> Vec<WebhookSubscription> {
+        DataStore::load_webhook_subscriptions(self)
+    }
+
+    fn load_fetch_states(&mut self, keys: &[FetchKey]) -> Vec<FetchState> {
+        DataStore::load_fetch_states(self, keys)
+    }
+
+    fn load_unpublished_change_batches(&mut self, limit: i64) -> Vec<(i64, Value)> {
+        DataStore::load_unpublished_change_batches(self, limit)
+    }
+
+    fn mark_change_batches_published(&mut self, ids: &[i64]) {
+        DataStore::mark_change_batches_published(self, ids)
+    }
+
+    fn prune_published_change_batches(&mut self, retention_secs: i64) -> usize {
+        DataStore::prune_published_change_batches(self, retention_secs)
+    }
+}
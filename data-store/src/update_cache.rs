@@ -1,8 +1,9 @@
 use crate::models::{
-    ApprovalForAll as StoreApproval, ApprovalId, Erc1155, Erc1155Owner, Nft, TokenContract,
-    Transaction,
+    ApprovalForAll as StoreApproval, ApprovalId, ContractOwner, Erc1155, Erc1155Owner,
+    NewWebhookDelivery, Nft, Sale, TokenContract, TokenUriObservation, Transaction,
 };
 use eth::types::{Address, BlockData, Message, NftId};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Debug)]
@@ -13,8 +14,20 @@ pub struct UpdateCache {
     pub multi_token_owners: HashMap<(NftId, Address, Address), Erc1155Owner>,
     pub approval_for_alls: HashMap<ApprovalId, StoreApproval>,
     pub contracts: HashMap<Address, TokenContract>,
+    /// Already stored contracts whose deployment lookup was retried.
+    pub deployment_retries: Vec<TokenContract>,
+    pub contract_owners: HashMap<Address, ContractOwner>,
+    pub sales: Vec<Sale>,
+    /// Token uris emitted or read from the node (recorded even when unchanged).
+    pub uri_observations: Vec<TokenUriObservation>,
     pub transactions: HashSet<Transaction>,
     pub blocks: HashSet<BlockData>,
+    /// Webhook deliveries matched against these updates (ordered by block).
+    pub webhook_deliveries: Vec<NewWebhookDelivery>,
+    /// Metadata requests (see [UpdateCache::build_messages]) to publish once written.
+    pub outbox: Vec<Message>,
+    /// State changes of these updates (JSON), published by the change feed once written.
+    pub change_batch: Option<Value>,
 }
 
 impl UpdateCache {
@@ -29,15 +42,21 @@ impl UpdateCache {
             && self.multi_token_owners.is_empty()
             && self.approval_for_alls.is_empty()
             && self.contracts.is_empty()
+            && self.deployment_retries.is_empty()
+            && self.contract_owners.is_empty()
+            && self.sales.is_empty()
+            && self.uri_observations.is_empty()
             && self.transactions.is_empty()
             && self.blocks.is_empty()
+            && self.webhook_deliveries.is_empty()
+            && self.outbox.is_empty()
+            && self.change_batch.is_none()
     }
 
     pub fn build_messages(&self) -> Vec<Message> {
-        // TODO - we need to be careful how and when we decide to try and fetch stuff.
-        //  1. For tokens it could be when metadata_id is null and token_uri is not null.
-        //  2. For contracts when abi_id is null.
-        //  However, in both cases, we want to avoid retrying the same contract too many times.
+        // Tokens are requested when metadata_id is null and token_uri is not null,
+        // contracts when abi_id is null. Requests that aren't due according to their
+        // fetch state are dropped by the event handler (see FetchPolicy::due_messages).
         let erc721s: Vec<_> = self
             .nfts
             .iter()
@@ -0,0 +1,91 @@
+//! Metadata fetched per token uri: tokens sharing a uri (i.e. unrevealed placeholders or
+//! ERC1155 tokens with shared metadata) are linked to the cached metadata until it expires.
+use crate::{
+    models::UriCacheEntry,
+    schema::*,
+    store::{handle_insert_result, handle_query_result, DataStore},
+};
+use diesel::{
+    internal::derives::multiconnection::chrono::{Duration, Utc},
+    prelude::*,
+    upsert::excluded,
+};
+use std::collections::HashMap;
+
+/// Key of `uri` in the cache.
+pub fn uri_hash(uri: &str) -> Vec<u8> {
+    md5::compute(uri.as_bytes()).0.to_vec()
+}
+
+/// Metadata fetched from (or revalidated at) `uri`, cached for `ttl_secs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedUri {
+    pub uri: String,
+    pub metadata_id: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub ttl_secs: i64,
+}
+
+impl UriCacheEntry {
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at > Utc::now().naive_utc()
+    }
+}
+
+impl DataStore {
+    /// Cache entries of `uris` (by uri), expired or not.
+    pub fn load_uri_cache(&mut self, uris: &[&str]) -> HashMap<String, UriCacheEntry> {
+        if uris.is_empty() {
+            return HashMap::new();
+        }
+        let hashes: Vec<_> = uris.iter().map(|uri| uri_hash(uri)).collect();
+        let result = uri_cache::table
+            .filter(uri_cache::uri_hash.eq_any(hashes))
+            .select(UriCacheEntry::as_select())
+            .load(&mut self.get_connection());
+        handle_query_result(result)
+            .into_iter()
+            .map(|entry| (entry.uri.clone(), entry))
+            .collect()
+    }
+
+    pub fn save_uri_cache(&mut self, entries: &[CachedUri]) {
+        if entries.is_empty() {
+            return;
+        }
+        let now = Utc::now().naive_utc();
+        // A row can't be upserted twice in one statement.
+        let entries: HashMap<_, _> = entries
+            .iter()
+            .map(|entry| (uri_hash(&entry.uri), entry))
+            .collect();
+        let rows: Vec<_> = entries
+            .iter()
+            .map(|(hash, entry)| {
+                (
+                    uri_cache::uri_hash.eq(hash),
+                    uri_cache::uri.eq(&entry.uri),
+                    uri_cache::metadata_id.eq(&entry.metadata_id),
+                    uri_cache::etag.eq(&entry.etag),
+                    uri_cache::last_modified.eq(&entry.last_modified),
+                    uri_cache::fetched_at.eq(now),
+                    uri_cache::expires_at.eq(now + Duration::seconds(entry.ttl_secs)),
+                )
+            })
+            .collect();
+        let result = diesel::insert_into(uri_cache::table)
+            .values(rows)
+            .on_conflict(uri_cache::uri_hash)
+            .do_update()
+            .set((
+                uri_cache::metadata_id.eq(excluded(uri_cache::metadata_id)),
+                uri_cache::etag.eq(excluded(uri_cache::etag)),
+                uri_cache::last_modified.eq(excluded(uri_cache::last_modified)),
+                uri_cache::fetched_at.eq(excluded(uri_cache::fetched_at)),
+                uri_cache::expires_at.eq(excluded(uri_cache::expires_at)),
+            ))
+            .execute(&mut self.get_connection());
+        handle_insert_result(result, entries.len(), "save_uri_cache".into())
+    }
+}
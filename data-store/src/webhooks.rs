@@ -0,0 +1,143 @@
+//! Webhook subscriptions and their delivery log.
+use crate::{
+    models::{
+        NewWebhookDelivery, WebhookDelivery, WebhookKind, WebhookOutcome, WebhookSubscription,
+    },
+    schema::*,
+    store::{handle_insert_result, handle_query_result, Connexion, DataStore},
+};
+use diesel::{
+    dsl::{now, IntervalDsl},
+    prelude::*,
+};
+use eth::types::Address;
+
+/// Claimed deliveries aren't due again for this long (in seconds), which outlasts a page of
+/// attempts. Deliveries claimed by a worker that crashed are retried once it expires.
+const DELIVERY_LEASE_SECS: i64 = 30 * 60;
+
+impl DataStore {
+    pub fn add_webhook_subscription(
+        &mut self,
+        url: &str,
+        secret: &str,
+        kind: WebhookKind,
+        address: Address,
+    ) -> i64 {
+        let result = diesel::insert_into(webhook_subscriptions::table)
+            .values((
+                webhook_subscriptions::url.eq(url),
+                webhook_subscriptions::secret.eq(secret),
+                webhook_subscriptions::kind.eq(kind.as_str()),
+                webhook_subscriptions::address.eq::<Vec<u8>>(address.into()),
+            ))
+            .returning(webhook_subscriptions::id)
+            .get_result(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub fn load_webhook_subscriptions(&mut self) -> Vec<WebhookSubscription> {
+        let result = webhook_subscriptions::table
+            .filter(webhook_subscriptions::active.eq(true))
+            .select(WebhookSubscription::as_select())
+            .load(&mut self.get_connection());
+        handle_query_result(result)
+    }
+
+    pub(crate) fn save_webhook_deliveries(
+        conn: &mut Connexion,
+        deliveries: Vec<NewWebhookDelivery>,
+    ) {
+        let expected_inserts = deliveries.len();
+        tracing::info!("saving {} webhook deliveries", expected_inserts);
+        let result = diesel::insert_into(webhook_deliveries::table)
+            .values(deliveries)
+            .execute(conn);
+        handle_insert_result(result, expected_inserts, "save_webhook_deliveries".into())
+    }
+
+    /// Claims the pending deliveries whose next attempt is due, oldest first. They are locked
+    /// (skipping those claimed by concurrent workers) and leased, so that no other worker
+    /// attempts them before their attempt is recorded (or the lease expires).
+    pub fn due_webhook_deliveries(
+        &mut self,
+        limit: i64,
+    ) -> Vec<(WebhookDelivery, WebhookSubscription)> {
+        let mut conn = self.get_connection();
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let ids: Vec<i64> = webhook_deliveries::table
+                .filter(webhook_deliveries::status.eq("pending"))
+                .filter(webhook_deliveries::next_attempt_at.le(now))
+                .order(webhook_deliveries::id)
+                .limit(limit)
+                .select(webhook_deliveries::id)
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+            diesel::update(webhook_deliveries::table)
+                .filter(webhook_deliveries::id.eq_any(&ids))
+                .set(webhook_deliveries::next_attempt_at.eq(now + DELIVERY_LEASE_SECS.seconds()))
+                .execute(conn)?;
+            webhook_deliveries::table
+                .inner_join(webhook_subscriptions::table)
+                .filter(webhook_deliveries::id.eq_any(&ids))
+                .order(webhook_deliveries::id)
+                .select((
+                    WebhookDelivery::as_select(),
+                    WebhookSubscription::as_select(),
+                ))
+                .load(conn)
+        });
+        handle_query_result(result)
+    }
+
+    pub fn load_webhook_delivery(&mut self, id: i64) -> Option<WebhookDelivery> {
+        let result = webhook_deliveries::table
+            .find(id)
+            .select(WebhookDelivery::as_select())
+            .first(&mut self.get_connection())
+            .optional();
+        handle_query_result(result)
+    }
+
+    pub fn record_webhook_attempt(&mut self, id: i64, outcome: WebhookOutcome) {
+        let target = webhook_deliveries::table.find(id);
+        let attempts = webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1);
+        let conn = &mut self.get_connection();
+        let result = match outcome {
+            WebhookOutcome::Delivered { response_status } => diesel::update(target)
+                .set((
+                    attempts,
+                    webhook_deliveries::status.eq("delivered"),
+                    webhook_deliveries::response_status.eq(Some(response_status)),
+                    webhook_deliveries::last_error.eq(None::<String>),
+                    webhook_deliveries::delivered_at.eq(now.nullable()),
+                ))
+                .execute(conn),
+            WebhookOutcome::Retry {
+                response_status,
+                error,
+                retry_in_secs,
+            } => diesel::update(target)
+                .set((
+                    attempts,
+                    webhook_deliveries::response_status.eq(response_status),
+                    webhook_deliveries::last_error.eq(Some(error)),
+                    webhook_deliveries::next_attempt_at.eq(now + retry_in_secs.seconds()),
+                ))
+                .execute(conn),
+            WebhookOutcome::Failed {
+                response_status,
+                error,
+            } => diesel::update(target)
+                .set((
+                    attempts,
+                    webhook_deliveries::status.eq("failed"),
+                    webhook_deliveries::response_status.eq(response_status),
+                    webhook_deliveries::last_error.eq(Some(error)),
+                ))
+                .execute(conn),
+        };
+        handle_insert_result(result, 1, format!("record_webhook_attempt: {id}"))
+    }
+}
@@ -0,0 +1,58 @@
+//! Named in-process channels of [Message]s, through which the event processor and the
+//! metadata-retriever can be run in one process (e.g. embedded or in tests) without a broker.
+use crate::types::Message;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+struct Channel {
+    sender: UnboundedSender<Message>,
+    /// Until taken by the consumer.
+    receiver: Option<UnboundedReceiver<Message>>,
+}
+
+fn channels() -> &'static Mutex<HashMap<String, Channel>> {
+    static CHANNELS: OnceLock<Mutex<HashMap<String, Channel>>> = OnceLock::new();
+    CHANNELS.get_or_init(Default::default)
+}
+
+fn with_channel<T>(name: &str, f: impl FnOnce(&mut Channel) -> T) -> T {
+    let mut channels = channels().lock().expect("channels lock");
+    let channel = channels.entry(name.to_string()).or_insert_with(|| {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Channel {
+            sender,
+            receiver: Some(receiver),
+        }
+    });
+    f(channel)
+}
+
+/// Sender to the channel `name` (created on first use, by either side).
+pub fn sender(name: &str) -> UnboundedSender<Message> {
+    with_channel(name, |channel| channel.sender.clone())
+}
+
+/// Receiver of the channel `name`, which has a single consumer (None once taken).
+pub fn take_receiver(name: &str) -> Option<UnboundedReceiver<Message>> {
+    with_channel(name, |channel| channel.receiver.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Address;
+
+    #[tokio::test]
+    async fn named_channel() {
+        let message = Message::Contract {
+            address: Address::from(1),
+        };
+        sender("named_channel").send(message.clone()).unwrap();
+        let mut receiver = take_receiver("named_channel").unwrap();
+        assert!(take_receiver("named_channel").is_none());
+        assert_eq!(receiver.recv().await, Some(message));
+    }
+}
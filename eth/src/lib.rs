@@ -1,2 +1,3 @@
+pub mod channel;
 pub mod rpc;
 pub mod types;
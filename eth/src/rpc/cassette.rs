@@ -0,0 +1,446 @@
+use super::EthNodeReading;
+use crate::types::{
+    Address, BlockData, BlockTag, Bytes32, ContractDeployment, ContractDetails, EventLog, NftId,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// JSON file of recorded responses, keyed by request.
+pub struct CassetteFile {
+    path: PathBuf,
+    entries: Mutex<BTreeMap<String, Value>>,
+}
+
+impl CassetteFile {
+    /// Loads the entries recorded at `path` (if any).
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("read cassette {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("parse cassette {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T> {
+        let entries = self.entries.lock().expect("failed to lock mutex");
+        let value = entries
+            .get(key)
+            .with_context(|| format!("{key} not recorded in {}", self.path.display()))?;
+        serde_json::from_value(value.clone()).with_context(|| format!("invalid recording {key}"))
+    }
+
+    /// Adds (or overwrites) entries and rewrites the file.
+    pub fn record<T: Serialize>(
+        &self,
+        entries: impl IntoIterator<Item = (String, T)>,
+    ) -> Result<()> {
+        let mut recorded = self.entries.lock().expect("failed to lock mutex");
+        for (key, value) in entries {
+            recorded.insert(key, serde_json::to_value(value)?);
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&*recorded)?)
+            .with_context(|| format!("write cassette {}", self.path.display()))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CassetteMode {
+    /// Forward requests to a live source and save its responses.
+    Record,
+    /// Serve saved responses only (i.e. no network access).
+    Replay,
+}
+
+impl CassetteMode {
+    /// Record when `RECORD_CASSETTES` is set, otherwise replay.
+    pub fn from_env() -> Self {
+        match std::env::var("RECORD_CASSETTES") {
+            Ok(_) => Self::Record,
+            Err(_) => Self::Replay,
+        }
+    }
+}
+
+/// EthNodeReading backed by a cassette file, for deterministic (offline) tests.
+/// Responses are recorded per block, token and contract, so that replay
+/// doesn't depend on the order (or grouping) in which they are requested.
+pub struct Cassette {
+    file: CassetteFile,
+    /// Live node (record mode only).
+    node: Option<Arc<dyn EthNodeReading>>,
+}
+
+fn tag_key(block: BlockTag) -> String {
+    match block {
+        BlockTag::Latest => "latest".to_string(),
+        BlockTag::Number(number) => number.to_string(),
+    }
+}
+
+impl Cassette {
+    /// Replays from `path` when mode is Replay. Otherwise, records responses of `node`.
+    pub fn new<F>(mode: CassetteMode, path: impl Into<PathBuf>, node: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<Arc<dyn EthNodeReading>>,
+    {
+        match mode {
+            CassetteMode::Record => Self::record(path, node()?),
+            CassetteMode::Replay => Self::replay(path),
+        }
+    }
+
+    pub fn record(path: impl Into<PathBuf>, node: Arc<dyn EthNodeReading>) -> Result<Self> {
+        Ok(Self {
+            file: CassetteFile::load(path)?,
+            node: Some(node),
+        })
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        anyhow::ensure!(path.exists(), "missing cassette {}", path.display());
+        Ok(Self {
+            file: CassetteFile::load(path)?,
+            node: None,
+        })
+    }
+
+    /// Records `values` under `key(id)` for every id requested, including those without a response.
+    fn record_each<K, V: Serialize>(
+        &self,
+        ids: &[K],
+        key: impl Fn(&K) -> String,
+        values: &HashMap<K, V>,
+    ) where
+        K: Eq + std::hash::Hash,
+    {
+        self.file
+            .record(ids.iter().map(|id| (key(id), values.get(id))))
+            .expect("failed to record cassette");
+    }
+
+    /// Replays the responses recorded for each id, omitting those recorded without one.
+    fn replay_each<K, V: DeserializeOwned>(
+        &self,
+        ids: &[K],
+        key: impl Fn(&K) -> String,
+    ) -> HashMap<K, V>
+    where
+        K: Eq + std::hash::Hash + Copy,
+    {
+        ids.iter()
+            .filter_map(|id| {
+                self.file
+                    .get::<Option<V>>(&key(id))
+                    .expect("missing cassette entry: re-record with RECORD_CASSETTES=1")
+                    .map(|value| (*id, value))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl EthNodeReading for Cassette {
+    async fn get_contract_details(
+        &self,
+        addresses: &[Address],
+        block: BlockTag,
+    ) -> HashMap<Address, ContractDetails> {
+        let key = |address: &Address| format!("contract_details/{}/{address}", tag_key(block));
+        match &self.node {
+            Some(node) => {
+                let details = node.get_contract_details(addresses, block).await;
+                self.record_each(addresses, key, &details);
+                details
+            }
+            None => self.replay_each(addresses, key),
+        }
+    }
+
+    async fn get_uris(
+        &self,
+        token_ids: &[NftId],
+        block: BlockTag,
+    ) -> HashMap<NftId, Option<String>> {
+        let key = |token: &NftId| format!("uri/{}/{token}", tag_key(block));
+        match &self.node {
+            Some(node) => {
+                let uris = node.get_uris(token_ids, block).await;
+                self.record_each(token_ids, key, &uris);
+                uris
+            }
+            None => token_ids
+                .iter()
+                .map(|token| {
+                    let uri = self
+                        .file
+                        .get::<Option<String>>(&key(token))
+                        .expect("missing cassette entry: re-record with RECORD_CASSETTES=1");
+                    (*token, uri)
+                })
+                .collect(),
+        }
+    }
+
+    async fn get_blocks_for_range(&self, start: u64, end: u64) -> Result<HashMap<u64, BlockData>> {
+        let key = |block: &u64| format!("block/{block}");
+        let numbers: Vec<_> = (start..end).collect();
+        match &self.node {
+            Some(node) => {
+                let blocks = node.get_blocks_for_range(start, end).await?;
+                self.file
+                    .record(numbers.iter().map(|n| (key(n), blocks.get(n))))?;
+                Ok(blocks)
+            }
+            None => {
+                let mut blocks = HashMap::new();
+                for number in numbers {
+                    if let Some(block) = self.file.get::<Option<BlockData>>(&key(&number))? {
+                        blocks.insert(number, block);
+                    }
+                }
+                Ok(blocks)
+            }
+        }
+    }
+
+    async fn get_logs(&self, start: u64, end: u64, topics: &[Bytes32]) -> Result<Vec<EventLog>> {
+        let mut sorted_topics = topics.to_vec();
+        sorted_topics.sort();
+        let key = format!(
+            "logs/{start}-{end}/{}",
+            sorted_topics
+                .iter()
+                .map(|topic| topic.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        match &self.node {
+            Some(node) => {
+                let logs = node.get_logs(start, end, topics).await?;
+                self.file.record([(key, &logs)])?;
+                Ok(logs)
+            }
+            None => self.file.get(&key),
+        }
+    }
+
+    async fn get_contract_deployments(
+        &self,
+        addresses: &[Address],
+        known_block: u64,
+    ) -> HashMap<Address, ContractDeployment> {
+        // A contract's deployment doesn't depend on where the search started.
+        let key = |address: &Address| format!("deployment/{address}");
+        match &self.node {
+            Some(node) => {
+                let deployments = node.get_contract_deployments(addresses, known_block).await;
+                self.record_each(addresses, key, &deployments);
+                deployments
+            }
+            None => self.replay_each(addresses, key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TxDetails, U256};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Node with fixed responses, counting the requests it serves.
+    #[derive(Default)]
+    struct StaticNode {
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EthNodeReading for StaticNode {
+        async fn get_contract_details(
+            &self,
+            addresses: &[Address],
+            block: BlockTag,
+        ) -> HashMap<Address, ContractDetails> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            addresses
+                .iter()
+                .filter(|address| **address != Address::zero())
+                .map(|address| {
+                    let details = ContractDetails {
+                        address: *address,
+                        name: Some(format!("{block:?}")),
+                        symbol: None,
+                    };
+                    (*address, details)
+                })
+                .collect()
+        }
+
+        async fn get_uris(
+            &self,
+            token_ids: &[NftId],
+            _: BlockTag,
+        ) -> HashMap<NftId, Option<String>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            token_ids
+                .iter()
+                .map(|id| (*id, (id.token_id != U256::from(0)).then(|| id.to_string())))
+                .collect()
+        }
+
+        async fn get_blocks_for_range(
+            &self,
+            start: u64,
+            end: u64,
+        ) -> Result<HashMap<u64, BlockData>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok((start..end)
+                .map(|number| {
+                    let tx = TxDetails {
+                        hash: Bytes32::from(number),
+                        from: Address::from(number),
+                        value: Some(U256::from(number)),
+                        ..Default::default()
+                    };
+                    let block = BlockData {
+                        number,
+                        time: number * 12,
+                        transactions: HashMap::from([(0, tx)]),
+                    };
+                    (number, block)
+                })
+                .collect())
+        }
+
+        async fn get_logs(&self, start: u64, _: u64, topics: &[Bytes32]) -> Result<Vec<EventLog>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![EventLog {
+                address: Address::from(1),
+                topics: topics.to_vec(),
+                data: vec![1, 2, 3],
+                block_number: start,
+                transaction_index: 0,
+                log_index: 0,
+            }])
+        }
+
+        async fn get_contract_deployments(
+            &self,
+            addresses: &[Address],
+            known_block: u64,
+        ) -> HashMap<Address, ContractDeployment> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            addresses
+                .iter()
+                .map(|address| {
+                    let deployment = ContractDeployment {
+                        address: *address,
+                        block: known_block,
+                        tx_index: None,
+                        deployer: Some(Address::from(3)),
+                    };
+                    (*address, deployment)
+                })
+                .collect()
+        }
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let path = cassette_path("record_and_replay");
+        let node = Arc::new(StaticNode::default());
+        let recorder = Cassette::record(&path, node.clone()).unwrap();
+
+        let addresses = [Address::from(1), Address::zero()];
+        let tokens = [
+            NftId {
+                address: Address::from(1),
+                token_id: U256::from(0),
+            },
+            NftId {
+                address: Address::from(1),
+                token_id: U256::from(1),
+            },
+        ];
+        let topics = [Bytes32::from(2), Bytes32::from(1)];
+        let block = BlockTag::Number(10);
+
+        let (uris, details) = recorder
+            .get_uris_and_contract_details(&tokens, &addresses, block)
+            .await;
+        let blocks = recorder.get_blocks_for_range(10, 13).await.unwrap();
+        let logs = recorder.get_logs(10, 13, &topics).await.unwrap();
+        let deployments = recorder.get_contract_deployments(&addresses, 10).await;
+        assert_eq!(node.requests.load(Ordering::SeqCst), 5);
+
+        let player = Cassette::replay(&path).unwrap();
+        // Request order and grouping need not match the recording.
+        let reversed: Vec<_> = tokens.iter().rev().copied().collect();
+        assert_eq!(player.get_uris(&reversed, block).await, uris);
+        assert_eq!(
+            player.get_contract_details(&addresses, block).await,
+            details
+        );
+        assert_eq!(player.get_blocks_for_range(10, 13).await.unwrap(), blocks);
+        assert_eq!(
+            player.get_blocks_for_range(11, 12).await.unwrap()[&11],
+            blocks[&11]
+        );
+        let reversed: Vec<_> = topics.iter().rev().copied().collect();
+        assert_eq!(player.get_logs(10, 13, &reversed).await.unwrap(), logs);
+        assert_eq!(
+            player.get_contract_deployments(&addresses, 100).await,
+            deployments
+        );
+        // Contracts without details are replayed as such.
+        assert!(!details.contains_key(&Address::zero()));
+        // Nothing further was requested from the node.
+        assert_eq!(node.requests.load(Ordering::SeqCst), 5);
+
+        // Unrecorded requests are errors rather than silently empty.
+        assert!(player.get_blocks_for_range(12, 14).await.is_err());
+        assert!(player.get_logs(0, 1, &topics).await.is_err());
+        assert!(Cassette::replay(cassette_path("missing")).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "missing cassette entry")]
+    async fn replay_unrecorded_contract() {
+        let path = cassette_path("replay_unrecorded_contract");
+        let recorder = Cassette::record(&path, Arc::new(StaticNode::default())).unwrap();
+        recorder
+            .get_contract_details(&[Address::from(1)], BlockTag::Latest)
+            .await;
+        let player = Cassette::replay(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        player
+            .get_contract_details(&[Address::from(1)], BlockTag::Number(1))
+            .await;
+    }
+}
@@ -1,23 +1,45 @@
-use crate::types::{Address, BlockData, ContractDetails, NftId, TxDetails};
+use crate::types::{
+    Address, BlockData, BlockTag, Bytes32, ContractDeployment, ContractDetails, EventLog, NftId,
+    TxDetails,
+};
 use anyhow::{anyhow, Error, Result};
 use async_trait::async_trait;
 use ethers::{
+    abi::{decode, ParamType},
     middleware::Middleware,
     prelude::abigen,
-    providers::{Http, Provider},
+    providers::{Http, JsonRpcClient, Provider, RpcError},
+    types::{transaction::eip2718::TypedTransaction, Filter, TransactionRequest},
     utils::hex,
 };
-use futures::future::{join, join_all};
-use std::{collections::HashMap, fmt::Debug, sync::Arc, time::Duration};
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use super::EthNodeReading;
+use super::{
+    find_deployment_block, is_unsupported_method,
+    multicall::{
+        decode_try_aggregate, encode_try_aggregate, multicall3_address, Call,
+        MULTICALL3_DEPLOYMENT_BLOCK,
+    },
+    EthNodeReading,
+};
 
 abigen!(ERC721Metadata, "./src/abis/ERC721Metadata.json");
 
-fn erc721_contract_at_address(
+fn erc721_contract_at_address<P: JsonRpcClient>(
     address: Address,
-    provider: Arc<Provider<Http>>,
-) -> ERC721Metadata<Provider<Http>> {
+    provider: Arc<Provider<P>>,
+) -> ERC721Metadata<Provider<P>> {
     ERC721Metadata::new(ethers::types::Address::from(address.0 .0), provider)
 }
 
@@ -38,7 +60,7 @@ trait RetryGet<T: Send> {
         }
         true
     }
-    // so this shouldn't only retry from one provider, but from multiple, esp. at the top of the chain
+    // Failover between providers is left to the transport (see `pool::PoolTransport`).
     async fn retry_get(&self, max_retries: u32, wait_secs: u64) -> Result<T> {
         let mut retries = 0;
         loop {
@@ -63,20 +85,20 @@ trait RetryGet<T: Send> {
     }
 }
 
-struct GetBlock {
-    provider: Arc<Provider<Http>>,
+struct GetBlock<P> {
+    provider: Arc<Provider<P>>,
     block: u64,
 }
 
 #[async_trait::async_trait]
-impl RetryGet<Option<BlockData>> for GetBlock {
+impl<P: JsonRpcClient + 'static> RetryGet<Option<BlockData>> for GetBlock<P> {
     async fn try_get(&self) -> Result<Option<BlockData>> {
         let res: Option<ethers::types::Block<ethers::types::Transaction>> =
             self.provider.get_block_with_txs(self.block).await?;
 
         Ok(match res {
             Some(ethers_block) => {
-                let transactions = ethers_block
+                let transactions: HashMap<u64, TxDetails> = ethers_block
                     .transactions
                     .into_iter()
                     .map(|tx| {
@@ -98,36 +120,92 @@ impl RetryGet<Option<BlockData>> for GetBlock {
     }
 }
 
-struct GetBlockReceipts {
-    provider: Arc<Provider<Http>>,
+struct GetBlockReceipts<P> {
+    provider: Arc<Provider<P>>,
     block: u64,
+    /// Set once the node rejected `eth_getBlockReceipts`.
+    unsupported: Arc<AtomicBool>,
+}
+
+impl<P: JsonRpcClient + 'static> GetBlockReceipts<P> {
+    /// One `eth_getTransactionReceipt` call per transaction of the block.
+    async fn transaction_receipts(&self) -> Result<Vec<ethers::types::TransactionReceipt>> {
+        let hashes = match self.provider.get_block(self.block).await? {
+            Some(block) => block.transactions,
+            None => return Ok(vec![]),
+        };
+        let receipts = join_all(
+            hashes
+                .into_iter()
+                .map(|hash| self.provider.get_transaction_receipt(hash)),
+        )
+        .await;
+        let receipts: Result<Vec<_>, _> = receipts.into_iter().collect();
+        Ok(receipts?.into_iter().flatten().collect())
+    }
 }
 
 #[async_trait::async_trait]
-impl RetryGet<HashMap<u64, TxDetails>> for GetBlockReceipts {
-    async fn try_get(&self) -> Result<HashMap<u64, TxDetails>> {
-        let block = self.block;
-        let receipts = self.provider.get_block_receipts(block).await?;
-        Ok(receipts
-            .into_iter()
-            .map(|r| (r.transaction_index.0[0], r.into()))
-            .collect())
+impl<P: JsonRpcClient + 'static> RetryGet<Vec<ethers::types::TransactionReceipt>>
+    for GetBlockReceipts<P>
+{
+    async fn try_get(&self) -> Result<Vec<ethers::types::TransactionReceipt>> {
+        if self.unsupported.load(Ordering::Relaxed) {
+            return self.transaction_receipts().await;
+        }
+        match self.provider.get_block_receipts(self.block).await {
+            Ok(receipts) => Ok(receipts),
+            Err(err)
+                if err
+                    .as_error_response()
+                    .is_some_and(|err| is_unsupported_method(err.code, &err.message)) =>
+            {
+                tracing::warn!(
+                    "eth_getBlockReceipts unsupported ({err}), reading transaction receipts"
+                );
+                self.unsupported.store(true, Ordering::Relaxed);
+                self.transaction_receipts().await
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+struct GetCodeExists<P> {
+    provider: Arc<Provider<P>>,
+    address: Address,
+    block: u64,
+}
+
+#[async_trait::async_trait]
+impl<P: JsonRpcClient + 'static> RetryGet<bool> for GetCodeExists<P> {
+    async fn try_get(&self) -> Result<bool> {
+        let code = self
+            .provider
+            .get_code(
+                ethers::types::Address::from(self.address.0 .0),
+                Some(self.block.into()),
+            )
+            .await?;
+        Ok(!code.is_empty())
     }
 }
 
-struct GetErc721Uri {
-    provider: Arc<Provider<Http>>,
+struct GetErc721Uri<P> {
+    provider: Arc<Provider<P>>,
     token: NftId,
+    block: BlockTag,
 }
 
 #[async_trait::async_trait]
-impl RetryGet<String> for GetErc721Uri {
+impl<P: JsonRpcClient + 'static> RetryGet<String> for GetErc721Uri<P> {
     async fn try_get(&self) -> Result<String> {
         let contract = erc721_contract_at_address(self.token.address, self.provider.clone());
         contract
             .token_uri(ethers::types::U256::from_big_endian(
                 &self.token.token_id.0.to_be_bytes(),
             ))
+            .block(self.block)
             .call()
             .await
             // Remove Null Bytes: Postgres can't handle them.
@@ -136,17 +214,19 @@ impl RetryGet<String> for GetErc721Uri {
     }
 }
 
-struct GetName {
-    provider: Arc<Provider<Http>>,
+struct GetName<P> {
+    provider: Arc<Provider<P>>,
     address: Address,
+    block: BlockTag,
 }
 
 #[async_trait::async_trait]
-impl RetryGet<String> for GetName {
+impl<P: JsonRpcClient + 'static> RetryGet<String> for GetName<P> {
     async fn try_get(&self) -> Result<String> {
         let contract = erc721_contract_at_address(self.address, self.provider.clone());
         contract
             .name()
+            .block(self.block)
             .call()
             .await
             // Remove Null Bytes: Postgres can't handle them.
@@ -155,17 +235,19 @@ impl RetryGet<String> for GetName {
     }
 }
 
-struct GetSymbol {
-    provider: Arc<Provider<Http>>,
+struct GetSymbol<P> {
+    provider: Arc<Provider<P>>,
     address: Address,
+    block: BlockTag,
 }
 
 #[async_trait::async_trait]
-impl RetryGet<String> for GetSymbol {
+impl<P: JsonRpcClient + 'static> RetryGet<String> for GetSymbol<P> {
     async fn try_get(&self) -> Result<String> {
         let contract = erc721_contract_at_address(self.address, self.provider.clone());
         contract
             .symbol()
+            .block(self.block)
             .call()
             .await
             // Remove Null Bytes: Postgres can't handle them.
@@ -174,64 +256,90 @@ impl RetryGet<String> for GetSymbol {
     }
 }
 
-pub struct Client {
-    provider: Arc<Provider<Http>>,
+pub struct Client<P = Http> {
+    provider: Arc<Provider<P>>,
+    block_receipts_unsupported: Arc<AtomicBool>,
+    /// Attempts per request (see [RetryGet::retry_get]).
+    attempts: u32,
+    /// Read calls per Multicall3 aggregate (0 sends every call on its own).
+    multicall_chunk_size: usize,
 }
 
 #[async_trait]
-impl EthNodeReading for Client {
+impl<P: JsonRpcClient + 'static> EthNodeReading for Client<P> {
     async fn get_contract_details(
         &self,
         addresses: &[Address],
+        block: BlockTag,
     ) -> HashMap<Address, ContractDetails> {
         tracing::info!("preparing {} contract details requests", addresses.len());
-        let name_futures = addresses.iter().cloned().map(|a| self.get_name(a));
-        let symbol_futures = addresses.iter().cloned().map(|a| self.get_symbol(a));
-
-        let (names, symbols) = join(join_all(name_futures), join_all(symbol_futures)).await;
+        let calls: Vec<_> = addresses
+            .iter()
+            .flat_map(|&address| {
+                let contract = erc721_contract_at_address(address, self.provider.clone());
+                [
+                    Self::call(address, contract.name().calldata()),
+                    Self::call(address, contract.symbol().calldata()),
+                ]
+            })
+            .collect();
+        let results = self
+            .read_strings(&calls, block, |i| async move {
+                let address = addresses[i / 2];
+                if i % 2 == 0 {
+                    self.get_name(address, block).await
+                } else {
+                    self.get_symbol(address, block).await
+                }
+            })
+            .await;
         tracing::debug!("complete {} contract details requests", addresses.len());
 
         addresses
             .iter()
-            .zip(names.into_iter().zip(symbols))
-            .map(|(&address, (name, symbol))| {
+            .zip(results.chunks(2))
+            .map(|(&address, results)| {
                 (
                     address,
                     ContractDetails {
                         address,
-                        name,
-                        symbol,
+                        name: results[0].clone(),
+                        symbol: results[1].clone(),
                     },
                 )
             })
             .collect()
     }
 
-    async fn get_uris(&self, token_ids: &[NftId]) -> HashMap<NftId, Option<String>> {
+    async fn get_uris(
+        &self,
+        token_ids: &[NftId],
+        block: BlockTag,
+    ) -> HashMap<NftId, Option<String>> {
         tracing::info!("preparing {} tokenUri requests", token_ids.len());
-        let futures = token_ids
+        let calls: Vec<_> = token_ids
             .iter()
-            .cloned()
-            .map(|token| self.get_erc721_uri(token));
-
-        let uris = join_all(futures).await;
-
-        token_ids
-            .iter()
-            .zip(uris)
-            .map(|(&id, uri_result)| {
-                (
-                    id,
-                    match uri_result {
-                        Ok(val) => Some(val),
-                        Err(err) => {
-                            tracing::warn!("failed to decode token_uri for {:?}: {:?}", id, err);
-                            None
-                        }
-                    },
-                )
+            .map(|token| {
+                let contract = erc721_contract_at_address(token.address, self.provider.clone());
+                let token_id =
+                    ethers::types::U256::from_big_endian(&token.token_id.0.to_be_bytes());
+                Self::call(token.address, contract.token_uri(token_id).calldata())
             })
-            .collect()
+            .collect();
+        let uris = self
+            .read_strings(&calls, block, |i| async move {
+                let id = token_ids[i];
+                match self.get_erc721_uri(id, block).await {
+                    Ok(val) => Some(val),
+                    Err(err) => {
+                        tracing::warn!("failed to decode token_uri for {:?}: {:?}", id, err);
+                        None
+                    }
+                }
+            })
+            .await;
+
+        token_ids.iter().copied().zip(uris).collect()
     }
 
     async fn get_blocks_for_range(&self, start: u64, end: u64) -> Result<HashMap<u64, BlockData>> {
@@ -253,13 +361,74 @@ impl EthNodeReading for Client {
             })
             .collect())
     }
+
+    async fn get_logs(&self, start: u64, end: u64, topics: &[Bytes32]) -> Result<Vec<EventLog>> {
+        let filter = Filter::new()
+            .from_block(start)
+            .to_block(end - 1)
+            .topic0(topics.iter().map(|topic| topic.0 .0).collect::<Vec<_>>());
+        let logs = self.provider.get_logs(&filter).await?;
+        Ok(logs.into_iter().map(EventLog::from).collect())
+    }
+
+    async fn get_contract_deployments(
+        &self,
+        addresses: &[Address],
+        known_block: u64,
+    ) -> HashMap<Address, ContractDeployment> {
+        tracing::info!("locating {} contract deployments", addresses.len());
+        let futures = addresses
+            .iter()
+            .map(|&address| self.get_contract_deployment(address, known_block));
+        addresses
+            .iter()
+            .zip(join_all(futures).await)
+            .filter_map(|(address, result)| match result {
+                Ok(deployment) => Some((*address, deployment)),
+                Err(err) => {
+                    tracing::warn!("failed to locate deployment of {}: {:?}", address, err);
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 impl Client {
     pub fn new(url: &str) -> Result<Self> {
-        Ok(Self {
-            provider: Arc::new(Provider::<Http>::try_from(url)?),
-        })
+        Ok(Self::from_transport(Http::from_str(url)?))
+    }
+}
+
+impl<P: JsonRpcClient + 'static> Client<P> {
+    pub fn from_transport(transport: P) -> Self {
+        Self {
+            provider: Arc::new(Provider::new(transport)),
+            block_receipts_unsupported: Arc::default(),
+            attempts: 3,
+            multicall_chunk_size: 0,
+        }
+    }
+
+    /// Aggregates read calls through Multicall3 in chunks of `chunk_size`.
+    pub fn with_multicall(self, chunk_size: usize) -> Self {
+        Self {
+            multicall_chunk_size: chunk_size,
+            ..self
+        }
+    }
+
+    /// Client sending every request once, for transports which retry by themselves.
+    pub fn without_retries(self) -> Self {
+        Self {
+            attempts: 1,
+            ..self
+        }
+    }
+
+    pub fn transport(&self) -> &P {
+        let provider: &Provider<P> = &self.provider;
+        provider.as_ref()
     }
 
     fn unpack_results<T: Debug>(results: Vec<Result<T, Error>>) -> (Vec<T>, Vec<Error>) {
@@ -270,39 +439,171 @@ impl Client {
     }
 
     pub async fn get_block(&self, block: u64) -> Result<Option<BlockData>> {
-        GetBlock {
+        let mut block_data = GetBlock {
             provider: self.provider.clone(),
             block,
         }
-        .retry_get(3, 1)
+        .retry_get(self.attempts, 1)
+        .await?;
+        // Blocks only contain transactions, gas data & status come from the receipts.
+        if let Some(block_data) = block_data.as_mut().filter(|b| !b.transactions.is_empty()) {
+            for receipt in self.get_block_receipts(block).await? {
+                let index = receipt.transaction_index.as_u64();
+                if let Some(tx) = block_data.transactions.get_mut(&index) {
+                    let receipt = TxDetails::from(receipt);
+                    tx.gas_used = receipt.gas_used;
+                    tx.effective_gas_price = receipt.effective_gas_price;
+                    tx.status = receipt.status;
+                }
+            }
+        }
+        Ok(block_data)
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block: u64,
+    ) -> Result<Vec<ethers::types::TransactionReceipt>> {
+        GetBlockReceipts {
+            provider: self.provider.clone(),
+            block,
+            unsupported: self.block_receipts_unsupported.clone(),
+        }
+        .retry_get(self.attempts, 1)
         .await
     }
 
-    pub async fn get_erc721_uri(&self, token: NftId) -> Result<String> {
+    /// Executes read-only calls returning a string, through Multicall3 where possible.
+    /// Chunks that can't be aggregated (i.e. exceeding the node's gas cap) and blocks
+    /// before the Multicall3 deployment fall back to `single_call` (with the call's index).
+    async fn read_strings<F, Fut>(
+        &self,
+        calls: &[Call],
+        block: BlockTag,
+        single_call: F,
+    ) -> Vec<Option<String>>
+    where
+        F: Fn(usize) -> Fut,
+        Fut: Future<Output = Option<String>>,
+    {
+        let multicall_deployed = match block {
+            BlockTag::Latest => true,
+            BlockTag::Number(number) => number >= MULTICALL3_DEPLOYMENT_BLOCK,
+        };
+        if self.multicall_chunk_size == 0 || !multicall_deployed {
+            return join_all((0..calls.len()).map(single_call)).await;
+        }
+        let chunks = calls.chunks(self.multicall_chunk_size);
+        let aggregates = join_all(chunks.clone().map(|chunk| self.aggregate(chunk, block))).await;
+
+        let mut results = Vec::with_capacity(calls.len());
+        for (chunk, aggregate) in chunks.zip(aggregates) {
+            match aggregate {
+                Some(chunk_results) => results.extend(
+                    chunk_results
+                        .into_iter()
+                        .map(|data| data.and_then(|data| Self::decode_string(&data))),
+                ),
+                None => {
+                    tracing::debug!("falling back to {} single calls", chunk.len());
+                    let start = results.len();
+                    results.extend(join_all((start..start + chunk.len()).map(&single_call)).await);
+                }
+            }
+        }
+        results
+    }
+
+    /// Return data of `calls` from a single `tryAggregate` call (None for those that failed),
+    /// or None when the aggregate itself fails.
+    async fn aggregate(&self, calls: &[Call], block: BlockTag) -> Option<Vec<Option<Vec<u8>>>> {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(ethers::types::Address::from(multicall3_address().0 .0))
+            .data(encode_try_aggregate(calls))
+            .into();
+        match self.provider.call(&tx, Some(block.into())).await {
+            Ok(data) => decode_try_aggregate(&data, calls.len()),
+            Err(err) => {
+                tracing::debug!("multicall of {} calls failed: {err}", calls.len());
+                None
+            }
+        }
+    }
+
+    fn call(target: Address, calldata: Option<ethers::types::Bytes>) -> Call {
+        Call {
+            target,
+            data: calldata.expect("function call").to_vec(),
+        }
+    }
+
+    fn decode_string(data: &[u8]) -> Option<String> {
+        let string = decode(&[ParamType::String], data)
+            .ok()?
+            .pop()?
+            .into_string()?;
+        // Remove Null Bytes: Postgres can't handle them.
+        Some(string.replace('\0', ""))
+    }
+
+    pub async fn get_erc721_uri(&self, token: NftId, block: BlockTag) -> Result<String> {
         GetErc721Uri {
             provider: self.provider.clone(),
             token,
+            block,
         }
-        .retry_get(3, 1)
+        .retry_get(self.attempts, 1)
         .await
     }
 
-    async fn get_name(&self, address: Address) -> Option<String> {
+    pub async fn get_contract_deployment(
+        &self,
+        address: Address,
+        known_block: u64,
+    ) -> Result<ContractDeployment> {
+        let block = find_deployment_block(known_block, |block| {
+            let request = GetCodeExists {
+                provider: self.provider.clone(),
+                address,
+                block,
+            };
+            let attempts = self.attempts;
+            async move { request.retry_get(attempts, 1).await }
+        })
+        .await?;
+        // Only contracts created directly by a transaction have a receipt with
+        // `contract_address` set. Factory deployments remain without deployer.
+        let receipt = self
+            .get_block_receipts(block)
+            .await?
+            .into_iter()
+            .find(|r| r.contract_address.map(Address::from) == Some(address));
+        Ok(ContractDeployment {
+            address,
+            block,
+            tx_index: receipt.as_ref().map(|r| r.transaction_index.as_u64()),
+            deployer: receipt.map(|r| Address::from(r.from)),
+        })
+    }
+
+    async fn get_name(&self, address: Address, block: BlockTag) -> Option<String> {
         GetName {
             provider: self.provider.clone(),
             address,
+            block,
         }
-        .retry_get(3, 1)
+        .retry_get(self.attempts, 1)
         .await
         .ok()
     }
 
-    async fn get_symbol(&self, address: Address) -> Option<String> {
+    async fn get_symbol(&self, address: Address, block: BlockTag) -> Option<String> {
         GetSymbol {
             provider: self.provider.clone(),
             address,
+            block,
         }
-        .retry_get(3, 1)
+        .retry_get(self.attempts, 1)
         .await
         .ok()
     }
@@ -333,6 +634,10 @@ mod tests {
         assert_eq!(block.time, 1588598533);
         assert_eq!(block.number, number);
         assert_eq!(block.transactions.len(), 103);
+        assert!(block
+            .transactions
+            .values()
+            .all(|tx| tx.value.is_some() && tx.gas_used.is_some() && tx.status.is_some()));
         // Check that: https://etherscan.io/block/10000000
         assert_eq!(
             block.db_time(),
@@ -347,13 +652,102 @@ mod tests {
             .is_none());
     }
 
+    #[tokio::test]
+    async fn block_receipts_fallback() {
+        use ethers::providers::{JsonRpcError, MockProvider, MockResponse};
+        use ethers::types::{Block, TransactionReceipt, H256};
+
+        let mock = MockProvider::new();
+        let client = Client::from_transport(mock.clone());
+        let receipt = TransactionReceipt {
+            transaction_index: 0.into(),
+            gas_used: Some(21_000.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        };
+        let block = Block::<H256> {
+            transactions: vec![H256::repeat_byte(1)],
+            ..Default::default()
+        };
+        // Mocked responses are returned last in, first out.
+        mock.push(Some(receipt.clone())).unwrap();
+        mock.push(Some(block.clone())).unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32601,
+            message: "Method not found".to_string(),
+            data: None,
+        }));
+        assert_eq!(
+            client.get_block_receipts(1).await.unwrap(),
+            std::slice::from_ref(&receipt)
+        );
+
+        // Without trying eth_getBlockReceipts again.
+        mock.push(Some(receipt.clone())).unwrap();
+        mock.push(Some(block)).unwrap();
+        assert_eq!(client.get_block_receipts(2).await.unwrap(), [receipt]);
+    }
+
+    #[tokio::test]
+    async fn multicall_uris() {
+        use ethers::abi::{encode, Token};
+        use ethers::providers::{JsonRpcError, MockProvider, MockResponse};
+        use ethers::types::Bytes;
+
+        let mock = MockProvider::new();
+        let client = Client::from_transport(mock.clone()).with_multicall(2);
+        let tokens: Vec<_> = (1..=3)
+            .map(|id| {
+                NftId::from_str(&format!("0x2EE6AF0DFF3A1CE3F7E3414C52C48FD50D73691E/{id}"))
+                    .unwrap()
+            })
+            .collect();
+        let uri = |uri: &str| Bytes::from(encode(&[Token::String(uri.to_string())]));
+        let aggregate = encode(&[Token::Array(vec![
+            Token::Tuple(vec![
+                Token::Bool(true),
+                Token::Bytes(uri("ipfs://1\0").to_vec()),
+            ]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+        // Mocked responses are returned last in, first out:
+        // the second chunk exceeds the gas cap and falls back to a single call.
+        mock.push::<Bytes, _>(uri("ipfs://3")).unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "out of gas".to_string(),
+            data: None,
+        }));
+        mock.push::<Bytes, _>(Bytes::from(aggregate)).unwrap();
+        assert_eq!(
+            client.get_uris(&tokens, BlockTag::Latest).await,
+            hashmap! {
+                tokens[0] => Some("ipfs://1".to_string()),
+                tokens[1] => None,
+                tokens[2] => Some("ipfs://3".to_string()),
+            }
+        );
+
+        // Single calls before Multicall3 was deployed.
+        mock.push::<Bytes, _>(uri("ipfs://1")).unwrap();
+        assert_eq!(
+            client
+                .get_uris(
+                    &tokens[..1],
+                    BlockTag::Number(MULTICALL3_DEPLOYMENT_BLOCK - 1)
+                )
+                .await,
+            hashmap! { tokens[0] => Some("ipfs://1".to_string()) }
+        );
+    }
+
     #[tokio::test]
     async fn get_erc721_uri() {
         let eth_client = test_client();
         let ens_token = NftId::from_str("0x57F1887A8BF19B14FC0DF6FD9B2ACC9AF147EA85/64671196571681841248190411691641946869002480279128285790058847953168666315").unwrap();
         assert_eq!(
             eth_client
-                .get_erc721_uri(ens_token)
+                .get_erc721_uri(ens_token, BlockTag::Latest)
                 .await
                 .unwrap_err()
                 .to_string(),
@@ -361,13 +755,16 @@ mod tests {
         );
 
         let bored_ape = NftId::from_str("0x2EE6AF0DFF3A1CE3F7E3414C52C48FD50D73691E/16").unwrap();
-        assert!(eth_client.get_erc721_uri(bored_ape).await.is_ok());
+        assert!(eth_client
+            .get_erc721_uri(bored_ape, BlockTag::Latest)
+            .await
+            .is_ok());
 
         let mla_field_agent =
             NftId::from_str("0x7A41E410BB784D9875FA14F2D7D2FA825466CDAE/3490").unwrap();
         assert_eq!(
             eth_client
-                .get_erc721_uri(mla_field_agent)
+                .get_erc721_uri(mla_field_agent, BlockTag::Latest)
                 .await
                 .unwrap_err()
                 .to_string(),
@@ -385,7 +782,10 @@ mod tests {
             Address::from_str("0x7A41E410BB784D9875FA14F2D7D2FA825466CDAE").unwrap();
         assert_eq!(
             eth_client
-                .get_contract_details(&[ens_contract, bored_ape_contract, mla_field_agent])
+                .get_contract_details(
+                    &[ens_contract, bored_ape_contract, mla_field_agent],
+                    BlockTag::Latest,
+                )
                 .await,
             hashmap! {
                 ens_contract => ContractDetails{address: ens_contract, name: None, symbol: None },
@@ -402,12 +802,49 @@ mod tests {
             }
         );
     }
+    #[tokio::test]
+    async fn get_logs() {
+        let eth_client = test_client();
+        // keccak256("Transfer(address,address,uint256)")
+        let transfer_topic =
+            Bytes32::from_str("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+                .unwrap();
+        let logs = eth_client
+            .get_logs(10_000_000, 10_000_001, &[transfer_topic])
+            .await
+            .unwrap();
+        assert!(!logs.is_empty());
+        assert!(logs
+            .iter()
+            .all(|log| log.block_number == 10_000_000 && log.topics[0] == transfer_topic));
+    }
+
+    #[tokio::test]
+    async fn get_contract_deployment() {
+        let eth_client = test_client();
+        // https://etherscan.io/address/0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D
+        let bored_ape_contract =
+            Address::from_str("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D").unwrap();
+        let deployment = eth_client
+            .get_contract_deployment(bored_ape_contract, 12_300_000)
+            .await
+            .unwrap();
+        assert_eq!(deployment.block, 12287507);
+        assert!(deployment.tx_index.is_some());
+        assert_eq!(
+            deployment.deployer,
+            Some(Address::from_str("0xaBA7161A7fb69c88e16ED9f455CE62B791EE4D03").unwrap())
+        );
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_non_retryable_error() {
         let eth_client = test_client();
         let ens_contract = Address::from_str("0x57F1887A8BF19B14FC0DF6FD9B2ACC9AF147EA85").unwrap();
-        eth_client.get_contract_details(&[ens_contract]).await;
+        eth_client
+            .get_contract_details(&[ens_contract], BlockTag::Latest)
+            .await;
 
         let warn_message = "Contract call reverted with message:";
         // Ensure that certain strings are or aren't logged
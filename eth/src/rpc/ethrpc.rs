@@ -1,4 +1,7 @@
-use crate::types::{Address, BlockData, ContractDetails, NftId, TxDetails};
+use crate::types::{
+    Address, BlockData, BlockTag, Bytes32, ContractDeployment, ContractDetails, EventLog, NftId,
+    TxDetails,
+};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use ethrpc::http::Error;
@@ -9,18 +12,57 @@ use ethrpc::{
     types::*,
 };
 use futures::future::join_all;
+use serde::{Deserialize, Serialize};
 use solabi::{decode::Decode, encode::Encode, selector, FunctionEncoder};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use std::{collections::HashMap, fmt::Debug};
 
-use super::EthNodeReading;
+use super::{
+    find_deployment_block, is_unsupported_method,
+    multicall::{
+        decode_try_aggregate, encode_try_aggregate, multicall3_address, Call,
+        MULTICALL3_DEPLOYMENT_BLOCK,
+    },
+    EthNodeReading,
+};
 
 const NAME: FunctionEncoder<(), (String,)> = FunctionEncoder::new(selector!("name()"));
 const SYMBOL: FunctionEncoder<(), (String,)> = FunctionEncoder::new(selector!("symbol()"));
 const TOKEN_URI: FunctionEncoder<(U256,), (String,)> =
     FunctionEncoder::new(selector!("tokenURI(uint256)"));
+
+/// Receipt fields read by the indexer.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionReceipt {
+    transaction_index: U256,
+    contract_address: Option<ethrpc::types::Address>,
+    gas_used: U256,
+    /// Missing on some nodes before London.
+    effective_gas_price: Option<U256>,
+    /// Missing before Byzantium.
+    status: Option<U256>,
+}
+
+ethrpc::module! {
+    /// Receipt methods (not provided by `ethrpc`).
+    mod receipts {
+        /// Returns the receipt of a transaction by transaction hash.
+        pub(super) struct GetTransactionReceipt as "eth_getTransactionReceipt"
+            (Digest,) => Option<TransactionReceipt>;
+
+        /// Returns the receipts of all transactions in a block.
+        pub(super) struct GetBlockReceipts as "eth_getBlockReceipts"
+            (BlockId,) => Option<Vec<TransactionReceipt>>;
+    }
+}
+
 pub struct Client {
     provider: ethrpc::http::Buffered,
+    multicall_chunk_size: usize,
+    /// Set once the node rejected `eth_getBlockReceipts`.
+    block_receipts_unsupported: AtomicBool,
 }
 
 fn handle_error(error: EthRpcError, context: &str) {
@@ -72,7 +114,7 @@ impl EthNodeReading for Client {
             ));
         }
 
-        Ok(possible_blocks
+        let mut blocks: HashMap<u64, BlockData> = possible_blocks
             .into_iter()
             .flatten()
             .map(|block| {
@@ -97,29 +139,51 @@ impl EthNodeReading for Client {
                     },
                 )
             })
-            .collect())
-    }
+            .collect();
 
-    async fn get_uris(&self, token_ids: &[NftId]) -> HashMap<NftId, Option<String>> {
-        tracing::info!("preparing {} tokenUri requests", token_ids.len());
-        let futures = token_ids.iter().map(|token| {
-            self.provider
-                .call(eth::Call, (Self::uri_call(token), BlockId::default()))
+        // Gas data & status are only available on the receipts.
+        let numbers: Vec<_> = blocks.keys().copied().collect();
+        let receipt_futures = blocks.values().map(|block| {
+            let hashes: Vec<_> = block.transactions.values().map(|tx| tx.hash.0).collect();
+            self.get_block_receipts(block.number, hashes)
         });
+        let receipts = join_all(receipt_futures)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+        for (number, receipts) in numbers.into_iter().zip(receipts) {
+            let block = blocks.get_mut(&number).expect("known to exist");
+            for receipt in receipts {
+                if let Some(tx) = block
+                    .transactions
+                    .get_mut(&receipt.transaction_index.as_u64())
+                {
+                    tx.add_receipt(
+                        receipt.gas_used.as_u64(),
+                        receipt.effective_gas_price.map(crate::types::U256),
+                        receipt.status.map(|status| status.as_u64() == 1),
+                    );
+                }
+            }
+        }
+        Ok(blocks)
+    }
 
-        let uri_results = join_all(futures).await;
+    async fn get_uris(
+        &self,
+        token_ids: &[NftId],
+        block: BlockTag,
+    ) -> HashMap<NftId, Option<String>> {
+        tracing::info!("preparing {} tokenUri requests", token_ids.len());
+        let calls: Vec<_> = token_ids.iter().map(Self::uri_call).collect();
+        let uri_results = self.read_calls(&calls, block, "tokenUri").await;
         tracing::debug!("completed tokenUri requests");
         token_ids
             .iter()
             .zip(uri_results)
             .map(|(&id, uri_result)| {
-                let uri = match uri_result {
-                    Ok(bytes) => Self::decode_function_result_string(bytes, TOKEN_URI),
-                    Err(err) => {
-                        handle_error(err, &format!("tokenUri for {id}"));
-                        None
-                    }
-                };
+                let uri = uri_result
+                    .and_then(|bytes| Self::decode_function_result_string(bytes, TOKEN_URI));
                 (id, uri)
             })
             .collect()
@@ -128,39 +192,26 @@ impl EthNodeReading for Client {
     async fn get_contract_details(
         &self,
         addresses: &[Address],
+        block: BlockTag,
     ) -> HashMap<Address, ContractDetails> {
         tracing::info!("preparing {} contract details requests", addresses.len());
-        let name_futures = addresses.iter().cloned().map(|addr| {
-            self.provider
-                .call(eth::Call, (Self::name_call(addr), BlockId::default()))
-        });
-
-        let symbol_futures = addresses.iter().cloned().map(|addr| {
-            self.provider
-                .call(eth::Call, (Self::symbol_call(addr), BlockId::default()))
-        });
-
-        let (names, symbols) = (join_all(name_futures).await, join_all(symbol_futures).await);
+        let calls: Vec<_> = addresses
+            .iter()
+            .flat_map(|&address| [Self::name_call(address), Self::symbol_call(address)])
+            .collect();
+        let results = self.read_calls(&calls, block, "name/symbol").await;
         tracing::debug!("complete {} contract details requests", addresses.len());
 
         addresses
             .iter()
-            .zip(names.into_iter().zip(symbols))
-            .map(|(&address, (name_result, symbol_result))| {
-                let name = match name_result {
-                    Ok(name) => Self::decode_function_result_string(name, NAME),
-                    Err(err) => {
-                        handle_error(err, &format!("name for {address}"));
-                        None
-                    }
-                };
-                let symbol = match symbol_result {
-                    Ok(symbol) => Self::decode_function_result_string(symbol, SYMBOL),
-                    Err(err) => {
-                        handle_error(err, &format!("symbol for {address}"));
-                        None
-                    }
-                };
+            .zip(results.chunks(2))
+            .map(|(&address, results)| {
+                let name = results[0]
+                    .clone()
+                    .and_then(|name| Self::decode_function_result_string(name, NAME));
+                let symbol = results[1]
+                    .clone()
+                    .and_then(|symbol| Self::decode_function_result_string(symbol, SYMBOL));
                 (
                     address,
                     ContractDetails {
@@ -172,18 +223,255 @@ impl EthNodeReading for Client {
             })
             .collect()
     }
+
+    async fn get_logs(&self, start: u64, end: u64, topics: &[Bytes32]) -> Result<Vec<EventLog>> {
+        let filter = LogFilter {
+            blocks: LogBlocks::Range {
+                from: BlockSpec::Number(U256::from(start)),
+                to: BlockSpec::Number(U256::from(end - 1)),
+            },
+            address: LogFilterValue::Any,
+            topics: [
+                LogFilterValue::OneOf(topics.iter().map(|topic| topic.0).collect()),
+                LogFilterValue::Any,
+                LogFilterValue::Any,
+                LogFilterValue::Any,
+            ]
+            .into(),
+        };
+        let logs = self.provider.call(eth::GetLogs, (filter,)).await?;
+        Ok(logs
+            .into_iter()
+            .map(|log| EventLog {
+                address: Address::from(log.address),
+                topics: log.topics.into_iter().map(Bytes32::from).collect(),
+                data: log.data,
+                block_number: log.block_number.as_u64(),
+                transaction_index: log.transaction_index.as_u64(),
+                log_index: log.log_index.as_u64(),
+            })
+            .collect())
+    }
+
+    async fn get_contract_deployments(
+        &self,
+        addresses: &[Address],
+        known_block: u64,
+    ) -> HashMap<Address, ContractDeployment> {
+        tracing::info!("locating {} contract deployments", addresses.len());
+        let futures = addresses
+            .iter()
+            .map(|&address| self.get_contract_deployment(address, known_block));
+        addresses
+            .iter()
+            .zip(join_all(futures).await)
+            .filter_map(|(address, result)| match result {
+                Ok(deployment) => Some((*address, deployment)),
+                Err(err) => {
+                    tracing::warn!("failed to locate deployment of {address}: {err:?}");
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 impl Client {
-    pub fn new(url: &str, batch_delay: u64) -> Result<Self> {
+    /// Read calls are aggregated through Multicall3 in chunks of `multicall_chunk_size`
+    /// (0 sends every call on its own).
+    pub fn new(url: &str, batch_delay: u64, multicall_chunk_size: usize) -> Result<Self> {
         Ok(Self {
             provider: ethrpc::http::Client::new(Url::parse(url)?).buffered(Configuration {
                 delay: Duration::from_millis(batch_delay),
                 ..Default::default()
             }),
+            multicall_chunk_size,
+            block_receipts_unsupported: AtomicBool::new(false),
         })
     }
 
+    /// Receipts of a block with transactions `hashes`: one `eth_getBlockReceipts` call,
+    /// or one `eth_getTransactionReceipt` call per transaction on nodes without it.
+    async fn get_block_receipts(
+        &self,
+        block: u64,
+        hashes: Vec<Digest>,
+    ) -> Result<Vec<TransactionReceipt>> {
+        if hashes.is_empty() {
+            return Ok(vec![]);
+        }
+        if !self.block_receipts_unsupported.load(Ordering::Relaxed) {
+            match self
+                .provider
+                .call(receipts::GetBlockReceipts, (Self::block_id(block),))
+                .await
+            {
+                Ok(receipts) => return Ok(receipts.unwrap_or_default()),
+                Err(Error::Rpc(err))
+                    if is_unsupported_method(i32::from(err.code).into(), &err.message) =>
+                {
+                    tracing::warn!(
+                        "eth_getBlockReceipts unsupported ({}), reading transaction receipts",
+                        err.message
+                    );
+                    self.block_receipts_unsupported
+                        .store(true, Ordering::Relaxed);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let futures = hashes
+            .into_iter()
+            .map(|hash| self.provider.call(receipts::GetTransactionReceipt, (hash,)));
+        let (receipts, errors) = Self::unpack_results(join_all(futures).await);
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "failed to retrieve {} receipts {:?}",
+                errors.len(),
+                errors
+            ));
+        }
+        Ok(receipts.into_iter().flatten().collect())
+    }
+
+    /// Executes read-only calls, returning the return data of those that succeeded.
+    /// Chunks whose aggregate fails (i.e. exceeding the node's gas cap) fall back to single calls.
+    async fn read_calls(
+        &self,
+        calls: &[Call],
+        block: BlockTag,
+        context: &str,
+    ) -> Vec<Option<Vec<u8>>> {
+        let multicall_deployed = match block {
+            BlockTag::Latest => true,
+            BlockTag::Number(number) => number >= MULTICALL3_DEPLOYMENT_BLOCK,
+        };
+        if self.multicall_chunk_size == 0 || !multicall_deployed {
+            return self.read_single_calls(calls, block, context).await;
+        }
+        let chunks = calls.chunks(self.multicall_chunk_size);
+        let futures = chunks.clone().map(|chunk| {
+            let aggregate = Call {
+                target: multicall3_address(),
+                data: encode_try_aggregate(chunk),
+            };
+            self.provider.call(
+                eth::Call,
+                (Self::transaction_call(&aggregate), Self::tag_id(block)),
+            )
+        });
+        let aggregate_results = join_all(futures).await;
+
+        let mut results = Vec::with_capacity(calls.len());
+        for (chunk, aggregate_result) in chunks.zip(aggregate_results) {
+            let decoded = match aggregate_result {
+                Ok(bytes) => decode_try_aggregate(&bytes, chunk.len()),
+                // Errors unrelated to aggregating resurface on the single calls.
+                Err(err) => {
+                    tracing::debug!("multicall of {} {context} failed: {err}", chunk.len());
+                    None
+                }
+            };
+            match decoded {
+                Some(chunk_results) => results.extend(chunk_results),
+                None => {
+                    tracing::debug!("falling back to {} single {context} calls", chunk.len());
+                    results.extend(self.read_single_calls(chunk, block, context).await);
+                }
+            }
+        }
+        results
+    }
+
+    async fn read_single_calls(
+        &self,
+        calls: &[Call],
+        block: BlockTag,
+        context: &str,
+    ) -> Vec<Option<Vec<u8>>> {
+        let futures = calls.iter().map(|call| {
+            self.provider.call(
+                eth::Call,
+                (Self::transaction_call(call), Self::tag_id(block)),
+            )
+        });
+        join_all(futures)
+            .await
+            .into_iter()
+            .zip(calls)
+            .map(|(result, call)| match result {
+                Ok(bytes) => Some(bytes),
+                Err(err) => {
+                    handle_error(err, &format!("{context} for {}", call.target));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub async fn get_contract_deployment(
+        &self,
+        address: Address,
+        known_block: u64,
+    ) -> Result<ContractDeployment> {
+        let block = find_deployment_block(known_block, |block| async move {
+            let code = self
+                .provider
+                .call(eth::GetCode, (address.0, Self::block_id(block)))
+                .await?;
+            Ok(!code.is_empty())
+        })
+        .await?;
+
+        // Only contracts created directly by a transaction (i.e. with empty `to`)
+        // have a receipt with `contract_address`. Factory deployments remain without deployer.
+        let creations: Vec<_> = match self
+            .provider
+            .call(
+                eth::GetBlockByNumber,
+                (BlockSpec::Number(U256::from(block)), Hydrated::Yes),
+            )
+            .await?
+            .map(|b| b.transactions)
+        {
+            Some(BlockTransactions::Full(txs)) => {
+                txs.into_iter().filter(|tx| tx.to().is_none()).collect()
+            }
+            _ => vec![],
+        };
+        let receipts = join_all(creations.iter().map(|tx| {
+            self.provider
+                .call(receipts::GetTransactionReceipt, (tx.hash(),))
+        }))
+        .await;
+        let creation =
+            creations
+                .into_iter()
+                .zip(receipts)
+                .find_map(|(tx, receipt)| match receipt {
+                    Ok(Some(r)) if r.contract_address == Some(address.0) => Some(tx),
+                    _ => None,
+                });
+
+        Ok(ContractDeployment {
+            address,
+            block,
+            tx_index: creation.as_ref().map(|tx| tx.transaction_index().as_u64()),
+            deployer: creation.map(|tx| Address::from(tx.from())),
+        })
+    }
+
+    fn block_id(block: u64) -> BlockId {
+        BlockId::Number(U256::from(block))
+    }
+
+    fn tag_id(block: BlockTag) -> BlockId {
+        match block {
+            BlockTag::Latest => BlockId::default(),
+            BlockTag::Number(number) => Self::block_id(number),
+        }
+    }
+
     fn unpack_results<T: Debug>(results: Vec<Result<T, Error>>) -> (Vec<T>, Vec<Error>) {
         let (oks, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
         let oks: Vec<_> = oks.into_iter().map(Result::unwrap).collect();
@@ -191,27 +479,32 @@ impl Client {
         (oks, errors)
     }
 
-    fn uri_call(token: &NftId) -> TransactionCall {
+    fn transaction_call(call: &Call) -> TransactionCall {
         TransactionCall {
-            to: Some(token.address.0),
-            input: Some(TOKEN_URI.encode_params(&(token.token_id.0,))),
+            to: Some(call.target.0),
+            input: Some(call.data.clone()),
             ..Default::default()
         }
     }
 
-    fn name_call(address: Address) -> TransactionCall {
-        TransactionCall {
-            to: Some(address.0),
-            input: Some(NAME.encode_params(&())),
-            ..Default::default()
+    fn uri_call(token: &NftId) -> Call {
+        Call {
+            target: token.address,
+            data: TOKEN_URI.encode_params(&(token.token_id.0,)),
         }
     }
 
-    fn symbol_call(address: Address) -> TransactionCall {
-        TransactionCall {
-            to: Some(address.0),
-            input: Some(SYMBOL.encode_params(&())),
-            ..Default::default()
+    fn name_call(address: Address) -> Call {
+        Call {
+            target: address,
+            data: NAME.encode_params(&()),
+        }
+    }
+
+    fn symbol_call(address: Address) -> Call {
+        Call {
+            target: address,
+            data: SYMBOL.encode_params(&()),
         }
     }
 
@@ -245,7 +538,7 @@ mod tests {
     static FREE_ETH_RPC: &str = "https://rpc.ankr.com/eth";
 
     fn test_client() -> Client {
-        Client::new(FREE_ETH_RPC, 0).expect("Needed for test")
+        Client::new(FREE_ETH_RPC, 0, 100).expect("Needed for test")
     }
 
     #[tokio::test]
@@ -266,7 +559,9 @@ mod tests {
         ]
         .map(|s| Address::from_str(s).unwrap())
         .to_vec();
-        let details = eth_client.get_contract_details(&addresses.clone()).await;
+        let details = eth_client
+            .get_contract_details(&addresses.clone(), BlockTag::Latest)
+            .await;
 
         let expected = addresses
             .clone()
@@ -340,7 +635,7 @@ mod tests {
 
         let token_ids = [ens_token, bored_ape, mla_field_agent, null_bytes];
 
-        let uris = eth_client.get_uris(&token_ids).await;
+        let uris = eth_client.get_uris(&token_ids, BlockTag::Latest).await;
 
         assert_eq!(
             uris,
@@ -354,6 +649,72 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn multicall_matches_single_calls() {
+        let bored_ape = Address::from_str("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D").unwrap();
+        // Includes tokens that don't exist (i.e. reverting calls).
+        let token_ids: Vec<_> = [0, 1, 2, 10_000, 10_001]
+            .map(|id| NftId {
+                address: bored_ape,
+                token_id: crate::types::U256::from(id),
+            })
+            .to_vec();
+        let single = Client::new(FREE_ETH_RPC, 0, 0).unwrap();
+        // Chunks of uneven size.
+        let aggregated = Client::new(FREE_ETH_RPC, 0, 3).unwrap();
+
+        let uris = aggregated.get_uris(&token_ids, BlockTag::Latest).await;
+        assert_eq!(uris, single.get_uris(&token_ids, BlockTag::Latest).await);
+        assert!(uris[&token_ids[0]].is_some());
+        assert!(uris[&token_ids[3]].is_none());
+        assert_eq!(
+            aggregated
+                .get_contract_details(&[bored_ape], BlockTag::Latest)
+                .await,
+            single
+                .get_contract_details(&[bored_ape], BlockTag::Latest)
+                .await,
+        );
+    }
+
+    #[tokio::test]
+    async fn historical_contract_details() {
+        let eth_client = &test_client();
+        let bored_ape = Address::from_str("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D").unwrap();
+        let name_at = |block| async move {
+            eth_client
+                .get_contract_details(&[bored_ape], BlockTag::Number(block))
+                .await
+                .remove(&bored_ape)
+                .unwrap()
+                .name
+        };
+        // Before and after deployment (both prior to Multicall3).
+        assert_eq!(name_at(12_287_506).await, None);
+        assert_eq!(
+            name_at(12_287_507).await,
+            Some("BoredApeYachtClub".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn get_contract_deployment() {
+        let eth_client = test_client();
+        // https://etherscan.io/address/0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D
+        let bored_ape_contract =
+            Address::from_str("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D").unwrap();
+        let deployment = eth_client
+            .get_contract_deployment(bored_ape_contract, 12_300_000)
+            .await
+            .unwrap();
+        assert_eq!(deployment.block, 12287507);
+        assert!(deployment.tx_index.is_some());
+        assert_eq!(
+            deployment.deployer,
+            Some(Address::from_str("0xaBA7161A7fb69c88e16ED9f455CE62B791EE4D03").unwrap())
+        );
+    }
+
     #[test]
     fn error_handling() {
         let inner_error = jsonrpc::Error::custom("execution reverted");
@@ -1,33 +1,212 @@
+pub mod cassette;
 pub mod ethers;
 pub mod ethrpc;
-use crate::types::{Address, BlockData, ContractDetails, NftId};
+pub mod multicall;
+pub mod pool;
+use crate::types::{
+    Address, BlockData, BlockTag, Bytes32, ContractDeployment, ContractDetails, EventLog, NftId,
+};
 use anyhow::Result;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::{collections::HashMap, future::Future};
 
 #[async_trait]
 pub trait EthNodeReading: Send + Sync {
+    /// Contract state (i.e. name and symbol) is read as of `block`.
     async fn get_contract_details(
         &self,
         addresses: &[Address],
+        block: BlockTag,
     ) -> HashMap<Address, ContractDetails>;
 
-    async fn get_uris(&self, token_ids: &[NftId]) -> HashMap<NftId, Option<String>>;
+    /// Token URIs are read as of `block`, so burned tokens only have one before their burn.
+    async fn get_uris(
+        &self,
+        token_ids: &[NftId],
+        block: BlockTag,
+    ) -> HashMap<NftId, Option<String>>;
 
     async fn get_blocks_for_range(&self, start: u64, end: u64) -> Result<HashMap<u64, BlockData>>;
 
+    /// Logs emitted in blocks [start, end) whose first topic is one of `topics`.
+    async fn get_logs(&self, start: u64, end: u64, topics: &[Bytes32]) -> Result<Vec<EventLog>>;
+
+    /// Locates the deployment of each contract, given that it is known to have
+    /// code at `known_block`. Contracts whose deployment can't be determined
+    /// are omitted from the result.
+    async fn get_contract_deployments(
+        &self,
+        addresses: &[Address],
+        known_block: u64,
+    ) -> HashMap<Address, ContractDeployment>;
+
     async fn get_uris_and_contract_details(
         &self,
         tokens: &[NftId],
         addresses: &[Address],
+        block: BlockTag,
     ) -> (
         HashMap<NftId, Option<String>>,
         HashMap<Address, ContractDetails>,
     ) {
         // futures::future::join(self.get_uris(tokens), self.get_contract_details(addresses)).await
         (
-            self.get_uris(tokens).await,
-            self.get_contract_details(addresses).await,
+            self.get_uris(tokens, block).await,
+            self.get_contract_details(addresses, block).await,
         )
     }
 }
+
+/// Whether a JSON-RPC error means that the node doesn't implement the method
+/// (i.e. `eth_getBlockReceipts` on older clients).
+pub(crate) fn is_unsupported_method(code: i64, message: &str) -> bool {
+    const METHOD_NOT_FOUND: i64 = -32601;
+    let message = message.to_lowercase();
+    code == METHOD_NOT_FOUND
+        || [
+            "method not found",
+            "does not exist",
+            "not supported",
+            "unsupported method",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Whether a failed `eth_getLogs` request should be retried over a smaller block range,
+/// i.e. the node limits the range or the number of results of a request.
+pub(crate) fn is_range_too_large(err: &anyhow::Error) -> bool {
+    let message = format!("{err:#}").to_lowercase();
+    [
+        "block range",
+        "range too large",
+        "range is too large",
+        "query returned more than",
+        "too many results",
+        "response size exceeded",
+        "limit exceeded",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+/// Reads the logs of blocks [start, end) with `get_logs`, in sub-ranges of at most
+/// `max_blocks` blocks. Sub-ranges rejected as too large are halved (down to single blocks).
+pub async fn get_logs_in_ranges<F, Fut>(
+    start: u64,
+    end: u64,
+    max_blocks: u64,
+    get_logs: F,
+) -> Result<Vec<EventLog>>
+where
+    F: Fn(u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<EventLog>>>,
+{
+    let mut logs = vec![];
+    let mut size = max_blocks.max(1);
+    let mut from = start;
+    while from < end {
+        let to = end.min(from + size);
+        match get_logs(from, to).await {
+            Ok(range_logs) => {
+                logs.extend(range_logs);
+                from = to;
+            }
+            Err(err) if to - from > 1 && is_range_too_large(&err) => {
+                size = (to - from) / 2;
+                tracing::debug!("logs of {from}..{to} rejected ({err}), retrying {size} blocks");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(logs)
+}
+
+/// Binary search for the first block at which `has_code` returns true.
+/// Assumes that code is present at `known_block` and that, once deployed,
+/// code remains present (self-destructed and redeployed contracts may yield
+/// any of their deployment blocks).
+pub(crate) async fn find_deployment_block<F, Fut>(known_block: u64, has_code: F) -> Result<u64>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    let (mut low, mut high) = (0, known_block);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if has_code(mid).await? {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_method_errors() {
+        assert!(is_unsupported_method(-32601, "Method not found"));
+        assert!(is_unsupported_method(
+            -32000,
+            "the method eth_getBlockReceipts does not exist/is not available"
+        ));
+        assert!(!is_unsupported_method(-32000, "header not found"));
+        assert!(!is_unsupported_method(
+            -32005,
+            "query returned more than 10000 results"
+        ));
+    }
+
+    #[tokio::test]
+    async fn logs_in_ranges() {
+        let log = |block_number| EventLog {
+            address: Address::zero(),
+            topics: vec![],
+            data: vec![],
+            block_number,
+            transaction_index: 0,
+            log_index: 0,
+        };
+        let requests = std::sync::Mutex::new(vec![]);
+        // Rejects ranges of more than 3 blocks.
+        let logs = get_logs_in_ranges(0, 10, 8, |start, end| {
+            requests.lock().unwrap().push((start, end));
+            async move {
+                anyhow::ensure!(end - start <= 3, "block range too large");
+                Ok((start..end).map(log).collect())
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(logs, (0..10).map(log).collect::<Vec<_>>());
+        assert_eq!(
+            requests.into_inner().unwrap(),
+            [(0, 8), (0, 4), (0, 2), (2, 4), (4, 6), (6, 8), (8, 10)]
+        );
+
+        assert!(get_logs_in_ranges(0, 10, 8, |_, _| async {
+            Err(anyhow::anyhow!("header not found"))
+        })
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn find_deployment_block_search() {
+        for deployed in [0, 1, 17, 999, 1000] {
+            let block = find_deployment_block(1000, |n| async move { Ok(n >= deployed) })
+                .await
+                .unwrap();
+            assert_eq!(block, deployed);
+        }
+        assert!(
+            find_deployment_block(1000, |_| async { Err(anyhow::anyhow!("down")) })
+                .await
+                .is_err()
+        );
+    }
+}
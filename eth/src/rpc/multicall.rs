@@ -0,0 +1,132 @@
+use crate::types::Address;
+use ethers::{
+    abi::{decode, encode, ParamType, Token},
+    utils::id,
+};
+
+/// Multicall3 shares this address on all chains it is deployed to.
+pub fn multicall3_address() -> Address {
+    Address::from([
+        0xca, 0x11, 0xbd, 0xe0, 0x59, 0x77, 0xb3, 0x63, 0x11, 0x67, 0x02, 0x88, 0x62, 0xbe, 0x2a,
+        0x17, 0x39, 0x76, 0xca, 0x11,
+    ])
+}
+
+/// First mainnet block at which Multicall3 can be called.
+pub const MULTICALL3_DEPLOYMENT_BLOCK: u64 = 14_353_601;
+
+const TRY_AGGREGATE: &str = "tryAggregate(bool,(address,bytes)[])";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub target: Address,
+    pub data: Vec<u8>,
+}
+
+/// Calldata for `tryAggregate(false, calls)`, so that failing calls don't revert the batch.
+pub fn encode_try_aggregate(calls: &[Call]) -> Vec<u8> {
+    let calls = calls
+        .iter()
+        .map(|call| {
+            Token::Tuple(vec![
+                Token::Address(ethers::types::Address::from(call.target.0 .0)),
+                Token::Bytes(call.data.clone()),
+            ])
+        })
+        .collect();
+    let mut data = id(TRY_AGGREGATE).to_vec();
+    data.extend(encode(&[Token::Bool(false), Token::Array(calls)]));
+    data
+}
+
+/// Return data of each call, or None for those that failed.
+/// Returns None altogether when the result doesn't hold `expected` entries.
+pub fn decode_try_aggregate(data: &[u8], expected: usize) -> Option<Vec<Option<Vec<u8>>>> {
+    let result_type = ParamType::Array(Box::new(ParamType::Tuple(vec![
+        ParamType::Bool,
+        ParamType::Bytes,
+    ])));
+    let results = decode(&[result_type], data)
+        .ok()?
+        .pop()?
+        .into_array()?
+        .into_iter()
+        .map(|result| {
+            let mut fields = result.into_tuple()?.into_iter();
+            let success = fields.next()?.into_bool()?;
+            let data = fields.next()?.into_bytes()?;
+            Some(success.then_some(data))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (results.len() == expected).then_some(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let calls = vec![
+            Call {
+                target: Address::from(1),
+                data: vec![1, 2, 3, 4],
+            },
+            Call {
+                target: Address::from(2),
+                data: vec![],
+            },
+        ];
+        let data = encode_try_aggregate(&calls);
+        assert_eq!(data[..4], [0xbc, 0xe3, 0x8b, 0xd7]);
+        let mut params = decode(
+            &[
+                ParamType::Bool,
+                ParamType::Array(Box::new(ParamType::Tuple(vec![
+                    ParamType::Address,
+                    ParamType::Bytes,
+                ]))),
+            ],
+            &data[4..],
+        )
+        .unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0], Token::Bool(false));
+        let encoded_calls = params.pop().unwrap().into_array().unwrap();
+        assert_eq!(
+            encoded_calls[0],
+            Token::Tuple(vec![
+                Token::Address(ethers::types::Address::from_low_u64_be(1)),
+                Token::Bytes(vec![1, 2, 3, 4])
+            ])
+        );
+    }
+
+    #[test]
+    fn decoding() {
+        let data = encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![1])]),
+            Token::Tuple(vec![
+                Token::Bool(false),
+                Token::Bytes(vec![8, 195, 121, 160]),
+            ]),
+        ])]);
+        assert_eq!(
+            decode_try_aggregate(&data, 2),
+            Some(vec![Some(vec![1]), None])
+        );
+        // Unexpected number of results
+        assert_eq!(decode_try_aggregate(&data, 3), None);
+        assert_eq!(decode_try_aggregate(&[1, 2, 3], 2), None);
+    }
+
+    #[test]
+    fn address() {
+        assert_eq!(
+            ethers::types::Address::from(multicall3_address().0 .0),
+            "0xcA11bde05977b3631167028862bE2a173976CA11"
+                .parse::<ethers::types::Address>()
+                .unwrap()
+        );
+    }
+}
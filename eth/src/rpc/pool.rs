@@ -0,0 +1,616 @@
+use super::ethers::Client as EthersClient;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::providers::{Http, JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::{Debug, Display, Formatter},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// `EthNodeReading` backed by several JSON-RPC endpoints.
+pub type ProviderPool = EthersClient<PoolTransport>;
+
+impl ProviderPool {
+    pub fn from_providers(providers: &[ProviderConfig], options: PoolOptions) -> Result<Self> {
+        Ok(Self::from_pool(PoolTransport::new(providers, options)?))
+    }
+}
+
+impl<C> EthersClient<PoolTransport<C>>
+where
+    PoolTransport<C>: JsonRpcClient + 'static,
+{
+    /// The pool retries failed requests on other providers and returns reverts right away,
+    /// so the client doesn't retry on top of it.
+    pub fn from_pool(transport: PoolTransport<C>) -> Self {
+        Self::from_transport(transport).without_retries()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderConfig {
+    pub url: String,
+    /// Relative share of requests routed to this provider.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Maximum request rate (unlimited when absent).
+    pub requests_per_second: Option<u32>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Parses `<url>[;weight=<n>][;rps=<n>]`.
+impl FromStr for ProviderConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().split(';');
+        let url = parts.next().unwrap_or_default().to_string();
+        if url.is_empty() {
+            return Err(anyhow!("provider without url: {s}"));
+        }
+        let mut config = Self {
+            url,
+            weight: default_weight(),
+            requests_per_second: None,
+        };
+        for option in parts {
+            match option.split_once('=') {
+                Some(("weight", value)) => config.weight = value.parse()?,
+                Some(("rps", value)) => config.requests_per_second = Some(value.parse()?),
+                _ => return Err(anyhow!("invalid provider option {option:?}")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolOptions {
+    /// Attempts per request (over all providers) before giving up.
+    pub max_attempts: u32,
+    /// Cooldown after a provider's first consecutive failure, doubling with each further one.
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Execution failure: every other provider would answer the same.
+    Revert,
+    /// The provider refused the request due to rate limiting.
+    RateLimited,
+    /// Connection, timeout, malformed response or node specific failure.
+    Transport,
+}
+
+pub fn classify<E: RpcError>(error: &E) -> ErrorClass {
+    if let Some(JsonRpcError { code, message, .. }) = error.as_error_response() {
+        let message = message.to_lowercase();
+        return if *code == 3 || message.contains("revert") {
+            ErrorClass::Revert
+        } else if *code == 429 || *code == -32005 || message.contains("rate limit") {
+            ErrorClass::RateLimited
+        } else {
+            ErrorClass::Transport
+        };
+    }
+    // Rate limiting proxies tend to respond with a non JSON-RPC body.
+    let message = error.to_string().to_lowercase();
+    if message.contains("too many requests") || message.contains("rate limit") {
+        ErrorClass::RateLimited
+    } else {
+        ErrorClass::Transport
+    }
+}
+
+#[derive(Debug)]
+pub enum PoolError<E> {
+    Revert(E),
+    Exhausted { attempts: u32, last: E },
+}
+
+impl<E> PoolError<E> {
+    fn inner(&self) -> &E {
+        match self {
+            PoolError::Revert(err) | PoolError::Exhausted { last: err, .. } => err,
+        }
+    }
+}
+
+impl<E: Display> Display for PoolError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::Revert(err) => write!(f, "{err}"),
+            PoolError::Exhausted { attempts, last } => {
+                write!(f, "all {attempts} attempts failed, last error: {last}")
+            }
+        }
+    }
+}
+
+impl<E: RpcError> std::error::Error for PoolError<E> {}
+
+impl<E: RpcError> RpcError for PoolError<E> {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        self.inner().as_error_response()
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        self.inner().as_serde_error()
+    }
+}
+
+impl<E: RpcError + 'static> From<PoolError<E>> for ProviderError {
+    fn from(value: PoolError<E>) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(value))
+    }
+}
+
+/// Health snapshot of a single provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderHealth {
+    pub url: String,
+    /// Moving average of the request success rate.
+    pub score: f64,
+    pub requests: u64,
+    pub failures: u64,
+    pub cooling_down: bool,
+}
+
+/// Weight of the latest outcome in the health score.
+const HEALTH_DECAY: f64 = 0.2;
+/// Unhealthy providers still get a small share of traffic, so they can recover.
+const MIN_HEALTH: f64 = 0.05;
+
+#[derive(Debug)]
+struct Member<C> {
+    url: String,
+    client: C,
+    weight: u32,
+    interval: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct MemberState {
+    /// Smooth weighted round-robin counter.
+    current_weight: f64,
+    health: f64,
+    consecutive_failures: u32,
+    cooldown_until: Option<Instant>,
+    /// Earliest time at which the rate limit permits another request.
+    next_slot: Option<Instant>,
+    requests: u64,
+    failures: u64,
+}
+
+impl Default for MemberState {
+    fn default() -> Self {
+        Self {
+            current_weight: 0.0,
+            health: 1.0,
+            consecutive_failures: 0,
+            cooldown_until: None,
+            next_slot: None,
+            requests: 0,
+            failures: 0,
+        }
+    }
+}
+
+/// JSON-RPC transport routing each request to one of several providers.
+///
+/// Providers are picked by weight (scaled by health) and failed requests are retried
+/// on another provider, while the failing one cools down with exponential backoff.
+/// Reverts are returned right away.
+#[derive(Debug)]
+pub struct PoolTransport<C = Http> {
+    members: Vec<Member<C>>,
+    state: Mutex<Vec<MemberState>>,
+    options: PoolOptions,
+}
+
+impl PoolTransport<Http> {
+    pub fn new(providers: &[ProviderConfig], options: PoolOptions) -> Result<Self> {
+        let clients = providers
+            .iter()
+            .map(|config| Ok((config.clone(), Http::from_str(&config.url)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Self::with_clients(clients, options)
+    }
+}
+
+impl<C> PoolTransport<C> {
+    pub fn with_clients(clients: Vec<(ProviderConfig, C)>, options: PoolOptions) -> Result<Self> {
+        if clients.is_empty() {
+            return Err(anyhow!("provider pool requires at least one provider"));
+        }
+        if let Some((config, _)) = clients.iter().find(|(config, _)| config.weight == 0) {
+            return Err(anyhow!("provider {} has zero weight", config.url));
+        }
+        let state = clients.iter().map(|_| MemberState::default()).collect();
+        let members = clients
+            .into_iter()
+            .map(|(config, client)| Member {
+                url: config.url,
+                client,
+                weight: config.weight,
+                interval: config
+                    .requests_per_second
+                    .map(|rate| Duration::from_secs(1) / rate.max(1)),
+            })
+            .collect();
+        Ok(Self {
+            members,
+            state: Mutex::new(state),
+            options,
+        })
+    }
+
+    pub fn health(&self) -> Vec<ProviderHealth> {
+        let now = Instant::now();
+        let state = self.state.lock().expect("poisoned lock");
+        self.members
+            .iter()
+            .zip(state.iter())
+            .map(|(member, state)| ProviderHealth {
+                url: member.url.clone(),
+                score: state.health,
+                requests: state.requests,
+                failures: state.failures,
+                cooling_down: state.cooldown_until.is_some_and(|until| until > now),
+            })
+            .collect()
+    }
+
+    /// Picks a provider that wasn't `tried` yet (unless all were) and reserves a
+    /// request slot, returning its index along with the time to wait before sending.
+    fn select(&self, tried: &[usize]) -> (usize, Duration) {
+        let now = Instant::now();
+        let mut state = self.state.lock().expect("poisoned lock");
+        let mut candidates: Vec<_> = (0..self.members.len())
+            .filter(|index| !tried.contains(index))
+            .collect();
+        if candidates.is_empty() {
+            candidates = (0..self.members.len()).collect();
+        }
+        let available: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|&index| state[index].cooldown_until.is_none_or(|until| until <= now))
+            .collect();
+
+        let index = if available.is_empty() {
+            // Everything is cooling down: wait for whichever recovers first.
+            *candidates
+                .iter()
+                .min_by_key(|&&index| state[index].cooldown_until)
+                .expect("candidates non-empty")
+        } else {
+            let mut total = 0.0;
+            let mut best = available[0];
+            for &index in &available {
+                let weight = self.members[index].weight as f64 * state[index].health;
+                total += weight;
+                state[index].current_weight += weight;
+                if state[index].current_weight > state[best].current_weight {
+                    best = index;
+                }
+            }
+            state[best].current_weight -= total;
+            best
+        };
+
+        let ready = state[index]
+            .cooldown_until
+            .map_or(now, |until| until.max(now));
+        let slot = state[index].next_slot.map_or(ready, |slot| slot.max(ready));
+        if let Some(interval) = self.members[index].interval {
+            state[index].next_slot = Some(slot + interval);
+        }
+        state[index].requests += 1;
+        (index, slot - now)
+    }
+
+    /// Updates health with the outcome of a request, where reverts count as success.
+    fn record(&self, index: usize, error: Option<ErrorClass>) {
+        let mut state = self.state.lock().expect("poisoned lock");
+        let member = &mut state[index];
+        match error {
+            None | Some(ErrorClass::Revert) => {
+                member.health = member.health * (1.0 - HEALTH_DECAY) + HEALTH_DECAY;
+                member.consecutive_failures = 0;
+                member.cooldown_until = None;
+            }
+            Some(ErrorClass::RateLimited | ErrorClass::Transport) => {
+                member.health = (member.health * (1.0 - HEALTH_DECAY)).max(MIN_HEALTH);
+                member.failures += 1;
+                member.consecutive_failures += 1;
+                let backoff = self
+                    .options
+                    .base_backoff
+                    .saturating_mul(1 << (member.consecutive_failures - 1).min(16))
+                    .min(self.options.max_backoff);
+                member.cooldown_until = Some(Instant::now() + backoff);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C> JsonRpcClient for PoolTransport<C>
+where
+    C: JsonRpcClient,
+    C::Error: 'static,
+{
+    type Error = PoolError<C::Error>;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let mut tried = vec![];
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let (index, wait) = self.select(&tried);
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            let member = &self.members[index];
+            match member.client.request(method, &params).await {
+                Ok(result) => {
+                    self.record(index, None);
+                    return Ok(result);
+                }
+                Err(err) => {
+                    let class = classify(&err);
+                    self.record(index, Some(class));
+                    if class == ErrorClass::Revert {
+                        return Err(PoolError::Revert(err));
+                    }
+                    if attempts >= self.options.max_attempts {
+                        return Err(PoolError::Exhausted {
+                            attempts,
+                            last: err,
+                        });
+                    }
+                    tracing::debug!(
+                        "{method} failed on {} ({class:?}): {err} - attempt {attempts}",
+                        member.url
+                    );
+                    tried.push(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{MockError, MockProvider, MockResponse},
+        types::U64,
+    };
+
+    fn config(url: &str, weight: u32) -> ProviderConfig {
+        ProviderConfig {
+            url: url.to_string(),
+            weight,
+            requests_per_second: None,
+        }
+    }
+
+    fn mock_pool(weights: &[u32]) -> (PoolTransport<MockProvider>, Vec<MockProvider>) {
+        let mocks: Vec<_> = weights.iter().map(|_| MockProvider::new()).collect();
+        let pool = PoolTransport::with_clients(
+            weights
+                .iter()
+                .zip(&mocks)
+                .enumerate()
+                .map(|(i, (weight, mock))| (config(&format!("mock{i}"), *weight), mock.clone()))
+                .collect(),
+            PoolOptions {
+                max_attempts: 3,
+                base_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(1),
+            },
+        )
+        .unwrap();
+        (pool, mocks)
+    }
+
+    fn request_count(mock: &MockProvider) -> usize {
+        let mut count = 0;
+        while mock.assert_request("eth_getBalance", ["latest"]).is_ok() {
+            count += 1;
+        }
+        count
+    }
+
+    fn rpc_error(code: i64, message: &str) -> JsonRpcError {
+        JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn error_classification() {
+        let error = |code, message| MockError::JsonRpcError(rpc_error(code, message));
+        assert_eq!(
+            classify(&error(3, "execution reverted")),
+            ErrorClass::Revert
+        );
+        assert_eq!(
+            classify(&error(-32000, "execution reverted: not minted")),
+            ErrorClass::Revert
+        );
+        assert_eq!(classify(&error(429, "slow down")), ErrorClass::RateLimited);
+        assert_eq!(
+            classify(&error(-32005, "limit exceeded")),
+            ErrorClass::RateLimited
+        );
+        assert_eq!(
+            classify(&error(-32000, "header not found")),
+            ErrorClass::Transport
+        );
+        assert_eq!(classify(&MockError::EmptyResponses), ErrorClass::Transport);
+    }
+
+    #[test]
+    fn invalid_pools() {
+        assert!(PoolTransport::new(&[], PoolOptions::default()).is_err());
+        assert!(PoolTransport::new(&[config("not a url", 1)], PoolOptions::default()).is_err());
+        assert!(
+            PoolTransport::new(&[config("http://localhost", 0)], PoolOptions::default()).is_err()
+        );
+        assert!(
+            PoolTransport::new(&[config("http://localhost", 1)], PoolOptions::default()).is_ok()
+        );
+    }
+
+    #[test]
+    fn parse_provider_config() {
+        let config: ProviderConfig = "https://rpc.example.com/key;weight=3;rps=10"
+            .parse()
+            .unwrap();
+        assert_eq!(config.url, "https://rpc.example.com/key");
+        assert_eq!(config.weight, 3);
+        assert_eq!(config.requests_per_second, Some(10));
+
+        let config: ProviderConfig = "http://localhost:8545".parse().unwrap();
+        assert_eq!(config.weight, 1);
+        assert_eq!(config.requests_per_second, None);
+
+        assert!("".parse::<ProviderConfig>().is_err());
+        assert!("http://localhost;weight=x"
+            .parse::<ProviderConfig>()
+            .is_err());
+        assert!("http://localhost;timeout=3"
+            .parse::<ProviderConfig>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn weighted_routing() {
+        let (pool, mocks) = mock_pool(&[3, 1]);
+        for mock in &mocks {
+            for _ in 0..4 {
+                mock.push(U64::from(1)).unwrap();
+            }
+        }
+        for _ in 0..4 {
+            let result: U64 = pool.request("eth_getBalance", ["latest"]).await.unwrap();
+            assert_eq!(result, U64::from(1));
+        }
+        assert_eq!(request_count(&mocks[0]), 3);
+        assert_eq!(request_count(&mocks[1]), 1);
+    }
+
+    #[tokio::test]
+    async fn failover_on_transport_error() {
+        // The first provider has no responses, so every request to it fails.
+        let (pool, mocks) = mock_pool(&[2, 1]);
+        mocks[1].push(U64::from(12)).unwrap();
+        let result: U64 = pool.request("eth_getBalance", ["latest"]).await.unwrap();
+        assert_eq!(result, U64::from(12));
+
+        let health = pool.health();
+        assert_eq!((health[0].requests, health[0].failures), (1, 1));
+        assert!(health[0].cooling_down);
+        assert!(health[0].score < health[1].score);
+    }
+
+    #[tokio::test]
+    async fn reverts_are_not_retried() {
+        let (pool, mocks) = mock_pool(&[2, 1]);
+        mocks[0].push_response(MockResponse::Error(rpc_error(3, "execution reverted")));
+        mocks[1].push(U64::from(12)).unwrap();
+        let result: Result<U64, _> = pool.request("eth_getBalance", ["latest"]).await;
+        assert!(matches!(result, Err(PoolError::Revert(_))));
+        assert_eq!(request_count(&mocks[1]), 0);
+        assert_eq!(pool.health()[0].failures, 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (pool, mocks) = mock_pool(&[1, 1]);
+        let result: Result<U64, _> = pool.request("eth_getBalance", ["latest"]).await;
+        assert!(matches!(
+            result,
+            Err(PoolError::Exhausted { attempts: 3, .. })
+        ));
+        assert_eq!(request_count(&mocks[0]) + request_count(&mocks[1]), 3);
+    }
+
+    #[tokio::test]
+    async fn client_leaves_retries_to_pool() {
+        let (pool, mocks) = mock_pool(&[1, 1]);
+        let client = EthersClient::from_pool(pool);
+        assert!(client.get_block(1).await.is_err());
+        let requests = |mock: &MockProvider| {
+            let mut count = 0;
+            while mock
+                .assert_request("eth_getBlockByNumber", ("0x1", true))
+                .is_ok()
+            {
+                count += 1;
+            }
+            count
+        };
+        // Only the pool's attempts, without the client retrying on top.
+        assert_eq!(requests(&mocks[0]) + requests(&mocks[1]), 3);
+    }
+
+    #[test]
+    fn backoff_and_rate_limit() {
+        let (pool, _) = mock_pool(&[1]);
+        for _ in 0..3 {
+            pool.record(0, Some(ErrorClass::Transport));
+        }
+        {
+            let state = pool.state.lock().unwrap();
+            assert_eq!(state[0].consecutive_failures, 3);
+            // 100ms * 2^2
+            let cooldown = state[0].cooldown_until.unwrap() - Instant::now();
+            assert!(cooldown <= Duration::from_millis(400));
+            assert!(cooldown > Duration::from_millis(300));
+        }
+        pool.record(0, None);
+        assert!(!pool.health()[0].cooling_down);
+
+        let limited = PoolTransport::with_clients(
+            vec![(
+                ProviderConfig {
+                    requests_per_second: Some(10),
+                    ..config("mock", 1)
+                },
+                MockProvider::new(),
+            )],
+            PoolOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(limited.select(&[]).1, Duration::ZERO);
+        let (_, wait) = limited.select(&[]);
+        assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100));
+    }
+}
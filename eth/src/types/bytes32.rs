@@ -6,7 +6,7 @@ use diesel::{
     Queryable,
 };
 use ethrpc::types::Digest as H256;
-use serde::Serialize;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use solabi::ethprim::ParseDigestError;
 use std::{
     fmt::{Debug, Display},
@@ -40,6 +40,18 @@ impl Serialize for Bytes32 {
     }
 }
 
+impl<'de> Deserialize<'de> for Bytes32 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Str(&value), &"a 32-byte hex string")
+        })
+    }
+}
+
 impl FromSql<Bytes32, Pg> for Bytes32 {
     fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
         Bytes32::try_from(bytes.as_bytes().to_vec()).map_err(|(message, _)| message.into())
@@ -1,8 +1,17 @@
 use crate::types::{Address, U256};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
+/// Version of the tagged message format (see [Message]).
+const VERSION: u8 = 2;
+
+/// Request for the metadata-retriever.
+///
+/// Single requests are serialized in the original, externally tagged format
+/// (`{"token":{..}}`) understood by every retriever. Batches are only representable in the
+/// versioned format (`{"version":2,"type":"token_batch","tokens":[..]}`), which also
+/// accepts single requests.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "Wire", into = "Wire")]
 pub enum Message {
     Contract {
         address: Address,
@@ -12,6 +21,193 @@ pub enum Message {
         token_id: U256,
         token_uri: Option<String>,
     },
+    ContractBatch {
+        addresses: Vec<Address>,
+    },
+    TokenBatch {
+        tokens: Vec<TokenRequest>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TokenRequest {
+    pub address: Address,
+    pub token_id: U256,
+    pub token_uri: Option<String>,
+}
+
+impl Message {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Contract { .. } => "contract",
+            Self::Token { .. } => "token",
+            Self::ContractBatch { .. } => "contract_batch",
+            Self::TokenBatch { .. } => "token_batch",
+        }
+    }
+
+    /// Single requests of the message.
+    pub fn split(self) -> Vec<Message> {
+        match self {
+            Self::ContractBatch { addresses } => addresses
+                .into_iter()
+                .map(|address| Self::Contract { address })
+                .collect(),
+            Self::TokenBatch { tokens } => tokens
+                .into_iter()
+                .map(|token| Self::Token {
+                    address: token.address,
+                    token_id: token.token_id,
+                    token_uri: token.token_uri,
+                })
+                .collect(),
+            single => vec![single],
+        }
+    }
+
+    /// Groups the (single) requests of `messages` into contract and token batches of up to
+    /// `size` requests. Messages are left as they are for sizes below 2.
+    pub fn batch(messages: Vec<Message>, size: usize) -> Vec<Message> {
+        if size < 2 {
+            return messages;
+        }
+        let mut addresses = vec![];
+        let mut tokens = vec![];
+        for message in messages.into_iter().flat_map(Message::split) {
+            match message {
+                Self::Contract { address } => addresses.push(address),
+                Self::Token {
+                    address,
+                    token_id,
+                    token_uri,
+                } => tokens.push(TokenRequest {
+                    address,
+                    token_id,
+                    token_uri,
+                }),
+                _ => unreachable!("split into single requests"),
+            }
+        }
+        let contracts = addresses.chunks(size).map(|chunk| Self::ContractBatch {
+            addresses: chunk.to_vec(),
+        });
+        let tokens = tokens.chunks(size).map(|chunk| Self::TokenBatch {
+            tokens: chunk.to_vec(),
+        });
+        contracts.chain(tokens).collect()
+    }
+}
+
+/// Serialized forms of [Message].
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum Wire {
+    Versioned {
+        version: u8,
+        #[serde(flatten)]
+        body: Tagged,
+    },
+    Original(Original),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Original {
+    Contract {
+        address: Address,
+    },
+    Token {
+        address: Address,
+        token_id: U256,
+        token_uri: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Tagged {
+    Contract {
+        address: Address,
+    },
+    Token {
+        address: Address,
+        token_id: U256,
+        token_uri: Option<String>,
+    },
+    ContractBatch {
+        addresses: Vec<Address>,
+    },
+    TokenBatch {
+        tokens: Vec<TokenRequest>,
+    },
+}
+
+impl From<Message> for Wire {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Contract { address } => Self::Original(Original::Contract { address }),
+            Message::Token {
+                address,
+                token_id,
+                token_uri,
+            } => Self::Original(Original::Token {
+                address,
+                token_id,
+                token_uri,
+            }),
+            Message::ContractBatch { addresses } => Self::Versioned {
+                version: VERSION,
+                body: Tagged::ContractBatch { addresses },
+            },
+            Message::TokenBatch { tokens } => Self::Versioned {
+                version: VERSION,
+                body: Tagged::TokenBatch { tokens },
+            },
+        }
+    }
+}
+
+impl TryFrom<Wire> for Message {
+    type Error = String;
+
+    fn try_from(wire: Wire) -> Result<Self, Self::Error> {
+        Ok(match wire {
+            Wire::Original(Original::Contract { address })
+            | Wire::Versioned {
+                body: Tagged::Contract { address },
+                version: VERSION,
+            } => Self::Contract { address },
+            Wire::Original(Original::Token {
+                address,
+                token_id,
+                token_uri,
+            })
+            | Wire::Versioned {
+                body:
+                    Tagged::Token {
+                        address,
+                        token_id,
+                        token_uri,
+                    },
+                version: VERSION,
+            } => Self::Token {
+                address,
+                token_id,
+                token_uri,
+            },
+            Wire::Versioned {
+                body: Tagged::ContractBatch { addresses },
+                version: VERSION,
+            } => Self::ContractBatch { addresses },
+            Wire::Versioned {
+                body: Tagged::TokenBatch { tokens },
+                version: VERSION,
+            } => Self::TokenBatch { tokens },
+            Wire::Versioned { version, .. } => {
+                return Err(format!("unsupported message version {version}"))
+            }
+        })
+    }
 }
 
 #[cfg(test)]
@@ -79,4 +275,67 @@ mod tests {
 
         assert_eq!(vec_request, deserialized_request_struct);
     }
+
+    #[test]
+    fn batch_serialization() {
+        let batch = Message::ContractBatch {
+            addresses: vec![Address::from(1), Address::from(2)],
+        };
+        let json = serde_json::to_string(&batch).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":2,"type":"contract_batch","addresses":["0x0000000000000000000000000000000000000001","0x0000000000000000000000000000000000000002"]}"#
+        );
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), batch);
+
+        let batch = Message::TokenBatch {
+            tokens: vec![TokenRequest {
+                address: Address::from(1),
+                token_id: U256::from(7),
+                token_uri: None,
+            }],
+        };
+        let json = serde_json::to_string(&batch).unwrap();
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), batch);
+
+        // Single requests are accepted in the versioned format too.
+        assert_eq!(
+            serde_json::from_str::<Message>(
+                r#"{"version":2,"type":"contract","address":"0x0000000000000000000000000000000000000001"}"#
+            )
+            .unwrap(),
+            Message::Contract {
+                address: Address::from(1)
+            }
+        );
+        assert!(serde_json::from_str::<Message>(
+            r#"{"version":3,"type":"contract_batch","addresses":[]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn batching() {
+        let token = |id: u64| Message::Token {
+            address: Address::from(1),
+            token_id: U256::from(id),
+            token_uri: None,
+        };
+        let contract = |address: u64| Message::Contract {
+            address: Address::from(address),
+        };
+        let messages = vec![token(1), contract(1), token(2), token(3), contract(2)];
+        assert_eq!(Message::batch(messages.clone(), 1), messages);
+
+        let batches = Message::batch(messages.clone(), 2);
+        assert_eq!(
+            batches.iter().map(Message::kind).collect::<Vec<_>>(),
+            vec!["contract_batch", "token_batch", "token_batch"]
+        );
+        let singles: Vec<_> = batches.into_iter().flat_map(Message::split).collect();
+        assert_eq!(
+            singles,
+            vec![contract(1), contract(2), token(1), token(2), token(3)]
+        );
+    }
 }
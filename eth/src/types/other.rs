@@ -1,6 +1,9 @@
 use crate::types::{Address, Bytes32, U256};
 use bigdecimal::BigDecimal;
-use diesel::{self, internal::derives::multiconnection::chrono::NaiveDateTime};
+use diesel::{
+    self,
+    internal::derives::multiconnection::chrono::{DateTime, NaiveDateTime},
+};
 use serde::{Deserialize, Serialize};
 use solabi::ethprim::ParseAddressError;
 use std::collections::HashMap;
@@ -8,7 +11,7 @@ use std::fmt::{Display, Formatter};
 use std::num::ParseIntError;
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct BlockData {
     /// Block Number
     pub number: u64,
@@ -28,8 +31,9 @@ impl std::hash::Hash for BlockData {
 
 impl BlockData {
     pub fn db_time(&self) -> NaiveDateTime {
-        NaiveDateTime::from_timestamp_opt(self.time.try_into().expect("no crazy times"), 0)
+        DateTime::from_timestamp(self.time.try_into().expect("no crazy times"), 0)
             .expect("No crazy times plz")
+            .naive_utc()
     }
 }
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
@@ -77,29 +81,64 @@ impl NftId {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct TxDetails {
     pub hash: Bytes32,
     pub from: Address,
     pub to: Option<Address>,
+    /// Native ETH (in wei) sent with the transaction.
+    pub value: Option<U256>,
+    /// Receipt data: unavailable when only the transaction is known.
+    pub gas_used: Option<u64>,
+    pub effective_gas_price: Option<U256>,
+    /// True if the transaction succeeded.
+    pub status: Option<bool>,
+}
+
+impl TxDetails {
+    /// Price and status are missing from receipts of early blocks.
+    pub fn add_receipt(
+        &mut self,
+        gas_used: u64,
+        effective_gas_price: Option<U256>,
+        status: Option<bool>,
+    ) {
+        self.gas_used = Some(gas_used);
+        self.effective_gas_price = effective_gas_price;
+        self.status = status;
+    }
 }
 
 impl From<ethrpc::types::SignedTransaction> for TxDetails {
     fn from(value: ethrpc::types::SignedTransaction) -> Self {
+        use ethrpc::types::SignedTransaction::*;
+        let amount = match &value {
+            Legacy(tx) => tx.value,
+            Erc2930(tx) => tx.value,
+            Erc1559(tx) => tx.value,
+            Eip4844(tx) => tx.value,
+        };
         TxDetails {
             hash: Bytes32::from(value.hash()),
             from: Address::from(value.from()),
             to: value.to().map(Address::from),
+            value: Some(U256(amount)),
+            ..Default::default()
         }
     }
 }
 
+/// Receipts carry everything but the transaction value.
 impl From<ethers::types::TransactionReceipt> for TxDetails {
     fn from(value: ethers::types::TransactionReceipt) -> Self {
         TxDetails {
             hash: Bytes32::from(value.transaction_hash),
             from: Address::from(value.from),
             to: value.to.map(Address::from),
+            value: None,
+            gas_used: value.gas_used.map(|gas| gas.as_u64()),
+            effective_gas_price: value.effective_gas_price.map(U256::from),
+            status: value.status.map(|status| status.as_u64() == 1),
         }
     }
 }
@@ -110,11 +149,65 @@ impl From<ethers::types::Transaction> for TxDetails {
             hash: Bytes32::from(value.hash),
             from: Address::from(value.from),
             to: value.to.map(Address::from),
+            value: Some(U256::from(value.value)),
+            ..Default::default()
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
+/// Raw log emitted by a contract (as returned by eth_getLogs).
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct EventLog {
+    pub address: Address,
+    pub topics: Vec<Bytes32>,
+    pub data: Vec<u8>,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+}
+
+impl From<ethers::types::Log> for EventLog {
+    fn from(value: ethers::types::Log) -> Self {
+        EventLog {
+            address: Address::from(value.address),
+            topics: value.topics.into_iter().map(Bytes32::from).collect(),
+            data: value.data.to_vec(),
+            block_number: value.block_number.expect("mined log").as_u64(),
+            transaction_index: value.transaction_index.expect("mined log").as_u64(),
+            log_index: value.log_index.expect("mined log").as_u64(),
+        }
+    }
+}
+
+/// Block at which contract state is read.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum BlockTag {
+    #[default]
+    Latest,
+    Number(u64),
+}
+
+impl From<BlockTag> for ethers::types::BlockId {
+    fn from(value: BlockTag) -> Self {
+        ethers::types::BlockId::Number(match value {
+            BlockTag::Latest => ethers::types::BlockNumber::Latest,
+            BlockTag::Number(number) => ethers::types::BlockNumber::Number(number.into()),
+        })
+    }
+}
+
+/// Where and by whom a contract was deployed.
+/// `tx_index` and `deployer` are only known for contracts created directly
+/// by a transaction (i.e. not through a factory contract).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    pub address: Address,
+    pub block: u64,
+    pub tx_index: Option<u64>,
+    pub deployer: Option<Address>,
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct ContractDetails {
     pub address: Address,
     pub name: Option<String>,
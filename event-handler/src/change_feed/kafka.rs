@@ -0,0 +1,42 @@
+use super::{ChangeBatch, ChangeSink, ORDERING_KEY};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use std::time::Duration;
+
+/// Batches share a key, so that they land on (and are consumed from) a single partition in order.
+pub(super) struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaSink {
+    pub(super) fn new(brokers: &str, topic: &str) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .create()?;
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl ChangeSink for KafkaSink {
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+        let payload = serde_json::to_vec(batch)?;
+        let record = FutureRecord::to(&self.topic)
+            .key(ORDERING_KEY)
+            .payload(&payload);
+        self.producer
+            .send(record, Duration::from_secs(30))
+            .await
+            .map_err(|(err, _)| anyhow!("kafka publish to {}: {err}", self.topic))?;
+        Ok(())
+    }
+}
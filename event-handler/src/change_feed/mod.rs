@@ -0,0 +1,471 @@
+//! Feed of the state changes written by each store update, so that downstream services
+//! needn't poll the store. Batches are written in the transaction of the update they describe
+//! and published (in order, at least once) from the store's change outbox.
+use crate::pubsub::PubSubClient;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use data_store::storage::Storage;
+use eth::types::{Address, U256};
+use event_retriever::db_reader::{diesel::BlockRange, models::EventBase};
+use serde::{Deserialize, Serialize};
+use std::{io::Write, str::FromStr, time::Duration};
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+/// Key under which batches are published (where sinks support ordering).
+const ORDERING_KEY: &str = "state-changes";
+/// Maximum number of change batches published per poll.
+const PAGE_SIZE: i64 = 100;
+
+/// ERC721 tokens are reported as transferred, minted or burned
+/// and ERC1155 tokens by the resulting balances of both parties.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Transferred {
+        address: Address,
+        token_id: U256,
+        from: Address,
+        to: Address,
+    },
+    Minted {
+        address: Address,
+        token_id: U256,
+        to: Address,
+    },
+    Burned {
+        address: Address,
+        token_id: U256,
+        from: Address,
+    },
+    Approval {
+        address: Address,
+        token_id: U256,
+        approved: Option<Address>,
+    },
+    ApprovalForAll {
+        address: Address,
+        owner: Address,
+        operator: Address,
+        approved: bool,
+    },
+    Erc1155Balance {
+        address: Address,
+        token_id: U256,
+        owner: Address,
+        /// Balance after the transfer (decimal string).
+        balance: String,
+    },
+    ContractDiscovered {
+        address: Address,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub change: Change,
+}
+
+impl StateChange {
+    pub fn new(base: &EventBase, change: Change) -> Self {
+        Self {
+            block_number: base.block_number,
+            transaction_index: base.transaction_index,
+            log_index: base.log_index,
+            change,
+        }
+    }
+}
+
+/// Changes of a processed block range, ordered by block and log index.
+/// Once published, the store holds all blocks before `end_block` (the checkpoint).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeBatch {
+    pub start_block: i64,
+    pub end_block: i64,
+    pub changes: Vec<StateChange>,
+}
+
+impl ChangeBatch {
+    pub fn new(range: BlockRange, mut changes: Vec<StateChange>) -> Self {
+        // Stable, so that changes of a single log (i.e. batch transfers) keep their order.
+        changes.sort_by_key(|change| (change.block_number, change.log_index));
+        Self {
+            start_block: range.start,
+            end_block: range.end,
+            changes,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ChangeSink: Send + Sync {
+    /// Returns once the batch has been accepted by the sink.
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()>;
+}
+
+/// Publishes the batches of the store's change outbox, oldest first.
+pub struct ChangeFeedPublisher {
+    store: Box<dyn Storage>,
+    sink: Box<dyn ChangeSink>,
+    /// Published batches are kept this long (in seconds) before being pruned.
+    retention_secs: i64,
+}
+
+impl ChangeFeedPublisher {
+    pub fn new(store: Box<dyn Storage>, sink: Box<dyn ChangeSink>, retention_secs: i64) -> Self {
+        Self {
+            store,
+            sink,
+            retention_secs,
+        }
+    }
+
+    pub async fn run(&mut self, poll_secs: u64) -> Result<()> {
+        loop {
+            match self.publish_pending().await {
+                Ok(published) if published as i64 == PAGE_SIZE => continue,
+                Ok(_) => {
+                    let pruned = self
+                        .store
+                        .prune_published_change_batches(self.retention_secs);
+                    if pruned > 0 {
+                        tracing::debug!("pruned {pruned} published change batches");
+                    }
+                }
+                // The failed batch (and those after it) are retried on the next poll.
+                Err(err) => tracing::error!("failed to publish change batches: {err:?}"),
+            }
+            tokio::time::sleep(Duration::from_secs(poll_secs)).await;
+        }
+    }
+
+    /// Publishes the pending batches one by one (stopping at the first failure, to keep them
+    /// in order), marking each as published once accepted by the sink.
+    /// Returns the number of published (or skipped) batches.
+    pub async fn publish_pending(&mut self) -> Result<usize> {
+        let pending = self.store.load_unpublished_change_batches(PAGE_SIZE);
+        let mut published = 0;
+        for (id, value) in pending {
+            match serde_json::from_value::<ChangeBatch>(value) {
+                Ok(batch) => {
+                    tracing::debug!(
+                        "publishing {} changes of blocks [{}, {})",
+                        batch.changes.len(),
+                        batch.start_block,
+                        batch.end_block
+                    );
+                    self.sink.publish(&batch).await.context("publish changes")?;
+                }
+                // Retrying wouldn't help, so the batch is skipped (rather than blocking the feed).
+                Err(err) => tracing::error!("skipping undecodable change batch {id}: {err}"),
+            }
+            self.store.mark_change_batches_published(&[id]);
+            published += 1;
+        }
+        Ok(published)
+    }
+}
+
+/// One JSON line per batch.
+pub struct StdoutSink;
+
+#[async_trait]
+impl ChangeSink for StdoutSink {
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        serde_json::to_writer(&mut stdout, batch)?;
+        writeln!(stdout)?;
+        Ok(())
+    }
+}
+
+/// POSTs each batch as JSON, failing on non-success responses.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChangeSink for WebhookSink {
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(batch)?)
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("change webhook {}", self.url))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChangeSink for PubSubClient {
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+        self.publish_ordered(batch, ORDERING_KEY).await
+    }
+}
+
+/// Parsed from `stdout`, `pubsub://<topic>`, `http(s)://<url>`,
+/// `kafka://<brokers>/<topic>` or `nats://<server>/<subject>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeSinkConfig {
+    Stdout,
+    PubSub { topic: String },
+    Webhook { url: String },
+    Kafka { brokers: String, topic: String },
+    Nats { server: String, subject: String },
+}
+
+impl FromStr for ChangeSinkConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "stdout" {
+            return Ok(Self::Stdout);
+        }
+        let (scheme, rest) = s
+            .split_once("://")
+            .ok_or_else(|| anyhow!("invalid change sink {s}"))?;
+        let target = || {
+            rest.rsplit_once('/')
+                .filter(|(address, name)| !address.is_empty() && !name.is_empty())
+                .map(|(address, name)| (address.to_string(), name.to_string()))
+                .ok_or_else(|| anyhow!("expected {scheme}://<address>/<name>, got {s}"))
+        };
+        match scheme {
+            "pubsub" => Ok(Self::PubSub {
+                topic: rest.to_string(),
+            }),
+            "http" | "https" => Ok(Self::Webhook { url: s.to_string() }),
+            "kafka" => {
+                let (brokers, topic) = target()?;
+                Ok(Self::Kafka { brokers, topic })
+            }
+            "nats" => {
+                let (server, subject) = target()?;
+                Ok(Self::Nats { server, subject })
+            }
+            _ => Err(anyhow!("unsupported change sink {scheme}")),
+        }
+    }
+}
+
+impl ChangeSinkConfig {
+    pub async fn connect(&self) -> Result<Box<dyn ChangeSink>> {
+        let sink: Box<dyn ChangeSink> = match self {
+            Self::Stdout => Box::new(StdoutSink),
+            Self::PubSub { topic } => Box::new(PubSubClient::for_topic(topic).await?),
+            Self::Webhook { url } => Box::new(WebhookSink::new(url)),
+            #[cfg(feature = "kafka")]
+            Self::Kafka { brokers, topic } => Box::new(kafka::KafkaSink::new(brokers, topic)?),
+            #[cfg(feature = "nats")]
+            Self::Nats { server, subject } => {
+                Box::new(nats::NatsSink::connect(server, subject).await?)
+            }
+            #[allow(unreachable_patterns)]
+            unsupported => {
+                return Err(anyhow!(
+                    "{unsupported:?} requires building with the kafka or nats feature"
+                ))
+            }
+        };
+        Ok(sink)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use data_store::{memory_store::MemoryStore, update_cache::UpdateCache};
+    use std::sync::{Arc, Mutex};
+
+    /// Collects published batches.
+    #[derive(Default, Clone)]
+    pub(crate) struct MemorySink(pub Arc<Mutex<Vec<ChangeBatch>>>);
+
+    #[async_trait]
+    impl ChangeSink for MemorySink {
+        async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+            self.0.lock().unwrap().push(batch.clone());
+            Ok(())
+        }
+    }
+
+    /// Fails the first `failures` publications.
+    struct FlakySink {
+        failures: Mutex<usize>,
+        sink: MemorySink,
+    }
+
+    #[async_trait]
+    impl ChangeSink for FlakySink {
+        async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+            {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err(anyhow!("sink unavailable"));
+                }
+            }
+            self.sink.publish(batch).await
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_from_outbox() {
+        let mut store = MemoryStore::default();
+        let batch = |start| {
+            ChangeBatch::new(
+                BlockRange {
+                    start,
+                    end: start + 1,
+                },
+                vec![],
+            )
+        };
+        for value in [
+            serde_json::to_value(batch(1)).unwrap(),
+            serde_json::json!({"unexpected": true}),
+            serde_json::to_value(batch(2)).unwrap(),
+        ] {
+            store.mass_update(UpdateCache {
+                change_batch: Some(value),
+                ..Default::default()
+            });
+        }
+        let sink = MemorySink::default();
+        let flaky = FlakySink {
+            failures: Mutex::new(1),
+            sink: sink.clone(),
+        };
+        let mut publisher = ChangeFeedPublisher::new(Box::new(store), Box::new(flaky), 60);
+
+        // Failed batches stay in the outbox, ahead of later ones.
+        assert!(publisher.publish_pending().await.is_err());
+        assert!(sink.0.lock().unwrap().is_empty());
+        // Undecodable batches are skipped.
+        assert_eq!(publisher.publish_pending().await.unwrap(), 3);
+        assert_eq!(*sink.0.lock().unwrap(), vec![batch(1), batch(2)]);
+        assert_eq!(publisher.publish_pending().await.unwrap(), 0);
+    }
+
+    fn base(block_number: u64, log_index: u64) -> EventBase {
+        EventBase {
+            block_number,
+            log_index,
+            transaction_index: 0,
+            contract_address: Address::from(1),
+        }
+    }
+
+    #[test]
+    fn batch_order() {
+        let discovered = |block, log| {
+            StateChange::new(
+                &base(block, log),
+                Change::ContractDiscovered {
+                    address: Address::from(log),
+                },
+            )
+        };
+        let batch = ChangeBatch::new(
+            BlockRange { start: 1, end: 3 },
+            vec![discovered(2, 0), discovered(1, 5), discovered(1, 2)],
+        );
+        let order: Vec<_> = batch
+            .changes
+            .iter()
+            .map(|change| (change.block_number, change.log_index))
+            .collect();
+        assert_eq!(order, vec![(1, 2), (1, 5), (2, 0)]);
+        assert_eq!((batch.start_block, batch.end_block), (1, 3));
+    }
+
+    #[test]
+    fn change_serialization() {
+        let change = StateChange::new(
+            &base(1, 2),
+            Change::Minted {
+                address: Address::from(1),
+                token_id: U256::from(3),
+                to: Address::from(4),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({
+                "block_number": 1,
+                "transaction_index": 0,
+                "log_index": 2,
+                "change": {"minted": {
+                    "address": "0x0000000000000000000000000000000000000001",
+                    "token_id": "3",
+                    "to": "0x0000000000000000000000000000000000000004",
+                }}
+            })
+        );
+        let json = serde_json::to_string(&change).unwrap();
+        assert_eq!(serde_json::from_str::<StateChange>(&json).unwrap(), change);
+    }
+
+    #[test]
+    fn sink_config() {
+        assert_eq!(
+            "stdout".parse::<ChangeSinkConfig>().unwrap(),
+            ChangeSinkConfig::Stdout
+        );
+        assert_eq!(
+            "pubsub://changes".parse::<ChangeSinkConfig>().unwrap(),
+            ChangeSinkConfig::PubSub {
+                topic: "changes".to_string()
+            }
+        );
+        assert_eq!(
+            "https://example.com/hook"
+                .parse::<ChangeSinkConfig>()
+                .unwrap(),
+            ChangeSinkConfig::Webhook {
+                url: "https://example.com/hook".to_string()
+            }
+        );
+        assert_eq!(
+            "kafka://b1:9092,b2:9092/changes"
+                .parse::<ChangeSinkConfig>()
+                .unwrap(),
+            ChangeSinkConfig::Kafka {
+                brokers: "b1:9092,b2:9092".to_string(),
+                topic: "changes".to_string()
+            }
+        );
+        assert_eq!(
+            "nats://localhost:4222/indexer.changes"
+                .parse::<ChangeSinkConfig>()
+                .unwrap(),
+            ChangeSinkConfig::Nats {
+                server: "localhost:4222".to_string(),
+                subject: "indexer.changes".to_string()
+            }
+        );
+        assert!("kafka://brokers".parse::<ChangeSinkConfig>().is_err());
+        assert!("file:///tmp/changes".parse::<ChangeSinkConfig>().is_err());
+        assert!("changes".parse::<ChangeSinkConfig>().is_err());
+    }
+}
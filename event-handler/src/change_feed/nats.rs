@@ -0,0 +1,28 @@
+use super::{ChangeBatch, ChangeSink};
+use anyhow::Result;
+use async_trait::async_trait;
+
+pub(super) struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+}
+
+impl NatsSink {
+    pub(super) async fn connect(server: &str, subject: &str) -> Result<Self> {
+        Ok(Self {
+            client: async_nats::connect(server).await?,
+            subject: subject.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl ChangeSink for NatsSink {
+    /// Flushes after each batch, so that it has been written to the server before returning.
+    async fn publish(&self, batch: &ChangeBatch) -> Result<()> {
+        let payload = bytes::Bytes::from(serde_json::to_vec(batch)?);
+        self.client.publish(self.subject.clone(), payload).await?;
+        self.client.flush().await?;
+        Ok(())
+    }
+}
@@ -1,13 +1,14 @@
-use crate::config::ChainDataSource;
+use crate::{change_feed::ChangeSinkConfig, config::ChainDataSource, transport::TransportConfig};
 
-use eth::types::Address;
+use eth::{rpc::pool::ProviderConfig, types::Address};
+use std::path::PathBuf;
 use url::Url;
 
 #[derive(Debug, clap::Parser)]
 pub struct Args {
-    /// Source database connection string.
-    #[clap(long, env)]
-    pub source_url: Url,
+    /// Source database connection string (not used with an event dump).
+    #[clap(long, env, required_unless_present = "event_dump")]
+    pub source_url: Option<Url>,
 
     /// Store database connection string.
     #[clap(long, env)]
@@ -17,9 +18,14 @@ pub struct Args {
     #[clap(long, env)]
     pub db_schema: String,
 
-    /// The Ethereum RPC endpoint.
-    #[clap(long, env)]
-    pub node_url: Url,
+    /// The Ethereum RPC endpoint (not used with node providers).
+    #[clap(long, env, required_unless_present = "node_providers")]
+    pub node_url: Option<Url>,
+
+    /// Comma separated Ethereum RPC endpoints to balance requests over, instead of `node-url`:
+    /// <url>[;weight=<n>][;rps=<max requests per second>].
+    #[clap(long, env, value_delimiter = ',')]
+    pub node_providers: Vec<ProviderConfig>,
 
     /// The log filter.
     #[clap(long, env, default_value = "debug")]
@@ -37,8 +43,8 @@ pub struct Args {
     #[clap(long, env, default_value = "1000")]
     pub uri_retry_blocks: i64,
 
-    /// Wait time between buffered requests in milliseconds (Eth Rpc)
-    #[clap(long, env, default_value = "20")]
+    /// Wait time between buffered requests in milliseconds (Eth Rpc, not used with node providers)
+    #[clap(long, env, default_value = "20", conflicts_with = "node_providers")]
     pub node_batch_delay: u64,
 
     /// Include to skip additional on-chain data fetching
@@ -49,7 +55,109 @@ pub struct Args {
     #[clap(long, env, use_value_delimiter = true)]
     pub token_avoid_list: Vec<Address>,
 
+    /// Include to decode marketplace sales (requires eth_getLogs on the node)
+    #[clap(long, env)]
+    pub detect_sales: bool,
+
+    /// Number of contract reads aggregated per Multicall3 call (0 disables aggregation)
+    #[clap(long, env, default_value = "100")]
+    pub multicall_chunk_size: usize,
+
     /// Wait time for new finalized blocks
     #[clap(long, env, default_value = "180")]
     pub arak_poll_frequency: u64,
+
+    /// Process the events of a dump file (JSONL or Parquet) instead of those in the source
+    /// database, starting from its first block. Block data is read from the dump unless
+    /// `chain-source` is node.
+    #[clap(long, env)]
+    pub event_dump: Option<PathBuf>,
+
+    /// Where to publish the changes of each store update: stdout, pubsub://<topic>,
+    /// http(s)://<webhook>, kafka://<brokers>/<topic> or nats://<server>/<subject>.
+    #[clap(long, env)]
+    pub change_sink: Option<ChangeSinkConfig>,
+
+    /// Transport of metadata requests: pubsub://<topic>, http(s)://<retriever>/messages,
+    /// redis://<host:port>/<stream> or nats://<server>/<subject>.
+    /// Defaults to the PubSub topic `PUBSUB_TOPIC_ID`.
+    #[clap(long, env)]
+    pub metadata_transport: Option<TransportConfig>,
+
+    /// Wait time (in seconds) between polls of the metadata request and change outboxes.
+    #[clap(long, env, default_value = "5")]
+    pub outbox_poll_secs: u64,
+
+    /// How long (in hours) published metadata requests and changes are kept in their outboxes.
+    #[clap(long, env, default_value = "24")]
+    pub outbox_retention_hours: i64,
+
+    /// Number of tokens (or contracts) per published metadata request. Batches (of more than one)
+    /// are only understood by retrievers supporting version 2 messages.
+    #[clap(long, env, default_value = "1")]
+    pub metadata_batch_size: usize,
+
+    /// Seconds after which unanswered metadata requests are repeated.
+    #[clap(long, env, default_value = "3600")]
+    pub fetch_stale_secs: i64,
+
+    /// Seconds before a failed metadata fetch is retried (doubled on every further attempt).
+    #[clap(long, env, default_value = "600")]
+    pub fetch_retry_secs: i64,
+
+    /// Failed metadata fetches are given up on after this many attempts.
+    #[clap(long, env, default_value = "5")]
+    pub fetch_max_attempts: i32,
+
+    /// Seconds after which fetched metadata and ABIs are requested again (never by default).
+    #[clap(long, env)]
+    pub metadata_refresh_secs: Option<i64>,
+
+    /// Maintenance task to run instead of event processing.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Rewrite the time of stored blocks in [start, end) from the given chain source.
+    RepairBlocks {
+        #[clap(long)]
+        start: i64,
+        #[clap(long)]
+        end: i64,
+        #[clap(long, value_enum, default_value = "node")]
+        source: ChainDataSource,
+    },
+    /// Compare Database and Node chain data on a sample of blocks in [start, end).
+    CheckBlocks {
+        #[clap(long)]
+        start: i64,
+        #[clap(long)]
+        end: i64,
+        /// Number of blocks to compare.
+        #[clap(long, default_value = "20")]
+        samples: u64,
+    },
+    /// Write the events in [start, end), with their block data, to a dump file
+    /// (Parquet for `.parquet` files, JSONL otherwise).
+    ExportEvents {
+        #[clap(long)]
+        start: i64,
+        #[clap(long)]
+        end: i64,
+        #[clap(long)]
+        output: PathBuf,
+        #[clap(long, value_enum, default_value = "database")]
+        source: ChainDataSource,
+    },
+    /// Attempt due webhook deliveries (signed POSTs, retried with exponential backoff).
+    DeliverWebhooks {
+        /// Wait time when no deliveries are due (in seconds).
+        #[clap(long, default_value = "10")]
+        poll_secs: u64,
+        /// Deliveries are failed after this many attempts.
+        #[clap(long, default_value = "8")]
+        max_attempts: i32,
+    },
 }
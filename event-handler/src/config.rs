@@ -1,4 +1,5 @@
 use clap::ValueEnum;
+use data_store::fetch_state::FetchPolicy;
 use eth::types::Address;
 use serde::Deserialize;
 use std::collections::HashSet;
@@ -27,6 +28,16 @@ pub struct HandlerConfig {
     pub batch_delay: u64,
     /// List of Token Contract addresses to avoid making tokenUri requests for.
     pub token_avoid_list: HashSet<Address>,
+    /// True when marketplace sales should be decoded from node logs.
+    pub detect_sales: bool,
+    /// Number of contract reads aggregated per Multicall3 call (0 disables aggregation).
+    pub multicall_chunk_size: usize,
+    /// True when metadata requests should be written to the store outbox (for the outbox publisher).
+    #[serde(default)]
+    pub queue_metadata_requests: bool,
+    /// When tokens and contracts are requested again from the metadata-retriever.
+    #[serde(default)]
+    pub fetch_policy: FetchPolicy,
 }
 
 impl HandlerConfig {
@@ -0,0 +1,126 @@
+use eth::types::{BlockData, TxDetails};
+use std::fmt::{Display, Formatter};
+
+/// A disagreement between the Database and Node chain data sources on a single block.
+#[derive(Debug, PartialEq)]
+pub struct BlockMismatch {
+    pub block: u64,
+    pub reason: String,
+}
+
+impl Display for BlockMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "block {}: {}", self.block, self.reason)
+    }
+}
+
+/// Evenly spaced sample of (at most) `size` blocks from the half-open range `[start, end)`.
+pub fn sample_blocks(start: u64, end: u64, size: u64) -> Vec<u64> {
+    let width = end.saturating_sub(start);
+    if width == 0 || size == 0 {
+        return vec![];
+    }
+    let step = (width / size).max(1);
+    (start..end)
+        .step_by(step as usize)
+        .take(size as usize)
+        .collect()
+}
+
+/// Values may legitimately be missing from either source (i.e. no receipt).
+fn disagree<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+fn compare_transactions(index: u64, db: &TxDetails, node: &TxDetails) -> Option<String> {
+    if (db.hash, db.from, db.to) != (node.hash, node.from, node.to) {
+        return Some(format!("transaction {index} differs: {db:?} != {node:?}"));
+    }
+    if disagree(db.value, node.value)
+        || disagree(db.gas_used, node.gas_used)
+        || disagree(db.effective_gas_price, node.effective_gas_price)
+        || disagree(db.status, node.status)
+    {
+        return Some(format!(
+            "transaction {index} receipt differs: {db:?} != {node:?}"
+        ));
+    }
+    None
+}
+
+/// Compares block time and transactions, returning a description of every difference found.
+/// Arak only stores transactions emitting indexed events, so only those are compared.
+pub fn compare_blocks(db: &BlockData, node: &BlockData) -> Vec<BlockMismatch> {
+    let mismatch = |reason: String| BlockMismatch {
+        block: db.number,
+        reason,
+    };
+    let mut mismatches = vec![];
+    if db.time != node.time {
+        mismatches.push(mismatch(format!("time {} != {}", db.time, node.time)));
+    }
+    let mut indices: Vec<_> = db.transactions.keys().collect();
+    indices.sort();
+    for index in indices {
+        let reason = match node.transactions.get(index) {
+            Some(node_tx) => compare_transactions(*index, &db.transactions[index], node_tx),
+            None => Some(format!("transaction {index} missing from node")),
+        };
+        mismatches.extend(reason.map(mismatch));
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth::types::{Address, Bytes32, U256};
+    use std::collections::HashMap;
+
+    #[test]
+    fn sampling() {
+        assert_eq!(sample_blocks(10, 20, 5), vec![10, 12, 14, 16, 18]);
+        assert_eq!(sample_blocks(10, 13, 5), vec![10, 11, 12]);
+        assert!(sample_blocks(10, 10, 5).is_empty());
+        assert!(sample_blocks(10, 20, 0).is_empty());
+    }
+
+    #[test]
+    fn block_comparison() {
+        let tx = TxDetails {
+            hash: Bytes32::from(1),
+            from: Address::from(1),
+            to: Some(Address::from(2)),
+            value: Some(U256::from(5)),
+            ..Default::default()
+        };
+        let mut db = BlockData {
+            number: 1,
+            time: 100,
+            transactions: HashMap::from([(0, tx)]),
+        };
+        // Missing receipt data is not a mismatch.
+        let mut node = db.clone();
+        node.transactions
+            .get_mut(&0)
+            .unwrap()
+            .add_receipt(21_000, Some(U256::from(1)), Some(true));
+        assert!(compare_blocks(&db, &node).is_empty());
+
+        // Transactions without indexed events are only known to the node.
+        node.transactions.insert(1, tx);
+        assert!(compare_blocks(&db, &node).is_empty());
+
+        db.transactions.insert(2, tx);
+        node.time = 1;
+        node.transactions.get_mut(&0).unwrap().value = Some(U256::from(6));
+        let reasons: Vec<_> = compare_blocks(&db, &node)
+            .into_iter()
+            .map(|mismatch| mismatch.reason)
+            .collect();
+        assert_eq!(reasons.len(), 3);
+        assert_eq!(reasons[0], "time 100 != 1");
+        assert!(reasons[1].starts_with("transaction 0 receipt differs"));
+        assert_eq!(reasons[2], "transaction 2 missing from node");
+    }
+}
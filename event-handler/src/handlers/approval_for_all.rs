@@ -1,3 +1,4 @@
+use crate::change_feed::Change;
 use crate::handlers::EventHandler;
 use crate::processor::EventProcessor;
 use data_store::models::ApprovalId;
@@ -34,6 +35,15 @@ impl EventHandler<ApprovalForAll> for EventProcessor {
         approval.operator = event.operator;
 
         self.updates.approval_for_alls.insert(approval_id, approval);
+        self.record_change(
+            &base,
+            Change::ApprovalForAll {
+                address: base.contract_address,
+                owner: event.owner,
+                operator: event.operator,
+                approved: event.approved,
+            },
+        );
     }
 }
 
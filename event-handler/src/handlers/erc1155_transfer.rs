@@ -1,5 +1,7 @@
+use crate::change_feed::Change;
 use crate::handlers::EventHandler;
 use crate::processor::EventProcessor;
+use crate::webhooks::Transfer;
 use eth::types::{Address, TxDetails};
 use event_retriever::db_reader::models::{Erc1155TransferSingle, EventBase};
 
@@ -36,6 +38,15 @@ impl EventHandler<Erc1155TransferSingle> for EventProcessor {
                         .load_or_initialize_erc1155_owner(&base, &token.id(), from),
                 };
             sender.decrease_balance(transfer.value);
+            self.record_change(
+                &base,
+                Change::Erc1155Balance {
+                    address: contract,
+                    token_id: transfer.id,
+                    owner: from,
+                    balance: sender.balance.to_string(),
+                },
+            );
             self.updates
                 .multi_token_owners
                 .insert((token.id(), contract, from), sender);
@@ -52,11 +63,26 @@ impl EventHandler<Erc1155TransferSingle> for EventProcessor {
                     .load_or_initialize_erc1155_owner(&base, &token.id(), to),
             };
         recipient.increase_balance(transfer.value);
+        if to != Address::zero() {
+            self.record_change(
+                &base,
+                Change::Erc1155Balance {
+                    address: contract,
+                    token_id: transfer.id,
+                    owner: to,
+                    balance: recipient.balance.to_string(),
+                },
+            );
+        }
         self.updates
             .multi_token_owners
             .insert((token.id(), contract, to), recipient);
 
         self.updates.multi_tokens.insert(token.id(), token);
+        self.record_transfer(Transfer {
+            amount: Some(transfer.value),
+            ..Transfer::new(&base, transfer.id, from, to)
+        });
     }
 }
 
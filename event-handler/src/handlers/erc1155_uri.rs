@@ -1,5 +1,6 @@
 use crate::handlers::EventHandler;
 use crate::processor::EventProcessor;
+use data_store::models::TokenUriObservation;
 use eth::types::TxDetails;
 use event_retriever::db_reader::models::{Erc1155Uri, EventBase};
 
@@ -9,6 +10,9 @@ impl EventHandler<Erc1155Uri> for EventProcessor {
             Some(token) => token,
             None => return,
         };
+        self.updates
+            .uri_observations
+            .push(TokenUriObservation::event(&base, uri.id, &uri.value));
         token.token_uri = Some(uri.value);
         self.updates.multi_tokens.insert(token.id(), token);
     }
@@ -88,5 +92,10 @@ mod tests {
             },
             "idempotency"
         );
+        // Only the applied event is recorded.
+        assert_eq!(
+            handler.updates.uri_observations,
+            [TokenUriObservation::event(&base, id, "https://my-website.com")]
+        );
     }
 }
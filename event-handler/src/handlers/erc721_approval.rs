@@ -1,3 +1,4 @@
+use crate::change_feed::Change;
 use crate::handlers::EventHandler;
 use crate::processor::EventProcessor;
 use eth::types::{Address, NftId, TxDetails};
@@ -33,6 +34,14 @@ impl EventHandler<Erc721Approval> for EventProcessor {
         nft.last_update_tx = base.transaction_index as i64;
         nft.last_update_log_index = base.log_index as i64;
         self.updates.nfts.insert(nft_id, nft);
+        self.record_change(
+            &base,
+            Change::Approval {
+                address: nft_id.address,
+                token_id: nft_id.token_id,
+                approved: (approval.approved != Address::zero()).then_some(approval.approved),
+            },
+        );
     }
 }
 
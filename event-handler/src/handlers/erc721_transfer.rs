@@ -1,5 +1,7 @@
+use crate::change_feed::Change;
 use crate::handlers::EventHandler;
 use crate::processor::EventProcessor;
+use crate::webhooks::Transfer;
 use eth::types::{Address, NftId, TxDetails};
 use event_retriever::db_reader::models::{Erc721Transfer, EventBase};
 
@@ -48,6 +50,30 @@ impl EventHandler<Erc721Transfer> for EventProcessor {
             nft.last_transfer_tx = Some(tx_index);
             nft.approved = None;
             self.updates.nfts.insert(nft_id, nft);
+
+            let (address, token_id) = (nft_id.address, nft_id.token_id);
+            let change = if transfer.from == Address::zero() {
+                Change::Minted {
+                    address,
+                    token_id,
+                    to: transfer.to,
+                }
+            } else if transfer.to == Address::zero() {
+                Change::Burned {
+                    address,
+                    token_id,
+                    from: transfer.from,
+                }
+            } else {
+                Change::Transferred {
+                    address,
+                    token_id,
+                    from: transfer.from,
+                    to: transfer.to,
+                }
+            };
+            self.record_change(&base, change);
+            self.record_transfer(Transfer::new(&base, token_id, transfer.from, transfer.to));
         }
     }
 }
@@ -0,0 +1,107 @@
+use crate::handlers::EventHandler;
+use crate::processor::EventProcessor;
+use eth::types::TxDetails;
+use event_retriever::db_reader::models::{EventBase, OwnershipTransferred};
+
+impl EventHandler<OwnershipTransferred> for EventProcessor {
+    fn handle_event(&mut self, base: EventBase, event: OwnershipTransferred, tx: &TxDetails) {
+        let address = base.contract_address;
+        let mut contract_owner = match self.updates.contract_owners.remove(&address) {
+            Some(owner) => owner,
+            None => self.store.load_or_initialize_contract_owner(address),
+        };
+        if contract_owner.event_applied(&base) {
+            tracing::warn!(
+                "skipping attempt to replay event {:?} at tx {:?} on {:?}",
+                base,
+                tx.hash,
+                address
+            );
+            // Put the owner back in cache!
+            self.updates.contract_owners.insert(address, contract_owner);
+            return;
+        }
+
+        contract_owner.last_update_block = base.block_number as i64;
+        contract_owner.last_update_tx = base.transaction_index as i64;
+        contract_owner.last_update_log_index = base.log_index as i64;
+
+        contract_owner.owner = event.new_owner;
+
+        self.updates.contract_owners.insert(address, contract_owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::test_util::{setup_data, SetupData};
+    use data_store::models::ContractOwner;
+    use eth::types::Address;
+
+    #[tokio::test]
+    async fn ownership_transferred() {
+        let SetupData {
+            mut handler,
+            token_id: _,
+            token: _,
+            mut base,
+            tx,
+        } = setup_data();
+
+        let first_owner = Address::from(3);
+        let second_owner = Address::from(4);
+        let address = base.contract_address;
+
+        handler.handle_event(
+            base,
+            OwnershipTransferred {
+                previous_owner: Address::zero(),
+                new_owner: first_owner,
+            },
+            &tx,
+        );
+
+        assert_eq!(
+            handler.updates.contract_owners.get(&address).unwrap(),
+            &ContractOwner {
+                contract_address: address,
+                owner: first_owner,
+                last_update_block: base.block_number as i64,
+                last_update_tx: base.transaction_index as i64,
+                last_update_log_index: base.log_index as i64,
+            },
+            "initial owner"
+        );
+
+        base.log_index += 1;
+        handler.handle_event(
+            base,
+            OwnershipTransferred {
+                previous_owner: first_owner,
+                new_owner: second_owner,
+            },
+            &tx,
+        );
+        assert_eq!(
+            handler.updates.contract_owners.get(&address).unwrap().owner,
+            second_owner,
+            "ownership transfer"
+        );
+
+        // Replay protection -- failed attempt to revert ownership.
+        handler.handle_event(
+            base,
+            OwnershipTransferred {
+                previous_owner: second_owner,
+                new_owner: first_owner,
+            },
+            &tx,
+        );
+        assert_eq!(
+            handler.updates.contract_owners.get(&address).unwrap().owner,
+            second_owner,
+            "idempotency"
+        );
+    }
+}
@@ -1,5 +1,11 @@
+pub mod change_feed;
 pub mod cli;
 pub mod config;
+pub mod consistency;
 mod handlers;
+pub mod outbox;
 pub mod processor;
 pub mod pubsub;
+pub mod sales;
+pub mod transport;
+pub mod webhooks;
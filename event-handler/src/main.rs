@@ -1,10 +1,21 @@
 extern crate event_handler;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
+use data_store::{fetch_state::FetchPolicy, store::DataStore};
 use event_handler::{
-    cli::Args, config::HandlerConfig, processor::EventProcessor, pubsub::PubSubClient,
+    change_feed::ChangeFeedPublisher,
+    cli::{Args, Command},
+    config::HandlerConfig,
+    outbox::OutboxPublisher,
+    processor::{node_client, EventProcessor},
+    pubsub::PubSubClient,
+    transport::MessagePublisher,
+    webhooks::WebhookWorker,
 };
+use event_retriever::{db_reader::diesel::BlockRange, dump::EventDump};
+use tokio::task::JoinHandle;
+use url::Url;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -16,7 +27,17 @@ async fn main() -> Result<()> {
         .with_ansi(false)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
-    let config = HandlerConfig {
+    // Webhook delivery only needs the store.
+    if let Some(Command::DeliverWebhooks {
+        poll_secs,
+        max_attempts,
+    }) = args.command
+    {
+        let store = DataStore::new(args.store_url.as_str(), &args.db_schema)?;
+        tracing::info!("delivering webhooks (up to {max_attempts} attempts)");
+        return WebhookWorker::new(store, max_attempts).run(poll_secs).await;
+    }
+    let mut config = HandlerConfig {
         chain_data_source: args.chain_source,
         page_size: args.page_size,
         fetch_node_data: !args.skip_node_fetching,
@@ -24,17 +45,176 @@ async fn main() -> Result<()> {
         uri_retry_blocks: args.uri_retry_blocks,
         batch_delay: args.node_batch_delay,
         token_avoid_list: args.token_avoid_list.into_iter().collect(),
+        detect_sales: args.detect_sales,
+        multicall_chunk_size: args.multicall_chunk_size,
+        queue_metadata_requests: false,
+        fetch_policy: FetchPolicy {
+            stale_secs: args.fetch_stale_secs,
+            retry_secs: args.fetch_retry_secs,
+            max_attempts: args.fetch_max_attempts,
+            refresh_secs: args.metadata_refresh_secs,
+        },
+    };
+    // Publishers running alongside the processor, which stops with the first of them to stop.
+    let mut background: Vec<(&str, JoinHandle<Result<()>>)> = vec![];
+    // Maintenance commands and event dumps don't request metadata.
+    if let (None, None) = (&args.command, &args.event_dump) {
+        let publisher = match &args.metadata_transport {
+            Some(transport) => {
+                tracing::info!("posting metadata requests to {transport:?}");
+                transport.connect().await?
+            }
+            None => Box::new(PubSubClient::from_env().await?) as Box<dyn MessagePublisher>,
+        };
+        let mut outbox = OutboxPublisher::new(
+            DataStore::new(args.store_url.as_str(), &config.db_schema)?,
+            publisher,
+            args.outbox_retention_hours * 3600,
+            args.metadata_batch_size,
+        );
+        let poll_secs = args.outbox_poll_secs;
+        background.push((
+            "metadata outbox",
+            tokio::spawn(async move { outbox.run(poll_secs).await }),
+        ));
+        config.queue_metadata_requests = true;
+    }
+    // Changes are written along with each update, and published from the store.
+    let mut change_feed = match &args.change_sink {
+        Some(sink) => {
+            tracing::info!("publishing state changes to {sink:?}");
+            Some(ChangeFeedPublisher::new(
+                Box::new(DataStore::new(args.store_url.as_str(), &config.db_schema)?),
+                sink.connect().await?,
+                args.outbox_retention_hours * 3600,
+            ))
+        }
+        None => None,
     };
     tracing::info!("initializing event processor with {config:?}");
-    let mut handler = EventProcessor::new(
-        args.source_url.as_str(),
-        args.store_url.as_str(),
-        args.node_url.as_str(),
-        config,
-        Some(PubSubClient::from_env().await?),
+    if !args.node_providers.is_empty() {
+        tracing::info!(
+            "balancing node requests over {} providers",
+            args.node_providers.len()
+        );
+    }
+    let eth_client = node_client(
+        args.node_url.as_ref().map(Url::as_str),
+        &args.node_providers,
+        &config,
     )?;
+    let (mut handler, dump_start) = match &args.event_dump {
+        Some(path) => {
+            let dump = EventDump::read(path)?;
+            tracing::info!(
+                "loaded {} events from {}",
+                dump.events.len(),
+                path.display()
+            );
+            let start = dump.first_block().unwrap_or_default() as i64;
+            let handler = EventProcessor::with_source(
+                Box::new(dump),
+                args.store_url.as_str(),
+                eth_client,
+                config,
+            )?;
+            (handler, Some(start))
+        }
+        None => {
+            let handler = EventProcessor::new(
+                args.source_url
+                    .expect("required without event dump")
+                    .as_str(),
+                args.store_url.as_str(),
+                eth_client,
+                config,
+            )?;
+            (handler, None)
+        }
+    };
+    if change_feed.is_some() {
+        handler = handler.with_change_feed();
+    }
+
+    match args.command {
+        None if dump_start.is_some() => {
+            let end = handler.run_inner(dump_start.expect("checked")).await?;
+            tracing::info!("processed event dump up to {end}");
+            if let Some(change_feed) = change_feed.as_mut() {
+                while change_feed.publish_pending().await? > 0 {}
+            }
+            Ok(())
+        }
+        None => {
+            if let Some(mut change_feed) = change_feed {
+                let poll_secs = args.outbox_poll_secs;
+                background.push((
+                    "change feed",
+                    tokio::spawn(async move { change_feed.run(poll_secs).await }),
+                ));
+            }
+            let start_from = handler.store.get_processed_block() + 1;
+            tracing::info!("beginning event processor from {start_from}");
+            tokio::select! {
+                result = handler.run(start_from, args.arak_poll_frequency) => result,
+                err = supervise(background) => Err(err),
+            }
+        }
+        Some(Command::RepairBlocks { start, end, source }) => {
+            let repaired = handler
+                .repair_blocks(BlockRange { start, end }, &source)
+                .await?;
+            tracing::info!("repaired {repaired} blocks from {source:?}");
+            Ok(())
+        }
+        Some(Command::CheckBlocks {
+            start,
+            end,
+            samples,
+        }) => {
+            let mismatches = handler
+                .check_blocks(BlockRange { start, end }, samples)
+                .await?;
+            for mismatch in &mismatches {
+                tracing::warn!("{mismatch}");
+            }
+            tracing::info!("found {} mismatches in {samples} blocks", mismatches.len());
+            Ok(())
+        }
+        Some(Command::ExportEvents {
+            start,
+            end,
+            output,
+            source,
+        }) => {
+            let dump = handler
+                .export_events(BlockRange { start, end }, &source)
+                .await?;
+            dump.write(&output)?;
+            tracing::info!(
+                "exported {} events in {} blocks to {}",
+                dump.events.len(),
+                dump.blocks.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        Some(Command::DeliverWebhooks { .. }) => unreachable!("handled before processing"),
+    }
+}
 
-    let start_from = handler.store.get_processed_block() + 1;
-    tracing::info!("beginning event processor from {start_from}");
-    handler.run(start_from, args.arak_poll_frequency).await
+/// Waits for the first background task to stop: they run for the lifetime of the process, so
+/// this is always an error (the process exits, to be restarted).
+async fn supervise(tasks: Vec<(&str, JoinHandle<Result<()>>)>) -> anyhow::Error {
+    if tasks.is_empty() {
+        return std::future::pending().await;
+    }
+    let (names, handles): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+    let (result, index, _) = futures::future::select_all(handles).await;
+    let name = names[index];
+    match result {
+        Ok(Ok(())) => anyhow!("{name} stopped"),
+        Ok(Err(err)) => err.context(format!("{name} failed")),
+        Err(err) => anyhow!("{name} panicked: {err}"),
+    }
 }
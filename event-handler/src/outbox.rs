@@ -0,0 +1,69 @@
+//! Drains the store's message outbox: metadata requests are written in the transaction of the
+//! updates they refer to and published from here (at least once), so that neither a crash after
+//! the commit nor a transport failure loses them.
+use crate::transport::MessagePublisher;
+use anyhow::Result;
+use data_store::store::DataStore;
+use eth::types::Message;
+use std::time::Duration;
+
+/// Maximum number of outbox messages published at once.
+const PAGE_SIZE: i64 = 1000;
+
+pub struct OutboxPublisher {
+    store: DataStore,
+    publisher: Box<dyn MessagePublisher>,
+    /// Sent messages are kept this long (in seconds) before being pruned.
+    retention_secs: i64,
+    /// Requests per published message (see [Message::batch]).
+    batch_size: usize,
+}
+
+impl OutboxPublisher {
+    pub fn new(
+        store: DataStore,
+        publisher: Box<dyn MessagePublisher>,
+        retention_secs: i64,
+        batch_size: usize,
+    ) -> Self {
+        Self {
+            store,
+            publisher,
+            retention_secs,
+            batch_size,
+        }
+    }
+
+    pub async fn run(&mut self, poll_secs: u64) -> Result<()> {
+        loop {
+            match self.publish_pending().await {
+                Ok(published) if published as i64 == PAGE_SIZE => continue,
+                Ok(_) => {
+                    let pruned = self.store.prune_sent_messages(self.retention_secs);
+                    if pruned > 0 {
+                        tracing::debug!("pruned {pruned} sent outbox messages");
+                    }
+                }
+                // Unsent messages are retried on the next poll.
+                Err(err) => tracing::error!("failed to publish outbox messages: {err:?}"),
+            }
+            tokio::time::sleep(Duration::from_secs(poll_secs)).await;
+        }
+    }
+
+    /// Publishes the oldest unsent messages, marking them as sent once accepted by the transport.
+    /// Returns the number of published messages.
+    pub async fn publish_pending(&mut self) -> Result<usize> {
+        let pending = self.store.load_unsent_messages(PAGE_SIZE);
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let (ids, messages): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+        self.publisher
+            .publish(&Message::batch(messages, self.batch_size))
+            .await?;
+        self.store.mark_messages_sent(&ids);
+        tracing::info!("published {} outbox messages", ids.len());
+        Ok(ids.len())
+    }
+}
@@ -24,17 +24,21 @@ impl PubSubClient {
         PubSubClient::new(client, "test-topic")
     }
     pub async fn from_env() -> Result<Self> {
+        let topic_id = std::env::var("PUBSUB_TOPIC_ID").expect("PUBSUB_TOPIC_ID must be set");
+        Self::for_topic(&topic_id).await
+    }
+
+    pub async fn for_topic(topic_id: &str) -> Result<Self> {
         // Client constructor requires one of
         //  - GOOGLE_APPLICATION_CREDENTIALS or
         //  - GOOGLE_APPLICATION_CREDENTIALS_JSON
         let config = ClientConfig::default().with_auth().await?;
         let client = Client::new(config).await?;
-        let topic_id = std::env::var("PUBSUB_TOPIC_ID").expect("PUBSUB_TOPIC_ID must be set");
-        Ok(Self::new(client, &topic_id))
+        Ok(Self::new(client, topic_id))
     }
 
     pub async fn post_message(&self, message: Message) -> Result<()> {
-        let awaiter = self.publisher.publish(Self::message_from(&message)).await;
+        let awaiter = self.publisher.publish(Self::request_from(&message)).await;
         match awaiter.get().await {
             Ok(_success) => (),
             Err(failure) => tracing::error!("failed publish for {:?} with {}", message, failure),
@@ -43,7 +47,7 @@ impl PubSubClient {
     }
 
     pub async fn post_batch(&self, messages: &[Message]) -> Result<()> {
-        let message_vec: Vec<_> = messages.iter().map(Self::message_from).collect();
+        let message_vec: Vec<_> = messages.iter().map(Self::request_from).collect();
         tracing::info!("posting {} messages to metadata fetcher", message_vec.len());
         let awaiter_vec = self.publisher.publish_bulk(message_vec).await;
 
@@ -66,9 +70,41 @@ impl PubSubClient {
             })
             .collect();
         tracing::info!("posted messages with {} errors", errors.len());
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "failed to publish {} of {} messages",
+                errors.len(),
+                messages.len()
+            ));
+        }
         Ok(())
     }
 
+    /// Publishes a single message, failing unless it was accepted.
+    /// Messages with the same ordering key are delivered in order (to ordered subscriptions).
+    pub async fn publish_ordered<T: serde::Serialize>(
+        &self,
+        value: &T,
+        ordering_key: &str,
+    ) -> Result<()> {
+        let message = PubsubMessage {
+            ordering_key: ordering_key.to_string(),
+            ..Self::message_from(value)
+        };
+        self.publisher.publish(message).await.get().await?;
+        Ok(())
+    }
+
+    /// Metadata request, with its kind (i.e. token or token_batch) as attribute
+    /// (for subscription filters and logs of push deliveries).
+    fn request_from(message: &Message) -> PubsubMessage {
+        let mut request = Self::message_from(message);
+        request
+            .attributes
+            .insert("kind".to_string(), message.kind().to_string());
+        request
+    }
+
     pub(crate) fn message_from<T: serde::Serialize>(val: &T) -> PubsubMessage {
         let input = serde_json::to_string(val).expect("val is JSON serializable");
         PubsubMessage {
@@ -95,6 +131,18 @@ mod tests {
         PubSubClient::new(client, "test-topic")
     }
 
+    #[test]
+    fn request_attributes() {
+        let request = PubSubClient::request_from(&Message::Contract {
+            address: Address::from(1),
+        });
+        assert_eq!(request.attributes["kind"], "contract");
+        assert_eq!(
+            request.data,
+            br#"{"contract":{"address":"0x0000000000000000000000000000000000000001"}}"#
+        );
+    }
+
     #[tokio::test]
     async fn mock_publish() {
         let ps_client = test_client().await;
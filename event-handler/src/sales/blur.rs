@@ -0,0 +1,201 @@
+use super::{apply_rate, token_address, token_uint, topic_address, DecodedSale, Marketplace};
+use eth::types::{Address, EventLog, U256};
+use ethers::{
+    abi::{decode as abi_decode, ParamType, Token},
+    types::U256 as EthersU256,
+};
+
+/// Emitted by BlurExchange (v1). Blend and Blur v2 executions are not covered.
+pub const ORDERS_MATCHED: &str = "OrdersMatched(address,address,(address,uint8,address,address,uint256,uint256,address,uint256,uint256,uint256,(uint16,address)[],uint256,bytes),bytes32,(address,uint8,address,address,uint256,uint256,address,uint256,uint256,uint256,(uint16,address)[],uint256,bytes),bytes32)";
+
+/// Fee rates are given in basis points.
+const FEE_DENOMINATOR: u64 = 10_000;
+
+fn order_type() -> ParamType {
+    ParamType::Tuple(vec![
+        ParamType::Address,   // trader
+        ParamType::Uint(8),   // side
+        ParamType::Address,   // matchingPolicy
+        ParamType::Address,   // collection
+        ParamType::Uint(256), // tokenId
+        ParamType::Uint(256), // amount
+        ParamType::Address,   // paymentToken
+        ParamType::Uint(256), // price
+        ParamType::Uint(256), // listingTime
+        ParamType::Uint(256), // expirationTime
+        ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Uint(16), // rate
+            ParamType::Address,  // recipient
+        ]))),
+        ParamType::Uint(256), // salt
+        ParamType::Bytes,     // extraParams
+    ])
+}
+
+#[derive(Debug)]
+struct Order {
+    trader: Address,
+    collection: Address,
+    token_id: EthersU256,
+    payment_token: Address,
+    price: EthersU256,
+    fee_rate: EthersU256,
+}
+
+impl Order {
+    fn from_token(token: Token) -> Option<Self> {
+        let fields = token.into_tuple()?;
+        if fields.len() != 13 {
+            return None;
+        }
+        let mut fields = fields.into_iter();
+        let trader = token_address(fields.next()?)?;
+        let mut fields = fields.skip(2);
+        let collection = token_address(fields.next()?)?;
+        let token_id = token_uint(fields.next()?)?;
+        let mut fields = fields.skip(1);
+        let payment_token = token_address(fields.next()?)?;
+        let price = token_uint(fields.next()?)?;
+        let mut fields = fields.skip(2);
+        let fee_rate = fields
+            .next()?
+            .into_array()?
+            .into_iter()
+            .try_fold(EthersU256::zero(), |total, fee| {
+                total.checked_add(token_uint(fee.into_tuple()?.into_iter().next()?)?)
+            })?;
+        Some(Self {
+            trader,
+            collection,
+            token_id,
+            payment_token,
+            price,
+            fee_rate,
+        })
+    }
+}
+
+/// Fees are always paid out of the sell order's fee list.
+pub fn decode(log: &EventLog) -> Option<Vec<DecodedSale>> {
+    let maker = topic_address(log.topics.get(1)?);
+    let mut tokens = abi_decode(
+        &[
+            order_type(),
+            ParamType::FixedBytes(32),
+            order_type(),
+            ParamType::FixedBytes(32),
+        ],
+        &log.data,
+    )
+    .ok()?
+    .into_iter();
+    let sell = Order::from_token(tokens.next()?)?;
+    let buy = Order::from_token(tokens.nth(1)?)?;
+    // Execution happens at the maker's price.
+    let price = if maker == buy.trader {
+        buy.price
+    } else {
+        sell.price
+    };
+
+    Some(vec![DecodedSale {
+        collection: Some(sell.collection),
+        token_id: U256::from(sell.token_id),
+        price,
+        currency: Some(sell.payment_token),
+        fees: Some(apply_rate(price, sell.fee_rate, FEE_DENOMINATOR)?),
+        ..DecodedSale::new(Marketplace::Blur, log)
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sales::event_topic;
+    use eth::types::Bytes32;
+    use ethers::abi::encode;
+
+    fn order(trader: u64, side: u8, price: EthersU256, fee_rates: &[u16]) -> Token {
+        let address = |value: u64| Token::Address(ethers::types::Address::from_low_u64_be(value));
+        Token::Tuple(vec![
+            address(trader),
+            Token::Uint(side.into()),
+            address(50),
+            address(10),
+            Token::Uint(123.into()),
+            Token::Uint(1.into()),
+            address(0),
+            Token::Uint(price),
+            Token::Uint(0.into()),
+            Token::Uint(0.into()),
+            Token::Array(
+                fee_rates
+                    .iter()
+                    .map(|rate| Token::Tuple(vec![Token::Uint((*rate).into()), address(99)]))
+                    .collect(),
+            ),
+            Token::Uint(0.into()),
+            Token::Bytes(vec![]),
+        ])
+    }
+
+    #[test]
+    fn decode_orders_matched() {
+        let seller = Address::from(1);
+        let mut maker_topic = [0u8; 32];
+        maker_topic[12..].copy_from_slice(&Vec::<u8>::from(seller));
+        let log = EventLog {
+            address: Address::from(100),
+            topics: vec![
+                event_topic(ORDERS_MATCHED),
+                Bytes32::from(maker_topic),
+                Bytes32::from(2),
+            ],
+            data: encode(&[
+                order(1, 1, 10_000.into(), &[50, 200]),
+                Token::FixedBytes(vec![0; 32]),
+                order(2, 0, 10_000.into(), &[]),
+                Token::FixedBytes(vec![0; 32]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        assert_eq!(
+            decode(&log).unwrap(),
+            vec![DecodedSale {
+                marketplace: Marketplace::Blur,
+                block_number: 1,
+                transaction_index: 2,
+                log_index: 3,
+                collection: Some(Address::from(10)),
+                token_id: U256::from(123),
+                price: 10_000.into(),
+                currency: Some(Address::zero()),
+                fees: Some(250.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn decode_overflowing_fees() {
+        let log = EventLog {
+            address: Address::from(100),
+            topics: vec![
+                event_topic(ORDERS_MATCHED),
+                Bytes32::from(1),
+                Bytes32::from(2),
+            ],
+            data: encode(&[
+                order(1, 1, EthersU256::MAX, &[50]),
+                Token::FixedBytes(vec![0; 32]),
+                order(2, 0, EthersU256::MAX, &[]),
+                Token::FixedBytes(vec![0; 32]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        assert!(decode(&log).is_none());
+    }
+}
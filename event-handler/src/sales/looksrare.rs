@@ -0,0 +1,93 @@
+use super::{token_address, token_uint, DecodedSale, Marketplace};
+use eth::types::{EventLog, U256};
+use ethers::abi::{decode as abi_decode, ParamType};
+
+/// Emitted by LooksRareExchange (v1) when a taker buys a listing.
+pub const TAKER_BID: &str =
+    "TakerBid(bytes32,uint256,address,address,address,address,address,uint256,uint256,uint256)";
+/// Emitted by LooksRareExchange (v1) when a taker sells into a bid.
+pub const TAKER_ASK: &str =
+    "TakerAsk(bytes32,uint256,address,address,address,address,address,uint256,uint256,uint256)";
+
+/// Both events share their layout and differ only in direction, which is taken from the transfer.
+/// Protocol & royalty fees are emitted in separate events and not included here.
+pub fn decode(log: &EventLog) -> Option<Vec<DecodedSale>> {
+    let mut tokens = abi_decode(
+        &[
+            ParamType::FixedBytes(32), // orderHash
+            ParamType::Uint(256),      // orderNonce
+            ParamType::Address,        // currency
+            ParamType::Address,        // collection
+            ParamType::Uint(256),      // tokenId
+            ParamType::Uint(256),      // amount
+            ParamType::Uint(256),      // price
+        ],
+        &log.data,
+    )
+    .ok()?
+    .into_iter()
+    .skip(2);
+    let currency = token_address(tokens.next()?)?;
+    let collection = token_address(tokens.next()?)?;
+    let token_id = token_uint(tokens.next()?)?;
+    let price = token_uint(tokens.nth(1)?)?;
+
+    Some(vec![DecodedSale {
+        collection: Some(collection),
+        token_id: U256::from(token_id),
+        price,
+        currency: Some(currency),
+        ..DecodedSale::new(Marketplace::LooksRare, log)
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sales::event_topic;
+    use eth::types::{Address, Bytes32};
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn decode_taker_events() {
+        let weth = ethers::types::Address::from_low_u64_be(20);
+        let data = encode(&[
+            Token::FixedBytes(vec![0; 32]),
+            Token::Uint(7.into()),
+            Token::Address(weth),
+            Token::Address(ethers::types::Address::from_low_u64_be(10)),
+            Token::Uint(123.into()),
+            Token::Uint(1.into()),
+            Token::Uint(5000.into()),
+        ]);
+        for signature in [TAKER_BID, TAKER_ASK] {
+            let log = EventLog {
+                address: Address::from(100),
+                topics: vec![
+                    event_topic(signature),
+                    Bytes32::from(1),
+                    Bytes32::from(2),
+                    Bytes32::from(3),
+                ],
+                data: data.clone(),
+                block_number: 1,
+                transaction_index: 2,
+                log_index: 3,
+            };
+            assert_eq!(
+                decode(&log).unwrap(),
+                vec![DecodedSale {
+                    marketplace: Marketplace::LooksRare,
+                    block_number: 1,
+                    transaction_index: 2,
+                    log_index: 3,
+                    collection: Some(Address::from(10)),
+                    token_id: U256::from(123),
+                    price: 5000.into(),
+                    currency: Some(Address::from(20)),
+                    fees: None,
+                }]
+            );
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Decoding of NFT marketplace fill events and matching them
+//! to the token transfers (from the same transaction) that they settle.
+mod blur;
+mod looksrare;
+mod seaport;
+mod sudoswap;
+mod x2y2;
+
+use data_store::models::Sale;
+use eth::types::{Address, Bytes32, EventLog, NftId, U256};
+use ethers::{abi::Token, types::U256 as EthersU256, utils::keccak256};
+use event_retriever::db_reader::{diesel::BlockRangeEvents, models::EventMeta};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Marketplace {
+    Seaport,
+    Blur,
+    LooksRare,
+    X2Y2,
+    Sudoswap,
+}
+
+impl Marketplace {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Marketplace::Seaport => "seaport",
+            Marketplace::Blur => "blur",
+            Marketplace::LooksRare => "looksrare",
+            Marketplace::X2Y2 => "x2y2",
+            Marketplace::Sudoswap => "sudoswap",
+        }
+    }
+
+    /// Exchange contracts whose fill events are decoded (any other contract can emit them).
+    /// Sudoswap events are emitted by each pool (pair) rather than an exchange, so their
+    /// emitter is not verified (None): such sales are only as reliable as the matched transfer.
+    fn exchanges(&self) -> Option<&'static [&'static str]> {
+        let exchanges: &[&str] = match self {
+            Marketplace::Seaport => &[
+                // Seaport 1.1, 1.4, 1.5 and 1.6 (same address on all chains).
+                "0x00000000006c3852cbEf3e08E8dF289169EdE581",
+                "0x00000000000001ad428e4906aE43D8F9852d0dD6",
+                "0x00000000000000ADc04C56Bf30aC9d3c0aAF14dC",
+                "0x0000000000000068F116a894984e2DB1123eB395",
+            ],
+            Marketplace::Blur => &["0x000000000000Ad05Ccc4F10045630fb830B95127"],
+            Marketplace::LooksRare => &["0x59728544B08AB483533076417FbBB2fD0B17CE3a"],
+            Marketplace::X2Y2 => &["0x74312363e45DCaBA76c59ec49a7Aa8A65a67EeD3"],
+            Marketplace::Sudoswap => return None,
+        };
+        Some(exchanges)
+    }
+}
+
+/// A single token sale as emitted by a marketplace fill event.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodedSale {
+    pub marketplace: Marketplace,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    /// Token contract (None when the event doesn't emit it, e.g. Sudoswap pairs).
+    pub collection: Option<Address>,
+    pub token_id: U256,
+    /// Price paid for the token in the smallest unit of `currency`.
+    pub price: EthersU256,
+    /// Zero address for native ETH (None when the event doesn't emit it).
+    pub currency: Option<Address>,
+    /// Marketplace and royalty fees deducted from the price (when emitted).
+    pub fees: Option<EthersU256>,
+}
+
+impl DecodedSale {
+    fn new(marketplace: Marketplace, log: &EventLog) -> Self {
+        Self {
+            marketplace,
+            block_number: log.block_number,
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+            collection: None,
+            token_id: U256::from(0),
+            price: EthersU256::zero(),
+            currency: None,
+            fees: None,
+        }
+    }
+}
+
+/// Token movement observed by the processor, to which sales are matched.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NftTransfer {
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub token: NftId,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+type Decoder = fn(&EventLog) -> Option<Vec<DecodedSale>>;
+
+pub struct SaleDecoder {
+    /// Decoders by event topic, along with the exchanges allowed to emit the event
+    /// (any contract if None).
+    decoders: HashMap<Bytes32, (Decoder, Option<HashSet<Address>>)>,
+}
+
+impl Default for SaleDecoder {
+    fn default() -> Self {
+        use Marketplace::*;
+        let decoders: [(&str, Marketplace, Decoder); 7] = [
+            (seaport::ORDER_FULFILLED, Seaport, seaport::decode),
+            (blur::ORDERS_MATCHED, Blur, blur::decode),
+            (looksrare::TAKER_BID, LooksRare, looksrare::decode),
+            (looksrare::TAKER_ASK, LooksRare, looksrare::decode),
+            (x2y2::EV_INVENTORY, X2Y2, x2y2::decode),
+            (sudoswap::SWAP_NFT_IN_PAIR, Sudoswap, sudoswap::decode),
+            (sudoswap::SWAP_NFT_OUT_PAIR, Sudoswap, sudoswap::decode),
+        ];
+        Self {
+            decoders: decoders
+                .into_iter()
+                .map(|(signature, marketplace, decoder)| {
+                    let exchanges = marketplace.exchanges().map(|exchanges| {
+                        exchanges
+                            .iter()
+                            .map(|address| Address::from_str(address).expect("valid address"))
+                            .collect()
+                    });
+                    (event_topic(signature), (decoder, exchanges))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SaleDecoder {
+    /// Event topics of all supported marketplace fill events.
+    pub fn topics(&self) -> Vec<Bytes32> {
+        self.decoders.keys().copied().collect()
+    }
+
+    /// Decodes all token sales from a log.
+    /// Logs which can't be attributed to individual tokens (e.g. bundles)
+    /// or that weren't emitted by the marketplace's exchange yield nothing.
+    pub fn decode(&self, log: &EventLog) -> Vec<DecodedSale> {
+        let Some((decoder, exchanges)) = log.topics.first().and_then(|t| self.decoders.get(t))
+        else {
+            return vec![];
+        };
+        if exchanges
+            .as_ref()
+            .is_some_and(|exchanges| !exchanges.contains(&log.address))
+        {
+            tracing::debug!("skipping fill event of unknown exchange {}", log.address);
+            return vec![];
+        }
+        decoder(log).unwrap_or_default()
+    }
+}
+
+pub fn event_topic(signature: &str) -> Bytes32 {
+    Bytes32::from(keccak256(signature.as_bytes()))
+}
+
+/// Collects all token transfers (erc721 & erc1155) from processed events.
+pub fn nft_transfers(events: &BlockRangeEvents) -> Vec<NftTransfer> {
+    let mut transfers = vec![];
+    for block_events in events.values() {
+        for event in block_events.values().flatten() {
+            let base = event.base;
+            let mut push = |token_id: U256, from: Address, to: Address, amount: U256| {
+                transfers.push(NftTransfer {
+                    block_number: base.block_number,
+                    transaction_index: base.transaction_index,
+                    log_index: base.log_index,
+                    token: NftId {
+                        address: base.contract_address,
+                        token_id,
+                    },
+                    from,
+                    to,
+                    amount,
+                })
+            };
+            match &event.meta {
+                EventMeta::Erc721Transfer(t) => push(t.token_id, t.from, t.to, U256::from(1)),
+                EventMeta::Erc1155TransferSingle(t) => push(t.id, t.from, t.to, t.value),
+                EventMeta::Erc1155TransferBatch(batch) => {
+                    for (id, value) in batch.ids.iter().zip(batch.values.iter()) {
+                        push(*id, batch.from, batch.to, *value)
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    transfers
+}
+
+/// Pairs each sale with an unmatched transfer of the same token in the same transaction.
+/// Marketplaces emit fill events after the transfers they settle, so the nearest preceding
+/// transfer is preferred, falling back to the nearest subsequent one.
+/// Sales without a corresponding transfer are dropped.
+pub fn match_sales(mut decoded: Vec<DecodedSale>, transfers: &[NftTransfer]) -> Vec<Sale> {
+    let mut tx_transfers: HashMap<(u64, u64), Vec<(usize, &NftTransfer)>> = HashMap::new();
+    for (index, transfer) in transfers.iter().enumerate() {
+        tx_transfers
+            .entry((transfer.block_number, transfer.transaction_index))
+            .or_default()
+            .push((index, transfer));
+    }
+    decoded.sort_by_key(|sale| (sale.block_number, sale.log_index));
+
+    let mut matched = HashSet::new();
+    let mut sales = vec![];
+    for sale in decoded {
+        let candidates = tx_transfers
+            .get(&(sale.block_number, sale.transaction_index))
+            .into_iter()
+            .flatten()
+            .filter(|(index, transfer)| {
+                !matched.contains(index)
+                    && transfer.token.token_id == sale.token_id
+                    && sale
+                        .collection
+                        .is_none_or(|address| address == transfer.token.address)
+            });
+        let preceding = candidates
+            .clone()
+            .filter(|(_, transfer)| transfer.log_index < sale.log_index)
+            .max_by_key(|(_, transfer)| transfer.log_index);
+        let following = candidates
+            .filter(|(_, transfer)| transfer.log_index > sale.log_index)
+            .min_by_key(|(_, transfer)| transfer.log_index);
+        let Some((index, transfer)) = preceding.or(following) else {
+            tracing::debug!("no transfer found for sale {:?}", sale);
+            continue;
+        };
+        matched.insert(*index);
+        sales.push(Sale {
+            block_number: sale.block_number as i64,
+            transaction_index: sale.transaction_index as i64,
+            transfer_log_index: transfer.log_index as i64,
+            sale_log_index: sale.log_index as i64,
+            marketplace: sale.marketplace.as_str().to_string(),
+            contract_address: transfer.token.address,
+            token_id: transfer.token.db_token_id(),
+            amount: transfer.amount.into(),
+            seller: transfer.from,
+            buyer: transfer.to,
+            price: U256::from(sale.price).into(),
+            currency: sale.currency.map(Address::into),
+            fees: sale.fees.map(|fees| U256::from(fees).into()),
+        });
+    }
+    sales
+}
+
+fn topic_address(topic: &Bytes32) -> Address {
+    let mut bytes = [0u8; 20];
+    bytes.copy_from_slice(&topic.0 .0[12..]);
+    Address::from(bytes)
+}
+
+fn token_address(token: Token) -> Option<Address> {
+    token.into_address().map(Address::from)
+}
+
+fn token_uint(token: Token) -> Option<EthersU256> {
+    token.into_uint()
+}
+
+/// Portion `rate / denominator` of `price` (None on overflow).
+fn apply_rate(price: EthersU256, rate: EthersU256, denominator: u64) -> Option<EthersU256> {
+    Some(price.checked_mul(rate)? / EthersU256::from(denominator))
+}
+
+/// Sum of the `amounts` (None on overflow). Amounts are taken from logs that any contract
+/// can emit, so they mustn't be trusted to fit.
+fn checked_sum(amounts: impl IntoIterator<Item = EthersU256>) -> Option<EthersU256> {
+    amounts
+        .into_iter()
+        .try_fold(EthersU256::zero(), |total, amount| {
+            total.checked_add(amount)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_transfer(log_index: u64, token_id: u64) -> NftTransfer {
+        NftTransfer {
+            block_number: 1,
+            transaction_index: 2,
+            log_index,
+            token: NftId {
+                address: Address::from(10),
+                token_id: U256::from(token_id),
+            },
+            from: Address::from(11),
+            to: Address::from(12),
+            amount: U256::from(1),
+        }
+    }
+
+    fn test_sale(log_index: u64, token_id: u64) -> DecodedSale {
+        DecodedSale {
+            marketplace: Marketplace::Seaport,
+            block_number: 1,
+            transaction_index: 2,
+            log_index,
+            collection: Some(Address::from(10)),
+            token_id: U256::from(token_id),
+            price: EthersU256::from(1000),
+            currency: Some(Address::zero()),
+            fees: Some(EthersU256::from(25)),
+        }
+    }
+
+    #[test]
+    fn topics() {
+        let decoder = SaleDecoder::default();
+        assert_eq!(decoder.topics().len(), 7);
+        // Seaport OrderFulfilled
+        assert!(decoder.topics().contains(
+            &Bytes32::from_str(
+                "0x9d9af8e38d66c62e2c12f0225249fd9d721c54b83f48d9352c97c6cacdcb6f31"
+            )
+            .unwrap()
+        ));
+    }
+
+    #[test]
+    fn decode_from_exchanges_only() {
+        use ethers::abi::encode;
+
+        let decoder = SaleDecoder::default();
+        let taker_bid = |address| EventLog {
+            address,
+            topics: vec![
+                event_topic(looksrare::TAKER_BID),
+                Bytes32::from(1),
+                Bytes32::from(2),
+                Bytes32::from(3),
+            ],
+            data: encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::Uint(7.into()),
+                Token::Address(ethers::types::Address::from_low_u64_be(20)),
+                Token::Address(ethers::types::Address::from_low_u64_be(10)),
+                Token::Uint(123.into()),
+                Token::Uint(1.into()),
+                Token::Uint(5000.into()),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        let exchange = Address::from_str("0x59728544B08AB483533076417FbBB2fD0B17CE3a").unwrap();
+        assert_eq!(decoder.decode(&taker_bid(exchange)).len(), 1);
+        assert!(decoder.decode(&taker_bid(Address::from(100))).is_empty());
+
+        // Sudoswap pairs can't be told apart by address.
+        let swap = EventLog {
+            topics: vec![event_topic(sudoswap::SWAP_NFT_OUT_PAIR)],
+            data: encode(&[
+                Token::Uint(1000.into()),
+                Token::Array(vec![Token::Uint(123.into())]),
+            ]),
+            ..taker_bid(Address::from(100))
+        };
+        assert_eq!(decoder.decode(&swap).len(), 1);
+    }
+
+    #[test]
+    fn match_sales_by_tx_and_log_index() {
+        let transfers = vec![
+            test_transfer(1, 5),
+            test_transfer(3, 5),
+            test_transfer(5, 6),
+            // Different transaction
+            NftTransfer {
+                transaction_index: 3,
+                ..test_transfer(7, 5)
+            },
+        ];
+        let sales = match_sales(
+            vec![
+                // Matches nearest preceding transfer (log 3, not log 1).
+                test_sale(4, 5),
+                // Matches remaining transfer of token 5.
+                test_sale(6, 5),
+                // Falls back to subsequent transfer.
+                test_sale(0, 6),
+                // No transfer of this token (in this transaction).
+                test_sale(8, 7),
+            ],
+            &transfers,
+        );
+        assert_eq!(
+            sales
+                .iter()
+                .map(|sale| (sale.sale_log_index, sale.transfer_log_index))
+                .collect::<Vec<_>>(),
+            vec![(0, 5), (4, 3), (6, 1)]
+        );
+        assert_eq!(sales[0].seller, Address::from(11));
+        assert_eq!(sales[0].buyer, Address::from(12));
+        assert_eq!(sales[0].marketplace, "seaport");
+    }
+
+    #[test]
+    fn match_sales_without_collection() {
+        let other_collection = NftTransfer {
+            token: NftId {
+                address: Address::from(20),
+                token_id: U256::from(5),
+            },
+            ..test_transfer(1, 5)
+        };
+        let sale = DecodedSale {
+            collection: None,
+            ..test_sale(2, 5)
+        };
+        let sales = match_sales(vec![sale.clone()], std::slice::from_ref(&other_collection));
+        assert_eq!(sales.len(), 1);
+        assert_eq!(sales[0].contract_address, Address::from(20));
+
+        // With a collection specified, other collections are not matched.
+        assert!(match_sales(vec![test_sale(2, 5)], &[other_collection]).is_empty());
+    }
+}
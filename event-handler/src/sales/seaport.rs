@@ -0,0 +1,261 @@
+use super::{checked_sum, token_address, token_uint, topic_address, DecodedSale, Marketplace};
+use eth::types::{Address, EventLog, U256};
+use ethers::{
+    abi::{decode as abi_decode, ParamType, Token},
+    types::U256 as EthersU256,
+};
+
+pub const ORDER_FULFILLED: &str = "OrderFulfilled(bytes32,address,address,address,(uint8,address,uint256,uint256)[],(uint8,address,uint256,uint256,address)[])";
+
+/// Seaport ItemType: NATIVE, ERC20, ERC721, ERC1155, ERC721_WITH_CRITERIA, ERC1155_WITH_CRITERIA
+const NATIVE: u8 = 0;
+const ERC20: u8 = 1;
+
+#[derive(Debug)]
+struct Item {
+    item_type: u8,
+    token: Address,
+    identifier: EthersU256,
+    amount: EthersU256,
+    /// Only present on consideration items.
+    recipient: Option<Address>,
+}
+
+impl Item {
+    fn from_token(token: Token) -> Option<Self> {
+        let mut fields = token.into_tuple()?.into_iter();
+        Some(Self {
+            item_type: token_uint(fields.next()?)?.low_u32() as u8,
+            token: token_address(fields.next()?)?,
+            identifier: token_uint(fields.next()?)?,
+            amount: token_uint(fields.next()?)?,
+            recipient: fields.next().and_then(token_address),
+        })
+    }
+
+    fn is_payment(&self) -> bool {
+        matches!(self.item_type, NATIVE | ERC20)
+    }
+
+    fn currency(&self) -> Address {
+        // Native items have the zero address as token.
+        self.token
+    }
+}
+
+fn items(token: Token) -> Option<Vec<Item>> {
+    token
+        .into_array()?
+        .into_iter()
+        .map(Item::from_token)
+        .collect()
+}
+
+/// Decodes single token orders: either a filled listing (token offered for payment)
+/// or an accepted offer (payment offered for token). Bundles and swaps are skipped.
+pub fn decode(log: &EventLog) -> Option<Vec<DecodedSale>> {
+    let offerer = topic_address(log.topics.get(1)?);
+    let spent_item = ParamType::Tuple(vec![
+        ParamType::Uint(8),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+    ]);
+    let received_item = ParamType::Tuple(vec![
+        ParamType::Uint(8),
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Address,
+    ]);
+    let mut tokens = abi_decode(
+        &[
+            ParamType::FixedBytes(32),
+            ParamType::Address,
+            ParamType::Array(Box::new(spent_item)),
+            ParamType::Array(Box::new(received_item)),
+        ],
+        &log.data,
+    )
+    .ok()?
+    .into_iter()
+    .skip(2);
+    let offer = items(tokens.next()?)?;
+    let consideration = items(tokens.next()?)?;
+
+    let (offered_tokens, offered_payments): (Vec<_>, Vec<_>) =
+        offer.into_iter().partition(|item| !item.is_payment());
+    let (considered_tokens, considered_payments): (Vec<_>, Vec<_>) = consideration
+        .into_iter()
+        .partition(|item| !item.is_payment());
+
+    let (nft, payments, fees) = match (offered_tokens.len(), considered_tokens.len()) {
+        // Listing: everything paid out to anyone but the offerer is a fee.
+        (1, 0) => {
+            let fees = checked_sum(
+                considered_payments
+                    .iter()
+                    .filter(|item| item.recipient != Some(offerer))
+                    .map(|item| item.amount),
+            )?;
+            (&offered_tokens[0], &considered_payments, fees)
+        }
+        // Accepted offer: the offered payment is split between seller and fee recipients.
+        (0, 1) => {
+            let fees = checked_sum(considered_payments.iter().map(|item| item.amount))?;
+            (&considered_tokens[0], &offered_payments, fees)
+        }
+        _ => return None,
+    };
+    let currency = payments.first()?.currency();
+    if payments.iter().any(|item| item.currency() != currency) {
+        return None;
+    }
+
+    Some(vec![DecodedSale {
+        collection: Some(nft.token),
+        token_id: U256::from(nft.identifier),
+        price: checked_sum(payments.iter().map(|item| item.amount))?,
+        currency: Some(currency),
+        fees: Some(fees),
+        ..DecodedSale::new(Marketplace::Seaport, log)
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sales::event_topic;
+    use eth::types::Bytes32;
+    use ethers::abi::encode;
+
+    fn address_topic(address: Address) -> Bytes32 {
+        let mut bytes = [0u8; 32];
+        bytes[12..].copy_from_slice(&Vec::<u8>::from(address));
+        Bytes32::from(bytes)
+    }
+
+    fn item(item_type: u8, token: u64, identifier: u64, amount: u64) -> Vec<Token> {
+        vec![
+            Token::Uint(item_type.into()),
+            Token::Address(ethers::types::Address::from_low_u64_be(token)),
+            Token::Uint(identifier.into()),
+            Token::Uint(amount.into()),
+        ]
+    }
+
+    fn received(item_type: u8, token: u64, amount: u64, recipient: u64) -> Token {
+        let mut fields = item(item_type, token, 0, amount);
+        fields.push(Token::Address(ethers::types::Address::from_low_u64_be(
+            recipient,
+        )));
+        Token::Tuple(fields)
+    }
+
+    #[test]
+    fn decode_listing() {
+        let offerer = Address::from(1);
+        let log = EventLog {
+            address: Address::from(100),
+            topics: vec![
+                event_topic(ORDER_FULFILLED),
+                address_topic(offerer),
+                address_topic(Address::zero()),
+            ],
+            data: encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::Address(ethers::types::Address::from_low_u64_be(2)),
+                Token::Array(vec![Token::Tuple(item(2, 10, 123, 1))]),
+                Token::Array(vec![
+                    received(NATIVE, 0, 975, 1),
+                    received(NATIVE, 0, 25, 3),
+                ]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        assert_eq!(
+            decode(&log).unwrap(),
+            vec![DecodedSale {
+                marketplace: Marketplace::Seaport,
+                block_number: 1,
+                transaction_index: 2,
+                log_index: 3,
+                collection: Some(Address::from(10)),
+                token_id: U256::from(123),
+                price: 1000.into(),
+                currency: Some(Address::zero()),
+                fees: Some(25.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn decode_accepted_offer() {
+        let offerer = Address::from(1);
+        let weth = 20;
+        let log = EventLog {
+            address: Address::from(100),
+            topics: vec![
+                event_topic(ORDER_FULFILLED),
+                address_topic(offerer),
+                address_topic(Address::zero()),
+            ],
+            data: encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::Address(ethers::types::Address::from_low_u64_be(2)),
+                Token::Array(vec![Token::Tuple(item(ERC20, weth, 0, 1000))]),
+                Token::Array(vec![
+                    Token::Tuple({
+                        let mut nft = item(2, 10, 123, 1);
+                        nft.push(Token::Address(ethers::types::Address::from_low_u64_be(1)));
+                        nft
+                    }),
+                    received(ERC20, weth, 25, 3),
+                ]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        let sale = decode(&log).unwrap().pop().unwrap();
+        assert_eq!(sale.price, 1000.into());
+        assert_eq!(sale.fees, Some(25.into()));
+        assert_eq!(sale.currency, Some(Address::from(weth)));
+        assert_eq!(sale.token_id, U256::from(123));
+    }
+
+    #[test]
+    fn decode_overflowing_amounts() {
+        let offerer = Address::from(1);
+        let payment = |recipient: u64| {
+            let mut fields = item(NATIVE, 0, 0, 0);
+            fields[3] = Token::Uint(EthersU256::MAX);
+            fields.push(Token::Address(ethers::types::Address::from_low_u64_be(
+                recipient,
+            )));
+            Token::Tuple(fields)
+        };
+        let log = |recipients: [u64; 2]| EventLog {
+            address: Address::from(100),
+            topics: vec![
+                event_topic(ORDER_FULFILLED),
+                address_topic(offerer),
+                address_topic(Address::zero()),
+            ],
+            data: encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::Address(ethers::types::Address::from_low_u64_be(2)),
+                Token::Array(vec![Token::Tuple(item(2, 10, 123, 1))]),
+                Token::Array(recipients.into_iter().map(payment).collect()),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        // Neither the price nor the fees can be summed.
+        assert!(decode(&log([1, 1])).is_none());
+        assert!(decode(&log([3, 4])).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use super::{token_uint, DecodedSale, Marketplace};
+use eth::types::{EventLog, U256};
+use ethers::{
+    abi::{decode as abi_decode, ParamType},
+    types::U256 as EthersU256,
+};
+
+/// Emitted by Sudoswap (v2) pairs when the pair buys tokens from a trader.
+pub const SWAP_NFT_IN_PAIR: &str = "SwapNFTInPair(uint256,uint256[])";
+/// Emitted by Sudoswap (v2) pairs when the pair sells tokens to a trader.
+pub const SWAP_NFT_OUT_PAIR: &str = "SwapNFTOutPair(uint256,uint256[])";
+
+/// Pairs emit neither the token contract nor the payment token (ETH or ERC20),
+/// so the collection is determined by the matched transfer and the currency is left unknown.
+/// The total amount is split evenly over all swapped tokens and direction is taken from the transfer.
+pub fn decode(log: &EventLog) -> Option<Vec<DecodedSale>> {
+    let mut tokens = abi_decode(
+        &[
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+        ],
+        &log.data,
+    )
+    .ok()?
+    .into_iter();
+    let total = token_uint(tokens.next()?)?;
+    let ids = tokens
+        .next()?
+        .into_array()?
+        .into_iter()
+        .map(token_uint)
+        .collect::<Option<Vec<_>>>()?;
+    if ids.is_empty() {
+        return None;
+    }
+    let price = total / EthersU256::from(ids.len());
+    Some(
+        ids.into_iter()
+            .map(|token_id| DecodedSale {
+                token_id: U256::from(token_id),
+                price,
+                ..DecodedSale::new(Marketplace::Sudoswap, log)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sales::event_topic;
+    use eth::types::Address;
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn decode_swap() {
+        let log = EventLog {
+            address: Address::from(100),
+            topics: vec![event_topic(SWAP_NFT_OUT_PAIR)],
+            data: encode(&[
+                Token::Uint(3000.into()),
+                Token::Array(vec![
+                    Token::Uint(1.into()),
+                    Token::Uint(2.into()),
+                    Token::Uint(3.into()),
+                ]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        };
+        let sales = decode(&log).unwrap();
+        assert_eq!(
+            sales.iter().map(|sale| sale.token_id).collect::<Vec<_>>(),
+            vec![U256::from(1), U256::from(2), U256::from(3)]
+        );
+        assert!(sales.iter().all(|sale| sale.price == 1000.into()
+            && sale.collection.is_none()
+            && sale.currency.is_none()));
+    }
+}
@@ -0,0 +1,175 @@
+use super::{apply_rate, token_address, token_uint, DecodedSale, Marketplace};
+use eth::types::{EventLog, U256};
+use ethers::{
+    abi::{decode as abi_decode, ParamType, Token},
+    types::U256 as EthersU256,
+};
+
+pub const EV_INVENTORY: &str = "EvInventory(bytes32,address,address,uint256,uint256,uint256,uint256,uint256,address,bytes,(uint256,bytes),(uint8,uint256,uint256,uint256,bytes32,address,bytes,uint256,uint256,uint256,(uint256,address)[]))";
+
+/// Fee percentages are given in parts per million.
+const FEE_DENOMINATOR: u64 = 1_000_000;
+const ERC721_DELEGATE: u64 = 1;
+const ERC1155_DELEGATE: u64 = 2;
+
+fn detail_type() -> ParamType {
+    ParamType::Tuple(vec![
+        ParamType::Uint(8),        // op
+        ParamType::Uint(256),      // orderIdx
+        ParamType::Uint(256),      // itemIdx
+        ParamType::Uint(256),      // price
+        ParamType::FixedBytes(32), // itemHash
+        ParamType::Address,        // executionDelegate
+        ParamType::Bytes,          // dataReplacement
+        ParamType::Uint(256),      // bidIncentivePct
+        ParamType::Uint(256),      // aucMinIncrementPct
+        ParamType::Uint(256),      // aucIncDurationSecs
+        ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Uint(256), // percentage
+            ParamType::Address,   // to
+        ]))),
+    ])
+}
+
+/// Item data holds the encoded (token, tokenId[, amount]) pairs of the order item.
+fn decode_item_token(delegate_type: u64, data: &[u8]) -> Option<Token> {
+    let pair = match delegate_type {
+        ERC721_DELEGATE => vec![ParamType::Address, ParamType::Uint(256)],
+        ERC1155_DELEGATE => vec![
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        _ => return None,
+    };
+    let mut pairs = abi_decode(&[ParamType::Array(Box::new(ParamType::Tuple(pair)))], data)
+        .ok()?
+        .pop()?
+        .into_array()?;
+    // Bundles can't be priced per token.
+    if pairs.len() != 1 {
+        return None;
+    }
+    pairs.pop()
+}
+
+pub fn decode(log: &EventLog) -> Option<Vec<DecodedSale>> {
+    let mut tokens = abi_decode(
+        &[
+            ParamType::Address,                                             // maker
+            ParamType::Address,                                             // taker
+            ParamType::Uint(256),                                           // orderSalt
+            ParamType::Uint(256),                                           // settleSalt
+            ParamType::Uint(256),                                           // intent
+            ParamType::Uint(256),                                           // delegateType
+            ParamType::Uint(256),                                           // deadline
+            ParamType::Address,                                             // currency
+            ParamType::Bytes,                                               // dataMask
+            ParamType::Tuple(vec![ParamType::Uint(256), ParamType::Bytes]), // item
+            detail_type(),
+        ],
+        &log.data,
+    )
+    .ok()?
+    .into_iter()
+    .skip(5);
+    let delegate_type = token_uint(tokens.next()?)?.low_u64();
+    let currency = token_address(tokens.nth(1)?)?;
+    let item_data = tokens.nth(1)?.into_tuple()?.pop()?.into_bytes()?;
+    let mut detail = tokens.next()?.into_tuple()?.into_iter();
+    let price = token_uint(detail.nth(3)?)?;
+    let fee_rate = detail
+        .last()?
+        .into_array()?
+        .into_iter()
+        .try_fold(EthersU256::zero(), |total, fee| {
+            total.checked_add(token_uint(fee.into_tuple()?.into_iter().next()?)?)
+        })?;
+
+    let mut pair = decode_item_token(delegate_type, &item_data)?
+        .into_tuple()?
+        .into_iter();
+    let collection = token_address(pair.next()?)?;
+    let token_id = token_uint(pair.next()?)?;
+
+    Some(vec![DecodedSale {
+        collection: Some(collection),
+        token_id: U256::from(token_id),
+        price,
+        currency: Some(currency),
+        fees: Some(apply_rate(price, fee_rate, FEE_DENOMINATOR)?),
+        ..DecodedSale::new(Marketplace::X2Y2, log)
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sales::event_topic;
+    use eth::types::{Address, Bytes32};
+    use ethers::abi::encode;
+
+    fn address(value: u64) -> Token {
+        Token::Address(ethers::types::Address::from_low_u64_be(value))
+    }
+
+    fn inventory_log(pairs: Vec<Token>) -> EventLog {
+        let item_data = encode(&[Token::Array(pairs)]);
+        EventLog {
+            address: Address::from(100),
+            topics: vec![event_topic(EV_INVENTORY), Bytes32::from(1)],
+            data: encode(&[
+                address(1),
+                address(2),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+                Token::Uint(1.into()),
+                Token::Uint(ERC721_DELEGATE.into()),
+                Token::Uint(0.into()),
+                address(0),
+                Token::Bytes(vec![]),
+                Token::Tuple(vec![Token::Uint(2_000_000.into()), Token::Bytes(item_data)]),
+                Token::Tuple(vec![
+                    Token::Uint(1.into()),
+                    Token::Uint(0.into()),
+                    Token::Uint(0.into()),
+                    Token::Uint(2_000_000.into()),
+                    Token::FixedBytes(vec![0; 32]),
+                    address(30),
+                    Token::Bytes(vec![]),
+                    Token::Uint(0.into()),
+                    Token::Uint(0.into()),
+                    Token::Uint(0.into()),
+                    Token::Array(vec![Token::Tuple(vec![
+                        Token::Uint(5000.into()),
+                        address(99),
+                    ])]),
+                ]),
+            ]),
+            block_number: 1,
+            transaction_index: 2,
+            log_index: 3,
+        }
+    }
+
+    #[test]
+    fn decode_inventory() {
+        let pair = |token_id: u64| Token::Tuple(vec![address(10), Token::Uint(token_id.into())]);
+        assert_eq!(
+            decode(&inventory_log(vec![pair(123)])).unwrap(),
+            vec![DecodedSale {
+                marketplace: Marketplace::X2Y2,
+                block_number: 1,
+                transaction_index: 2,
+                log_index: 3,
+                collection: Some(Address::from(10)),
+                token_id: U256::from(123),
+                price: 2_000_000.into(),
+                currency: Some(Address::zero()),
+                fees: Some(10_000.into()),
+            }]
+        );
+        // Bundle
+        assert!(decode(&inventory_log(vec![pair(123), pair(124)])).is_none());
+    }
+}
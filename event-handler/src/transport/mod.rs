@@ -0,0 +1,225 @@
+//! Transports of metadata requests ([Message]s) from the event processor to the
+//! metadata-retriever (which has a matching consumer for each of them).
+use crate::pubsub::PubSubClient;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use eth::types::Message;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "redis")]
+mod redis;
+
+#[async_trait]
+pub trait MessagePublisher: Send + Sync {
+    /// Returns once the messages have been handed to the transport, failing unless all of them
+    /// were accepted (the outbox publisher then retries the whole batch).
+    async fn publish(&self, messages: &[Message]) -> Result<()>;
+}
+
+#[async_trait]
+impl MessagePublisher for PubSubClient {
+    async fn publish(&self, messages: &[Message]) -> Result<()> {
+        self.post_batch(messages).await
+    }
+}
+
+/// POSTs messages (as a JSON array) directly to the metadata-retriever's `/messages` route.
+pub struct HttpPublisher {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpPublisher {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for HttpPublisher {
+    async fn publish(&self, messages: &[Message]) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(messages)?)
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("post messages to {}", self.url))?;
+        Ok(())
+    }
+}
+
+/// Sends messages to a receiver in the same process (i.e. an embedded consumer or tests).
+pub struct ChannelPublisher(mpsc::UnboundedSender<Message>);
+
+impl ChannelPublisher {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Message>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self(sender), receiver)
+    }
+
+    /// Publisher to the named channel consumed by the metadata-retriever (see [eth::channel]).
+    pub fn named(name: &str) -> Self {
+        Self(eth::channel::sender(name))
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for ChannelPublisher {
+    async fn publish(&self, messages: &[Message]) -> Result<()> {
+        for message in messages {
+            self.0
+                .send(message.clone())
+                .map_err(|_| anyhow!("message receiver dropped"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parsed from `pubsub://<topic>`, `http(s)://<url>`, `redis://<host:port>/<stream>`,
+/// `nats://<server>/<subject>` or `channel://<name>` (in-process).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportConfig {
+    PubSub { topic: String },
+    Http { url: String },
+    Redis { url: String, stream: String },
+    Nats { server: String, subject: String },
+    Channel { name: String },
+}
+
+impl FromStr for TransportConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (scheme, rest) = s
+            .split_once("://")
+            .ok_or_else(|| anyhow!("invalid message transport {s}"))?;
+        let target = || {
+            rest.rsplit_once('/')
+                .filter(|(address, name)| !address.is_empty() && !name.is_empty())
+                .map(|(address, name)| (address.to_string(), name.to_string()))
+                .ok_or_else(|| anyhow!("expected {scheme}://<address>/<name>, got {s}"))
+        };
+        match scheme {
+            "pubsub" => Ok(Self::PubSub {
+                topic: rest.to_string(),
+            }),
+            "http" | "https" => Ok(Self::Http { url: s.to_string() }),
+            "redis" => {
+                let (address, stream) = target()?;
+                Ok(Self::Redis {
+                    url: format!("redis://{address}"),
+                    stream,
+                })
+            }
+            "nats" => {
+                let (server, subject) = target()?;
+                Ok(Self::Nats { server, subject })
+            }
+            "channel" if !rest.is_empty() => Ok(Self::Channel {
+                name: rest.to_string(),
+            }),
+            _ => Err(anyhow!("unsupported message transport {scheme}")),
+        }
+    }
+}
+
+impl TransportConfig {
+    pub async fn connect(&self) -> Result<Box<dyn MessagePublisher>> {
+        let publisher: Box<dyn MessagePublisher> = match self {
+            Self::PubSub { topic } => Box::new(PubSubClient::for_topic(topic).await?),
+            Self::Http { url } => Box::new(HttpPublisher::new(url)),
+            Self::Channel { name } => Box::new(ChannelPublisher::named(name)),
+            #[cfg(feature = "redis")]
+            Self::Redis { url, stream } => {
+                Box::new(redis::RedisPublisher::connect(url, stream).await?)
+            }
+            #[cfg(feature = "nats")]
+            Self::Nats { server, subject } => {
+                Box::new(nats::NatsPublisher::connect(server, subject).await?)
+            }
+            #[allow(unreachable_patterns)]
+            unsupported => {
+                return Err(anyhow!(
+                    "{unsupported:?} requires building with the redis or nats feature"
+                ))
+            }
+        };
+        Ok(publisher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth::types::Address;
+
+    #[tokio::test]
+    async fn channel_publisher() {
+        let (publisher, mut receiver) = ChannelPublisher::new();
+        let message = Message::Contract {
+            address: Address::from(1),
+        };
+        publisher
+            .publish(&[message.clone(), message.clone()])
+            .await
+            .unwrap();
+        assert_eq!(receiver.recv().await, Some(message.clone()));
+        assert_eq!(receiver.recv().await, Some(message.clone()));
+
+        drop(receiver);
+        assert!(publisher.publish(&[message]).await.is_err());
+    }
+
+    #[test]
+    fn transport_config() {
+        assert_eq!(
+            "pubsub://metadata".parse::<TransportConfig>().unwrap(),
+            TransportConfig::PubSub {
+                topic: "metadata".to_string()
+            }
+        );
+        assert_eq!(
+            "http://retriever:8080/messages"
+                .parse::<TransportConfig>()
+                .unwrap(),
+            TransportConfig::Http {
+                url: "http://retriever:8080/messages".to_string()
+            }
+        );
+        assert_eq!(
+            "redis://localhost:6379/metadata"
+                .parse::<TransportConfig>()
+                .unwrap(),
+            TransportConfig::Redis {
+                url: "redis://localhost:6379".to_string(),
+                stream: "metadata".to_string()
+            }
+        );
+        assert_eq!(
+            "nats://localhost:4222/metadata"
+                .parse::<TransportConfig>()
+                .unwrap(),
+            TransportConfig::Nats {
+                server: "localhost:4222".to_string(),
+                subject: "metadata".to_string()
+            }
+        );
+        assert_eq!(
+            "channel://metadata".parse::<TransportConfig>().unwrap(),
+            TransportConfig::Channel {
+                name: "metadata".to_string()
+            }
+        );
+        assert!("redis://localhost:6379".parse::<TransportConfig>().is_err());
+        assert!("channel://".parse::<TransportConfig>().is_err());
+        assert!("stdout".parse::<TransportConfig>().is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use super::MessagePublisher;
+use anyhow::Result;
+use async_trait::async_trait;
+use eth::types::Message;
+
+pub(super) struct NatsPublisher {
+    client: async_nats::Client,
+    subject: String,
+}
+
+impl NatsPublisher {
+    pub(super) async fn connect(server: &str, subject: &str) -> Result<Self> {
+        Ok(Self {
+            client: async_nats::connect(server).await?,
+            subject: subject.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for NatsPublisher {
+    async fn publish(&self, messages: &[Message]) -> Result<()> {
+        for message in messages {
+            let payload = bytes::Bytes::from(serde_json::to_vec(message)?);
+            self.client.publish(self.subject.clone(), payload).await?;
+        }
+        self.client.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use super::MessagePublisher;
+use anyhow::Result;
+use async_trait::async_trait;
+use eth::types::Message;
+use redis::aio::MultiplexedConnection;
+
+/// Field of the stream entries holding the (JSON) message.
+const MESSAGE_FIELD: &str = "message";
+
+/// Appends one stream entry per message (consumed by the metadata-retriever's consumer group).
+pub(super) struct RedisPublisher {
+    connection: MultiplexedConnection,
+    stream: String,
+}
+
+impl RedisPublisher {
+    pub(super) async fn connect(url: &str, stream: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            connection: client.get_multiplexed_async_connection().await?,
+            stream: stream.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl MessagePublisher for RedisPublisher {
+    async fn publish(&self, messages: &[Message]) -> Result<()> {
+        let mut pipeline = redis::pipe();
+        for message in messages {
+            pipeline
+                .xadd(
+                    &self.stream,
+                    "*",
+                    &[(MESSAGE_FIELD, serde_json::to_string(message)?)],
+                )
+                .ignore();
+        }
+        // The connection is multiplexed, so clones share it.
+        pipeline
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        tracing::info!("added {} messages to {}", messages.len(), self.stream);
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! Webhook subscriptions are matched against the transfers of every processed range, creating
+//! deliveries in the same store update. Deliveries are attempted by the [WebhookWorker].
+use anyhow::Result;
+use data_store::{
+    models::{
+        NewWebhookDelivery, WebhookDelivery, WebhookKind, WebhookOutcome, WebhookSubscription,
+    },
+    store::DataStore,
+};
+use eth::types::{Address, U256};
+use event_retriever::db_reader::models::EventBase;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{collections::HashMap, time::Duration};
+
+/// Header holding the hex encoded HMAC-SHA256 of the request body (keyed by the secret).
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Body of a delivery.
+#[derive(Debug, Serialize)]
+struct WebhookEvent {
+    subscription_id: i64,
+    kind: &'static str,
+    block_number: i64,
+    log_index: i64,
+    contract_address: Address,
+    token_id: U256,
+    /// Recipient of the tokens.
+    owner: Address,
+    /// Tokens received (ERC1155 only).
+    amount: Option<String>,
+}
+
+impl WebhookEvent {
+    fn delivery(self) -> NewWebhookDelivery {
+        NewWebhookDelivery {
+            subscription_id: self.subscription_id,
+            block_number: self.block_number,
+            payload: serde_json::to_value(&self).expect("event is JSON serializable"),
+        }
+    }
+}
+
+/// Token transfer recorded by the transfer handlers (while there are subscriptions).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transfer {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub address: Address,
+    pub token_id: U256,
+    pub from: Address,
+    pub to: Address,
+    /// Tokens transferred (ERC1155 only).
+    pub amount: Option<U256>,
+}
+
+impl Transfer {
+    pub fn new(base: &EventBase, token_id: U256, from: Address, to: Address) -> Self {
+        Self {
+            block_number: base.block_number as i64,
+            log_index: base.log_index as i64,
+            address: base.contract_address,
+            token_id,
+            from,
+            to,
+            amount: None,
+        }
+    }
+}
+
+/// Matches the transfers of a range (in processing order) against the subscriptions:
+/// tokens are received by the recipient of every transfer and minted when sent from zero.
+pub(crate) fn match_transfers(
+    subscriptions: &[WebhookSubscription],
+    transfers: &[Transfer],
+) -> Vec<NewWebhookDelivery> {
+    let mut watched: HashMap<(WebhookKind, Address), Vec<i64>> = HashMap::new();
+    for subscription in subscriptions {
+        match subscription.kind() {
+            Some(kind) => watched
+                .entry((kind, subscription.address))
+                .or_default()
+                .push(subscription.id),
+            None => tracing::warn!(
+                "ignoring webhook {} of unknown kind {}",
+                subscription.id,
+                subscription.kind
+            ),
+        }
+    }
+    let subscribers = |kind, address| {
+        watched
+            .get(&(kind, address))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    };
+
+    let mut deliveries = vec![];
+    for transfer in transfers {
+        let mut matched = |kind: WebhookKind, subscription_ids: &[i64]| {
+            for &subscription_id in subscription_ids {
+                let event = WebhookEvent {
+                    subscription_id,
+                    kind: kind.as_str(),
+                    block_number: transfer.block_number,
+                    log_index: transfer.log_index,
+                    contract_address: transfer.address,
+                    token_id: transfer.token_id,
+                    owner: transfer.to,
+                    amount: transfer.amount.map(|amount| amount.to_string()),
+                };
+                deliveries.push(event.delivery());
+            }
+        };
+        if transfer.from == Address::zero() {
+            matched(
+                WebhookKind::Minted,
+                subscribers(WebhookKind::Minted, transfer.address),
+            );
+        }
+        if transfer.to != Address::zero() {
+            matched(
+                WebhookKind::Received,
+                subscribers(WebhookKind::Received, transfer.to),
+            );
+        }
+    }
+    deliveries
+}
+
+/// Hex encoded HMAC-SHA256 of `body`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Exponential backoff from 30 seconds, capped at 6 hours.
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 10) as u32;
+    Duration::from_secs(30 * 2u64.pow(exponent)).min(Duration::from_secs(6 * 60 * 60))
+}
+
+/// Attempts due deliveries, recording every attempt in the delivery log.
+pub struct WebhookWorker {
+    store: DataStore,
+    client: reqwest::Client,
+    /// Deliveries are failed after this many attempts.
+    max_attempts: i32,
+}
+
+impl WebhookWorker {
+    pub fn new(store: DataStore, max_attempts: i32) -> Self {
+        Self {
+            store,
+            client: reqwest::Client::new(),
+            max_attempts,
+        }
+    }
+
+    pub async fn run(&mut self, poll_secs: u64) -> Result<()> {
+        loop {
+            let attempted = self.deliver_due(100).await;
+            if attempted == 0 {
+                tokio::time::sleep(Duration::from_secs(poll_secs)).await;
+            }
+        }
+    }
+
+    /// Returns the number of attempted deliveries.
+    pub async fn deliver_due(&mut self, limit: i64) -> usize {
+        let due = self.store.due_webhook_deliveries(limit);
+        let attempted = due.len();
+        for (delivery, subscription) in due {
+            let outcome = self.attempt(&delivery, &subscription).await;
+            tracing::debug!("webhook delivery {}: {:?}", delivery.id, outcome);
+            self.store.record_webhook_attempt(delivery.id, outcome);
+        }
+        attempted
+    }
+
+    async fn attempt(
+        &self,
+        delivery: &WebhookDelivery,
+        subscription: &WebhookSubscription,
+    ) -> WebhookOutcome {
+        let body = serde_json::to_vec(&delivery.payload).expect("payload is JSON");
+        let response = self
+            .client
+            .post(&subscription.url)
+            .timeout(Duration::from_secs(10))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", signature(&subscription.secret, &body)),
+            )
+            .body(body)
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => WebhookOutcome::Delivered {
+                response_status: response.status().as_u16().into(),
+            },
+            Ok(response) => self.failure(
+                delivery,
+                Some(response.status().as_u16().into()),
+                format!("unexpected status {}", response.status()),
+            ),
+            Err(err) => self.failure(delivery, None, err.to_string()),
+        }
+    }
+
+    fn failure(
+        &self,
+        delivery: &WebhookDelivery,
+        response_status: Option<i32>,
+        error: String,
+    ) -> WebhookOutcome {
+        let attempts = delivery.attempts + 1;
+        if attempts >= self.max_attempts {
+            WebhookOutcome::Failed {
+                response_status,
+                error,
+            }
+        } else {
+            WebhookOutcome::Retry {
+                response_status,
+                error,
+                retry_in_secs: retry_delay(attempts).as_secs() as i64,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(id: i64, kind: WebhookKind, address: Address) -> WebhookSubscription {
+        WebhookSubscription {
+            id,
+            url: "https://hook".to_string(),
+            secret: "secret".to_string(),
+            kind: kind.as_str().to_string(),
+            address,
+            active: true,
+            created_at: Default::default(),
+        }
+    }
+
+    #[test]
+    fn match_received_and_minted() {
+        let contract = Address::from(1);
+        let wallet = Address::from(2);
+        let other = Address::from(3);
+        let subscriptions = [
+            subscription(1, WebhookKind::Received, wallet),
+            subscription(2, WebhookKind::Minted, contract),
+            subscription(3, WebhookKind::Received, other),
+        ];
+        let base = |block_number, log_index, contract_address| EventBase {
+            block_number,
+            log_index,
+            transaction_index: 0,
+            contract_address,
+        };
+        let token_id = U256::from(1);
+        let transfers = [
+            // ERC721 minted to the wallet and sent on in the same range.
+            Transfer::new(&base(10, 0, contract), token_id, Address::zero(), wallet),
+            Transfer::new(&base(10, 1, contract), token_id, wallet, other),
+            // ERC1155 tokens received by the wallet, then burned.
+            Transfer {
+                amount: Some(U256::from(3)),
+                ..Transfer::new(&base(15, 0, Address::from(4)), token_id, other, wallet)
+            },
+            Transfer {
+                amount: Some(U256::from(3)),
+                ..Transfer::new(
+                    &base(16, 0, Address::from(4)),
+                    token_id,
+                    wallet,
+                    Address::zero(),
+                )
+            },
+        ];
+
+        let deliveries = match_transfers(&subscriptions, &transfers);
+        let summary: Vec<_> = deliveries
+            .iter()
+            .map(|delivery| {
+                (
+                    delivery.subscription_id,
+                    delivery.block_number,
+                    delivery.payload["kind"].as_str().unwrap(),
+                    delivery.payload["amount"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, 10, "minted", None),
+                (1, 10, "received", None),
+                (3, 10, "received", None),
+                (1, 15, "received", Some("3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn hmac_signature() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn backoff() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(20), Duration::from_secs(6 * 60 * 60));
+    }
+}
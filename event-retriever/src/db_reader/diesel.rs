@@ -2,7 +2,7 @@ use crate::db_reader::{
     models::{
         db::{
             DbApprovalForAll, DbErc1155TransferBatch, DbErc1155TransferSingle, DbErc1155Uri,
-            DbErc721Approval, DbErc721Transfer, EvmEventTable,
+            DbErc721Approval, DbErc721Transfer, DbOwnershipTransferred, EvmEventTable,
         },
         merge_sorted_iters, EventMeta, NftEvent,
     },
@@ -10,10 +10,18 @@ use crate::db_reader::{
         self, approval_for_all::dsl::approval_for_all,
         erc1155_transfer_single::dsl::erc1155_transfer_single, erc1155_uri::dsl::erc1155_uri,
         erc721_approval::dsl::erc721_approval, erc721_transfer::dsl::erc721_transfer,
+        ownership_transferred::dsl::ownership_transferred,
     },
+    EventReading,
 };
 use anyhow::{Context, Result};
-use diesel::{pg::PgConnection, prelude::*, sql_query, sql_types::BigInt, Connection, RunQueryDsl};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+    Connection, RunQueryDsl,
+};
 use eth::types::{Address, BlockData, TxDetails};
 use std::collections::{btree_map::BTreeMap, HashMap};
 
@@ -27,6 +35,20 @@ pub struct BlockRange {
 
 pub struct EventSource {
     client: PgConnection,
+    /// Whether arak records receipt data (`value`, `gas_used`, ...) on `transactions`.
+    /// Older arak versions only store the base columns.
+    receipt_columns: bool,
+    /// Whether arak is configured to index `OwnershipTransferred` events.
+    ownership_transfers: bool,
+}
+
+/// Receipt columns written by newer arak versions.
+const RECEIPT_COLUMNS: [&str; 4] = ["value", "gas_used", "effective_gas_price", "status"];
+
+#[derive(QueryableByName)]
+struct ColumnCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
 }
 
 pub type BlockNum = u64;
@@ -35,14 +57,70 @@ pub type LogIndex = u64;
 pub type BlockEvents = BTreeMap<(TxIndex, LogIndex), Vec<NftEvent>>;
 pub type BlockRangeEvents = BTreeMap<BlockNum, BlockEvents>;
 
+/// Groups (ordered) events by block and transaction.
+pub fn group_events(events: impl IntoIterator<Item = NftEvent>) -> BlockRangeEvents {
+    let mut result: BlockRangeEvents = BTreeMap::new();
+    for event in events {
+        result
+            .entry(event.base.block_number)
+            .or_default()
+            .entry((event.base.transaction_index, event.base.log_index))
+            .or_default()
+            .push(event)
+    }
+    result
+}
+
 impl EventSource {
     pub fn new(connection: &str, schema: &str) -> Result<Self> {
         let mut conn = Self::establish_connection(connection)?;
         diesel::sql_query(format!("SET search_path TO {schema};"))
             .execute(&mut conn)
             .expect("Error setting search path");
+        let receipt_columns =
+            Self::count_columns(&mut conn, schema, "transactions", &RECEIPT_COLUMNS)?
+                == RECEIPT_COLUMNS.len() as i64;
+        if !receipt_columns {
+            tracing::warn!(
+                "{schema}.transactions has no receipt columns, transaction values and fees are read from the node"
+            );
+        }
+        // Arak only creates event tables that are configured.
+        let ownership_transfers = Self::count_columns(
+            &mut conn,
+            schema,
+            "ownership_transferred",
+            &["block_number"],
+        )? > 0;
+        if !ownership_transfers {
+            tracing::warn!(
+                "{schema}.ownership_transferred does not exist, contract owners will not be tracked"
+            );
+        }
+        Ok(Self {
+            client: conn,
+            receipt_columns,
+            ownership_transfers,
+        })
+    }
 
-        Ok(Self { client: conn })
+    /// Counts how many of `columns` exist on `schema.table`.
+    fn count_columns(
+        conn: &mut PgConnection,
+        schema: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> Result<i64> {
+        let result: ColumnCount = sql_query(
+            "SELECT count(*) AS count FROM information_schema.columns
+            WHERE table_schema = $1 AND table_name = $2 AND column_name = ANY($3)",
+        )
+        .bind::<Text, _>(schema)
+        .bind::<Text, _>(table)
+        .bind::<diesel::sql_types::Array<Text>, _>(columns)
+        .get_result(conn)
+        .context("Error reading table columns")?;
+        Ok(result.count)
     }
 
     fn establish_connection(db_url: &str) -> Result<PgConnection> {
@@ -72,10 +150,32 @@ impl EventSource {
             .filter(schema::blocks::dsl::number.ge(&range.start))
             .filter(schema::blocks::dsl::number.lt(&range.end))
             .load(&mut self.client)?;
-        let transactions: Vec<Transaction> = schema::transactions::dsl::transactions
+        let query = schema::transactions::dsl::transactions
             .filter(schema::transactions::dsl::block_number.ge(&range.start))
-            .filter(schema::transactions::dsl::block_number.lt(&range.end))
-            .load(&mut self.client)?;
+            .filter(schema::transactions::dsl::block_number.lt(&range.end));
+        let transactions: Vec<Transaction> = if self.receipt_columns {
+            query
+                .select(Transaction::as_select())
+                .load(&mut self.client)?
+        } else {
+            use schema::transactions::dsl as tx;
+            query
+                .select((tx::block_number, tx::index, tx::hash, tx::from, tx::to))
+                .load(&mut self.client)?
+                .into_iter()
+                .map(|(block_number, index, hash, from, to)| Transaction {
+                    block_number,
+                    index,
+                    hash,
+                    from,
+                    to,
+                    value: None,
+                    gas_used: None,
+                    effective_gas_price: None,
+                    status: None,
+                })
+                .collect()
+        };
 
         let mut tx_map: HashMap<i64, _> = HashMap::new();
         for tx in transactions {
@@ -88,6 +188,10 @@ impl EventSource {
                         hash: tx.hash,
                         from: tx.from,
                         to: tx.to.map(Address::from),
+                        value: tx.value,
+                        gas_used: tx.gas_used.map(|gas| gas as u64),
+                        effective_gas_price: tx.effective_gas_price,
+                        status: tx.status,
                     },
                 );
         }
@@ -98,7 +202,7 @@ impl EventSource {
                     block.number as u64,
                     BlockData {
                         number: block.number as u64,
-                        time: block.number as u64,
+                        time: block.time.and_utc().timestamp() as u64,
                         // default as empty hashmap is equivalent to no transactions in block.
                         transactions: tx_map.remove(&block.number).unwrap_or_default(),
                     },
@@ -116,7 +220,7 @@ impl EventSource {
     }
 
     pub fn get_events_for_block_range(&mut self, range: BlockRange) -> Result<BlockRangeEvents> {
-        let events = vec![
+        let mut events = vec![
             Box::new(self.get_approvals_for_all_for_block_range(range)?)
                 as Box<dyn Iterator<Item = NftEvent>>,
             Box::new(self.get_erc1155_transfers_batch_for_block_range(range)?),
@@ -125,19 +229,15 @@ impl EventSource {
             Box::new(self.get_erc721_approvals_for_block_range(range)?),
             Box::new(self.get_erc721_transfers_for_block_range(range)?),
         ];
+        if self.ownership_transfers {
+            events.push(Box::new(
+                self.get_ownership_transfers_for_block_range(range)?,
+            ));
+        }
         // We probably don't need this anymore (or this can construct the map).
         let ordered_events = merge_sorted_iters::<NftEvent>(events);
         tracing::debug!("Retrieved {} events for {:?}", ordered_events.len(), range);
-        let mut result: BlockRangeEvents = BTreeMap::new();
-        for event in ordered_events {
-            result
-                .entry(event.base.block_number)
-                .or_default()
-                .entry((event.base.transaction_index, event.base.log_index))
-                .or_default()
-                .push(event)
-        }
-        Ok(result)
+        Ok(group_events(ordered_events))
     }
 
     pub fn get_approvals_for_all_for_block_range(
@@ -247,6 +347,38 @@ impl EventSource {
             meta: EventMeta::Erc721Transfer(t.into()),
         }))
     }
+
+    pub fn get_ownership_transfers_for_block_range(
+        &mut self,
+        range: BlockRange,
+    ) -> Result<impl Iterator<Item = NftEvent>> {
+        let events: Vec<DbOwnershipTransferred> = ownership_transferred
+            .filter(schema::ownership_transferred::dsl::block_number.ge(&range.start))
+            .filter(schema::ownership_transferred::dsl::block_number.lt(&range.end))
+            .load(&mut self.client)?;
+        Ok(events.into_iter().map(|t| NftEvent {
+            base: t.event_base(),
+            meta: EventMeta::OwnershipTransferred(t.into()),
+        }))
+    }
+}
+
+impl EventReading for EventSource {
+    fn get_finalized_block(&mut self) -> i64 {
+        EventSource::get_finalized_block(self)
+    }
+
+    fn get_blocks_for_range(&mut self, range: BlockRange) -> Result<HashMap<u64, BlockData>> {
+        EventSource::get_blocks_for_range(self, range)
+    }
+
+    fn get_events_for_block_range(&mut self, range: BlockRange) -> Result<BlockRangeEvents> {
+        EventSource::get_events_for_block_range(self, range)
+    }
+
+    fn has_receipt_data(&self) -> bool {
+        self.receipt_columns
+    }
 }
 
 #[cfg(test)]
@@ -271,6 +403,18 @@ mod tests {
         EventSource::new(TEST_DB_URL, "public").unwrap()
     }
 
+    #[test]
+    fn blocks_for_range() {
+        let block = 15_000_123;
+        let blocks = test_client()
+            .get_blocks_for_range(single_block_range(block))
+            .unwrap();
+        let block_data = &blocks[&(block as u64)];
+        // Mined on June 21, 2022
+        assert!((1_655_769_600..1_655_856_000).contains(&block_data.time));
+        assert!(!block_data.transactions.is_empty());
+    }
+
     #[test]
     fn approvals_for_all() {
         // select block_number, count(*) cnt
@@ -1,3 +1,27 @@
+use anyhow::Result;
+use eth::types::BlockData;
+use std::collections::HashMap;
+
+use self::diesel::{BlockRange, BlockRangeEvents};
+
 pub mod diesel;
 pub mod models;
 mod schema;
+
+/// Source of events (and their block data) for the event handler.
+/// Implemented by arak ([EventSource](self::diesel::EventSource)) and by
+/// event dumps ([EventDump](crate::dump::EventDump)).
+pub trait EventReading: Send {
+    /// Events are available for all blocks before this one.
+    fn get_finalized_block(&mut self) -> i64;
+
+    fn get_blocks_for_range(&mut self, range: BlockRange) -> Result<HashMap<u64, BlockData>>;
+
+    fn get_events_for_block_range(&mut self, range: BlockRange) -> Result<BlockRangeEvents>;
+
+    /// False when transactions come without value, gas data & status
+    /// (i.e. from arak without receipt columns).
+    fn has_receipt_data(&self) -> bool {
+        true
+    }
+}
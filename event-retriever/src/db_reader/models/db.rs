@@ -1,7 +1,7 @@
 use crate::db_reader::{
     models::{
         ApprovalForAll, Erc1155TransferBatch, Erc1155TransferSingle, Erc1155Uri, Erc721Approval,
-        Erc721Transfer, EventBase,
+        Erc721Transfer, EventBase, OwnershipTransferred,
     },
     schema::*,
 };
@@ -176,6 +176,27 @@ impl From<DbErc721Transfer> for Erc721Transfer {
     }
 }
 
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = ownership_transferred)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub(crate) struct DbOwnershipTransferred {
+    block_number: i64,
+    log_index: i64,
+    transaction_index: i64,
+    address: Address,
+    previousowner_0: Address,
+    newowner_1: Address,
+}
+
+impl From<DbOwnershipTransferred> for OwnershipTransferred {
+    fn from(val: DbOwnershipTransferred) -> Self {
+        OwnershipTransferred {
+            previous_owner: val.previousowner_0,
+            new_owner: val.newowner_1,
+        }
+    }
+}
+
 macro_rules! impl_evm_event_table {
     ($x:ident) => {
         impl EvmEventTable for $x {
@@ -203,6 +224,7 @@ impl_evm_event_table!(DbErc1155TransferSingle);
 impl_evm_event_table!(DbErc1155Uri);
 impl_evm_event_table!(DbErc721Approval);
 impl_evm_event_table!(DbErc721Transfer);
+impl_evm_event_table!(DbOwnershipTransferred);
 
 #[derive(Queryable, Selectable, Clone, Debug, PartialEq)]
 #[diesel(table_name = transactions)]
@@ -213,6 +235,10 @@ pub struct Transaction {
     pub hash: Bytes32,
     pub from: Address,
     pub to: Option<Vec<u8>>,
+    pub value: Option<U256>,
+    pub gas_used: Option<i64>,
+    pub effective_gas_price: Option<U256>,
+    pub status: Option<bool>,
 }
 
 #[derive(Queryable, Selectable, Clone)]
@@ -367,6 +393,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn ownership_transferred_from_db() {
+        let addresses = n_addresses(3);
+        assert_eq!(
+            OwnershipTransferred::from(DbOwnershipTransferred {
+                block_number: 1,
+                log_index: 2,
+                transaction_index: 3,
+                address: addresses[0],
+                previousowner_0: addresses[1],
+                newowner_1: addresses[2],
+            }),
+            OwnershipTransferred {
+                previous_owner: addresses[1],
+                new_owner: addresses[2],
+            }
+        )
+    }
+
     struct TestStruct {
         block_number: i64,
         log_index: i64,
@@ -1,11 +1,12 @@
 use bigdecimal::{BigDecimal, Zero};
 use eth::types::{Address, U256};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashSet};
 use std::{cmp::Ordering, collections::BinaryHeap, fmt::Debug};
 
 pub(crate) mod db;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NftEvent {
     pub base: EventBase,
     pub meta: EventMeta,
@@ -30,7 +31,7 @@ impl Ord for NftEvent {
         self.base.cmp(&other.base)
     }
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum EventMeta {
     ApprovalForAll(ApprovalForAll),
     Erc1155TransferBatch(Erc1155TransferBatch),
@@ -38,10 +39,11 @@ pub enum EventMeta {
     Erc1155Uri(Erc1155Uri),
     Erc721Approval(Erc721Approval),
     Erc721Transfer(Erc721Transfer),
+    OwnershipTransferred(OwnershipTransferred),
 }
 
 /// Every Ethereum Event emits these properties
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct EventBase {
     pub block_number: u64,
     pub log_index: u64,
@@ -70,14 +72,14 @@ impl Ord for EventBase {
             .then_with(|| self.log_index.cmp(&other.log_index))
     }
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ApprovalForAll {
     pub owner: Address,
     pub operator: Address,
     pub approved: bool,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Erc1155TransferBatch {
     pub operator: Address,
     pub from: Address,
@@ -121,7 +123,7 @@ impl Erc1155TransferBatch {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Erc1155TransferSingle {
     pub operator: Address,
     pub from: Address,
@@ -130,26 +132,33 @@ pub struct Erc1155TransferSingle {
     pub value: U256,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Erc1155Uri {
     pub id: U256,
     pub value: String,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Erc721Approval {
     pub owner: Address,
     pub approved: Address,
     pub id: U256,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Erc721Transfer {
     pub from: Address,
     pub to: Address,
     pub token_id: U256,
 }
 
+/// Emitted by Ownable contracts whenever the contract owner changes.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct OwnershipTransferred {
+    pub previous_owner: Address,
+    pub new_owner: Address,
+}
+
 /// Merges a collection of Sorted Iterators into a sorted Vector of the Item.
 /// This implementation makes use of a Min Heap.
 pub fn merge_sorted_iters<T: Ord>(mut iters: Vec<Box<dyn Iterator<Item = T>>>) -> Vec<T> {
@@ -27,6 +27,10 @@ diesel::table! {
         hash -> Bytea,
         from -> Bytea,
         to -> Nullable<Bytea>,
+        value -> Nullable<Numeric>,
+        gas_used -> Nullable<Int8>,
+        effective_gas_price -> Nullable<Numeric>,
+        status -> Nullable<Bool>,
     }
 }
 
@@ -120,6 +124,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    ownership_transferred (block_number, log_index) {
+        block_number -> Int8,
+        log_index -> Int8,
+        transaction_index -> Int8,
+        address -> Bytea,
+        previousowner_0 -> Bytea,
+        newowner_1 -> Bytea,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     _event_block,
     approval_for_all,
@@ -130,4 +145,5 @@ diesel::allow_tables_to_appear_in_same_query!(
     erc1155_uri,
     erc721_approval,
     erc721_transfer,
+    ownership_transferred,
 );
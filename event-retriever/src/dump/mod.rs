@@ -0,0 +1,297 @@
+//! Versioned dumps of events and the blocks they were emitted in, so that event handling
+//! can be reproduced (i.e. for incident analysis) without access to arak or a node.
+use crate::db_reader::{
+    diesel::{group_events, BlockRange, BlockRangeEvents},
+    models::NftEvent,
+    EventReading,
+};
+use anyhow::{bail, Context, Result};
+use eth::types::BlockData;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+#[cfg(feature = "parquet")]
+mod parquet_file;
+
+/// Incremented on any change to the serialized records.
+pub const DUMP_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DumpFormat {
+    Jsonl,
+    Parquet,
+}
+
+impl DumpFormat {
+    /// Parquet for `.parquet` files, JSONL otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => Self::Parquet,
+            _ => Self::Jsonl,
+        }
+    }
+}
+
+/// Line of a JSONL dump, the first of which is the header.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DumpRecord {
+    Header { version: u32 },
+    Block(BlockData),
+    Event(NftEvent),
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if version != DUMP_VERSION {
+        bail!("unsupported dump version {version} (expected {DUMP_VERSION})");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct EventDump {
+    pub blocks: BTreeMap<u64, BlockData>,
+    /// Ordered by block and log index.
+    pub events: Vec<NftEvent>,
+}
+
+impl EventDump {
+    /// Keeps only the blocks in which events were emitted.
+    pub fn new(events: BlockRangeEvents, mut blocks: HashMap<u64, BlockData>) -> Result<Self> {
+        let mut dump = Self::default();
+        for (number, block_events) in events {
+            let block = blocks
+                .remove(&number)
+                .with_context(|| format!("missing block {number}"))?;
+            dump.blocks.insert(number, block);
+            dump.events.extend(block_events.into_values().flatten());
+        }
+        dump.events.sort();
+        Ok(dump)
+    }
+
+    pub fn first_block(&self) -> Option<u64> {
+        self.blocks.keys().next().copied()
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("open dump {}", path.display()))?;
+        match DumpFormat::from_path(path) {
+            DumpFormat::Jsonl => Self::read_jsonl(BufReader::new(file)),
+            DumpFormat::Parquet => Self::read_parquet(file),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("create dump {}", path.display()))?;
+        match DumpFormat::from_path(path) {
+            DumpFormat::Jsonl => self.write_jsonl(BufWriter::new(file)),
+            DumpFormat::Parquet => self.write_parquet(file),
+        }
+    }
+
+    fn read_jsonl(reader: impl BufRead) -> Result<Self> {
+        let mut lines = reader.lines();
+        match serde_json::from_str(&lines.next().context("empty dump")??)? {
+            DumpRecord::Header { version } => check_version(version)?,
+            _ => bail!("dump has no header"),
+        }
+        let mut dump = Self::default();
+        for (index, line) in lines.enumerate() {
+            match serde_json::from_str(&line?)
+                .with_context(|| format!("dump line {}", index + 2))?
+            {
+                DumpRecord::Block(block) => {
+                    dump.blocks.insert(block.number, block);
+                }
+                DumpRecord::Event(event) => dump.events.push(event),
+                DumpRecord::Header { .. } => bail!("unexpected header at line {}", index + 2),
+            }
+        }
+        dump.events.sort();
+        Ok(dump)
+    }
+
+    fn write_jsonl(&self, mut writer: impl Write) -> Result<()> {
+        let header = DumpRecord::Header {
+            version: DUMP_VERSION,
+        };
+        writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+        for block in self.blocks.values() {
+            writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&DumpRecord::Block(block.clone()))?
+            )?;
+        }
+        for event in &self.events {
+            writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&DumpRecord::Event(event.clone()))?
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "parquet")]
+    fn read_parquet(file: File) -> Result<Self> {
+        parquet_file::read(file)
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn read_parquet(_: File) -> Result<Self> {
+        bail!("parquet dumps require the `parquet` feature")
+    }
+
+    #[cfg(feature = "parquet")]
+    fn write_parquet(&self, file: File) -> Result<()> {
+        parquet_file::write(self, file)
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn write_parquet(&self, _: File) -> Result<()> {
+        bail!("parquet dumps require the `parquet` feature")
+    }
+}
+
+impl EventReading for EventDump {
+    /// The dump is complete, so all of its blocks are final.
+    fn get_finalized_block(&mut self) -> i64 {
+        self.blocks
+            .keys()
+            .next_back()
+            .map(|last| *last as i64 + 1)
+            .unwrap_or(0)
+    }
+
+    fn get_blocks_for_range(&mut self, range: BlockRange) -> Result<HashMap<u64, BlockData>> {
+        Ok(self
+            .blocks
+            .range(range.start as u64..range.end as u64)
+            .map(|(number, block)| (*number, block.clone()))
+            .collect())
+    }
+
+    fn get_events_for_block_range(&mut self, range: BlockRange) -> Result<BlockRangeEvents> {
+        let in_range = |event: &&NftEvent| {
+            (range.start as u64..range.end as u64).contains(&event.base.block_number)
+        };
+        Ok(group_events(self.events.iter().filter(in_range).cloned()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::db_reader::models::{Erc1155TransferSingle, Erc721Transfer, EventBase, EventMeta};
+    use eth::types::{Address, Bytes32, TxDetails, U256};
+
+    pub(crate) fn test_dump() -> EventDump {
+        let event = |block_number, log_index, meta| NftEvent {
+            base: EventBase {
+                block_number,
+                log_index,
+                transaction_index: 0,
+                contract_address: Address::from(1),
+            },
+            meta,
+        };
+        let transfer = EventMeta::Erc721Transfer(Erc721Transfer {
+            from: Address::zero(),
+            to: Address::from(2),
+            token_id: U256::from(3),
+        });
+        let single = EventMeta::Erc1155TransferSingle(Erc1155TransferSingle {
+            operator: Address::from(2),
+            from: Address::from(2),
+            to: Address::from(3),
+            id: U256::from(4),
+            value: U256::from(5),
+        });
+        let block = |number| BlockData {
+            number,
+            time: 1_600_000_000 + number,
+            transactions: HashMap::from([(
+                0,
+                TxDetails {
+                    hash: Bytes32::from(number),
+                    from: Address::from(2),
+                    to: Some(Address::from(1)),
+                    value: Some(U256::from(0)),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let events = group_events(vec![
+            event(10, 0, transfer.clone()),
+            event(10, 1, single),
+            event(12, 0, transfer),
+        ]);
+        let blocks = HashMap::from([(10, block(10)), (11, block(11)), (12, block(12))]);
+        EventDump::new(events, blocks).unwrap()
+    }
+
+    pub(crate) fn assert_same_dump(a: &EventDump, b: &EventDump) {
+        assert_eq!(a.blocks, b.blocks);
+        // NftEvent equality only compares event bases.
+        assert_eq!(format!("{:?}", a.events), format!("{:?}", b.events));
+    }
+
+    #[test]
+    fn new_dump() {
+        let dump = test_dump();
+        // Blocks without events are dropped.
+        assert_eq!(
+            dump.blocks.keys().copied().collect::<Vec<_>>(),
+            vec![10, 12]
+        );
+        assert_eq!(dump.events.len(), 3);
+        assert_eq!(dump.first_block(), Some(10));
+        assert!(EventDump::new(group_events(dump.events.clone()), HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let dump = test_dump();
+        let mut buffer = vec![];
+        dump.write_jsonl(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with(&format!(r#"{{"header":{{"version":{DUMP_VERSION}}}}}"#)));
+        assert_same_dump(&EventDump::read_jsonl(buffer.as_slice()).unwrap(), &dump);
+
+        // Other versions are rejected.
+        let future = text.replacen(
+            &format!(r#""version":{DUMP_VERSION}"#),
+            r#""version":999"#,
+            1,
+        );
+        assert!(EventDump::read_jsonl(future.as_bytes()).is_err());
+        // As are dumps without a header.
+        let headless: String = text
+            .lines()
+            .skip(1)
+            .map(|line| format!("{line}\n"))
+            .collect();
+        assert!(EventDump::read_jsonl(headless.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn event_reading() {
+        let mut dump = test_dump();
+        assert_eq!(dump.get_finalized_block(), 13);
+        let range = BlockRange { start: 11, end: 13 };
+        let blocks = dump.get_blocks_for_range(range).unwrap();
+        assert_eq!(blocks.keys().copied().collect::<Vec<_>>(), vec![12]);
+        let events = dump.get_events_for_block_range(range).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[&12][&(0, 0)].len(), 1);
+        assert_eq!(EventDump::default().get_finalized_block(), 0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Parquet dumps hold one row per block or event, with the record itself stored as JSON
+//! (so that both formats share a single schema version).
+use super::{check_version, EventDump, DUMP_VERSION};
+use anyhow::{bail, Context, Result};
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{
+        metadata::KeyValue, properties::WriterProperties, reader::FileReader,
+        serialized_reader::SerializedFileReader, writer::SerializedFileWriter,
+    },
+    record::RowAccessor,
+    schema::parser::parse_message_type,
+};
+use std::{fs::File, sync::Arc};
+
+const VERSION_KEY: &str = "evm_indexer_dump_version";
+const SCHEMA: &str = "
+    message event_dump {
+        REQUIRED BYTE_ARRAY kind (UTF8);
+        REQUIRED INT64 block_number;
+        REQUIRED BYTE_ARRAY record (UTF8);
+    }
+";
+
+pub(super) fn write(dump: &EventDump, file: File) -> Result<()> {
+    let mut kinds = vec![];
+    let mut block_numbers = vec![];
+    let mut records = vec![];
+    for block in dump.blocks.values() {
+        kinds.push(ByteArray::from("block"));
+        block_numbers.push(block.number as i64);
+        records.push(ByteArray::from(serde_json::to_vec(block)?));
+    }
+    for event in &dump.events {
+        kinds.push(ByteArray::from("event"));
+        block_numbers.push(event.base.block_number as i64);
+        records.push(ByteArray::from(serde_json::to_vec(event)?));
+    }
+
+    let properties = WriterProperties::builder()
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            VERSION_KEY.to_string(),
+            DUMP_VERSION.to_string(),
+        )]))
+        .build();
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(properties))?;
+    let mut row_group = writer.next_row_group()?;
+    let mut column = row_group.next_column()?.context("kind column")?;
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&kinds, None, None)?;
+    column.close()?;
+    let mut column = row_group.next_column()?.context("block_number column")?;
+    column
+        .typed::<Int64Type>()
+        .write_batch(&block_numbers, None, None)?;
+    column.close()?;
+    let mut column = row_group.next_column()?.context("record column")?;
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&records, None, None)?;
+    column.close()?;
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+pub(super) fn read(file: File) -> Result<EventDump> {
+    let reader = SerializedFileReader::new(file)?;
+    let version = reader
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == VERSION_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .context("dump has no version")?
+        .parse()
+        .context("invalid dump version")?;
+    check_version(version)?;
+
+    let mut dump = EventDump::default();
+    for row in reader.get_row_iter(None)? {
+        let row = row?;
+        let record = row.get_string(2)?;
+        match row.get_string(0)?.as_str() {
+            "block" => {
+                let block: eth::types::BlockData = serde_json::from_str(record)?;
+                dump.blocks.insert(block.number, block);
+            }
+            "event" => dump.events.push(serde_json::from_str(record)?),
+            kind => bail!("unknown record kind {kind}"),
+        }
+    }
+    dump.events.sort();
+    Ok(dump)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::tests::{assert_same_dump, test_dump};
+
+    #[test]
+    fn parquet_round_trip() {
+        let path = std::env::temp_dir().join(format!("event_dump-{}.parquet", std::process::id()));
+        let dump = test_dump();
+        dump.write(&path).unwrap();
+        assert_same_dump(&EventDump::read(&path).unwrap(), &dump);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 pub mod db_reader;
+pub mod dump;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,99 @@
+//! Batches the lookups of nested fields (i.e. the contract of every token in a page)
+//! into one store query per key type.
+use async_graphql::dataloader::Loader;
+use data_store::{
+    models::{ContractAbi, Erc1155, NftMetadata, TokenContract},
+    store::DataStore,
+};
+use eth::types::{Address, NftId};
+use std::{collections::HashMap, sync::Arc};
+use tokio::task::JoinError;
+
+/// Key of `nft_metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MetadataId(pub Vec<u8>);
+
+/// Key of `contract_abis`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AbiId(pub Vec<u8>);
+
+pub struct StoreLoader {
+    store: DataStore,
+}
+
+impl StoreLoader {
+    pub fn new(store: DataStore) -> Self {
+        Self { store }
+    }
+}
+
+/// Runs a (synchronous) store query off the async runtime.
+pub async fn blocking<T, F>(store: &DataStore, query: F) -> Result<T, Arc<JoinError>>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut DataStore) -> T + Send + 'static,
+{
+    let mut store = store.clone();
+    tokio::task::spawn_blocking(move || query(&mut store))
+        .await
+        .map_err(Arc::new)
+}
+
+impl Loader<Address> for StoreLoader {
+    type Value = TokenContract;
+    type Error = Arc<JoinError>;
+
+    async fn load(&self, keys: &[Address]) -> Result<HashMap<Address, TokenContract>, Self::Error> {
+        let keys = keys.to_vec();
+        let contracts = blocking(&self.store, move |store| store.load_contracts(&keys)).await?;
+        Ok(contracts
+            .into_iter()
+            .map(|contract| (contract.address, contract))
+            .collect())
+    }
+}
+
+impl Loader<NftId> for StoreLoader {
+    type Value = Erc1155;
+    type Error = Arc<JoinError>;
+
+    async fn load(&self, keys: &[NftId]) -> Result<HashMap<NftId, Erc1155>, Self::Error> {
+        let keys = keys.to_vec();
+        let tokens = blocking(&self.store, move |store| store.load_erc1155s(&keys)).await?;
+        Ok(tokens
+            .into_iter()
+            .map(|token| (token.id(), token))
+            .collect())
+    }
+}
+
+impl Loader<MetadataId> for StoreLoader {
+    type Value = NftMetadata;
+    type Error = Arc<JoinError>;
+
+    async fn load(
+        &self,
+        keys: &[MetadataId],
+    ) -> Result<HashMap<MetadataId, NftMetadata>, Self::Error> {
+        let uids: Vec<_> = keys.iter().map(|key| key.0.clone()).collect();
+        let documents = blocking(&self.store, move |store| store.load_nft_metadata(&uids)).await?;
+        Ok(documents
+            .into_iter()
+            .map(|document| (MetadataId(document.uid.clone()), document))
+            .collect())
+    }
+}
+
+impl Loader<AbiId> for StoreLoader {
+    type Value = ContractAbi;
+    type Error = Arc<JoinError>;
+
+    async fn load(&self, keys: &[AbiId]) -> Result<HashMap<AbiId, ContractAbi>, Self::Error> {
+        let uids: Vec<_> = keys.iter().map(|key| key.0.clone()).collect();
+        let abis = blocking(&self.store, move |store| store.load_contract_abis(&uids)).await?;
+        Ok(abis
+            .into_iter()
+            .map(|abi| (AbiId(abi.uid.clone()), abi))
+            .collect())
+    }
+}
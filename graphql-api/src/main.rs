@@ -0,0 +1,68 @@
+mod loader;
+mod pagination;
+mod schema;
+mod types;
+
+use actix_web::{
+    web::{self, Data},
+    App, HttpResponse, HttpServer,
+};
+use async_graphql::http::GraphiQLSource;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use clap::Parser;
+use data_store::store::DataStore;
+use schema::{build_schema, ApiSchema};
+use url::Url;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Store database connection string.
+    #[clap(long, env)]
+    store_url: Url,
+
+    /// DB schema
+    #[clap(long, env)]
+    db_schema: String,
+
+    /// The log filter.
+    #[clap(long, env, default_value = "info,actix_web=warn")]
+    log: String,
+
+    /// Address to serve the API on.
+    #[clap(long, env, default_value = "0.0.0.0:8080")]
+    bind: String,
+}
+
+async fn graphql(schema: Data<ApiSchema>, request: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(request.into_inner()).await.into()
+}
+
+async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+#[actix_web::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse_from(std::env::args());
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_env_filter(args.log)
+        .with_ansi(false)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+
+    let schema = build_schema(DataStore::new(args.store_url.as_str(), &args.db_schema)?);
+    tracing::info!("serving GraphQL API on {}", args.bind);
+    HttpServer::new(move || {
+        App::new().app_data(Data::new(schema.clone())).service(
+            web::resource("/graphql")
+                .route(web::post().to(graphql))
+                .route(web::get().to(graphiql)),
+        )
+    })
+    .bind(&args.bind)?
+    .run()
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Forward (keyset) pagination: cursors encode the ordering key of the last returned row.
+use async_graphql::{
+    connection::{Connection, Edge},
+    Error, OutputType, Result,
+};
+use eth::types::{Address, NftId, U256};
+use std::str::FromStr;
+
+pub const DEFAULT_PAGE_SIZE: i32 = 25;
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// Validated `first` argument.
+pub fn page_size(first: Option<i32>) -> Result<i64> {
+    match first.unwrap_or(DEFAULT_PAGE_SIZE) {
+        size @ 1..=MAX_PAGE_SIZE => Ok(size.into()),
+        size => Err(Error::new(format!(
+            "first must be between 1 and {MAX_PAGE_SIZE}, got {size}"
+        ))),
+    }
+}
+
+pub fn hex(address: Address) -> String {
+    format!("0x{:x}", address.0)
+}
+
+pub fn parse_address(value: &str) -> Result<Address> {
+    Address::from_str(value).map_err(|_| Error::new(format!("invalid address {value}")))
+}
+
+pub fn parse_token_id(value: &str) -> Result<U256> {
+    U256::from_dec_str(value).map_err(|_| Error::new(format!("invalid token id {value}")))
+}
+
+/// Cursor of a token: `<contract>/<token id>`.
+pub fn token_cursor(token: &NftId) -> String {
+    format!("{}/{}", hex(token.address), token.token_id)
+}
+
+pub fn parse_token_cursor(cursor: &str) -> Result<NftId> {
+    NftId::from_str(cursor).map_err(|_| Error::new(format!("invalid cursor {cursor}")))
+}
+
+/// Cursor of an ERC1155 balance: `<contract>/<token id>/<owner>`.
+pub fn balance_cursor(token: &NftId, owner: Address) -> String {
+    format!("{}/{}", token_cursor(token), hex(owner))
+}
+
+pub fn parse_balance_cursor(cursor: &str) -> Result<(NftId, Address)> {
+    let invalid = || Error::new(format!("invalid cursor {cursor}"));
+    let (token, owner) = cursor.rsplit_once('/').ok_or_else(invalid)?;
+    Ok((
+        parse_token_cursor(token)?,
+        Address::from_str(owner).map_err(|_| invalid())?,
+    ))
+}
+
+/// Page of (up to) `limit` nodes from `rows`, fetched with a limit of `limit + 1`
+/// so that the presence of a next page is known.
+pub fn connection<T, N: OutputType>(
+    mut rows: Vec<T>,
+    limit: i64,
+    has_previous_page: bool,
+    cursor: impl Fn(&T) -> String,
+    node: impl Fn(T) -> N,
+) -> Connection<String, N> {
+    let has_next_page = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let mut connection = Connection::new(has_previous_page, has_next_page);
+    connection.edges.extend(
+        rows.into_iter()
+            .map(|row| Edge::new(cursor(&row), node(row))),
+    );
+    connection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors() {
+        let token = NftId {
+            address: Address::from(1),
+            token_id: U256::from(42),
+        };
+        let cursor = token_cursor(&token);
+        assert_eq!(cursor, "0x0000000000000000000000000000000000000001/42");
+        assert_eq!(parse_token_cursor(&cursor).unwrap(), token);
+
+        let cursor = balance_cursor(&token, Address::from(2));
+        assert_eq!(
+            parse_balance_cursor(&cursor).unwrap(),
+            (token, Address::from(2))
+        );
+        assert!(parse_token_cursor("0x01").is_err());
+        assert!(parse_balance_cursor(&token_cursor(&token)).is_err());
+    }
+
+    #[test]
+    fn page_sizes() {
+        assert_eq!(page_size(None).unwrap(), 25);
+        assert_eq!(page_size(Some(100)).unwrap(), 100);
+        assert!(page_size(Some(0)).is_err());
+        assert!(page_size(Some(101)).is_err());
+    }
+
+    #[test]
+    fn next_page() {
+        let page = connection(vec![1, 2, 3], 2, false, i32::to_string, |row| row);
+        assert_eq!(page.edges.len(), 2);
+        assert!(page.has_next_page);
+        assert!(!page.has_previous_page);
+        assert_eq!(page.edges[1].cursor, "2");
+
+        let page = connection(vec![3], 2, true, i32::to_string, |row| row);
+        assert!(!page.has_next_page);
+        assert!(page.has_previous_page);
+    }
+}
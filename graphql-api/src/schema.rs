@@ -0,0 +1,184 @@
+use crate::{
+    loader::{blocking, StoreLoader},
+    pagination::{
+        connection, hex, page_size, parse_address, parse_token_cursor, parse_token_id, token_cursor,
+    },
+    types::{balance_page, nft_page, Balance, Contract, MultiToken, Owner, Token},
+};
+use async_graphql::{
+    connection::Connection, dataloader::DataLoader, Context, EmptyMutation, EmptySubscription,
+    Object, Result, Schema,
+};
+use data_store::{queries::TokenFilter, store::DataStore};
+use eth::types::NftId;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Allows owner → nfts → edges → node → contract → abi (and metadata attributes) with room to spare.
+const MAX_DEPTH: usize = 12;
+
+pub fn build_schema(store: DataStore) -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(DataLoader::new(
+            StoreLoader::new(store.clone()),
+            tokio::spawn,
+        ))
+        .data(store)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+fn filter(
+    contract: Option<String>,
+    owner: Option<String>,
+    minter: Option<String>,
+) -> Result<TokenFilter> {
+    let parse = |value: Option<String>| value.as_deref().map(parse_address).transpose();
+    Ok(TokenFilter {
+        contract: parse(contract)?,
+        owner: parse(owner)?,
+        minter: parse(minter)?,
+    })
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn nft(
+        &self,
+        ctx: &Context<'_>,
+        contract: String,
+        token_id: String,
+    ) -> Result<Option<Token>> {
+        let token = NftId {
+            address: parse_address(&contract)?,
+            token_id: parse_token_id(&token_id)?,
+        };
+        let nft = blocking(ctx.data_unchecked::<DataStore>(), move |store| {
+            store.load_nft(&token)
+        })
+        .await?;
+        Ok(nft.map(Token))
+    }
+
+    /// ERC721 tokens ordered by contract and token id.
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract: Option<String>,
+        owner: Option<String>,
+        minter: Option<String>,
+    ) -> Result<Connection<String, Token>> {
+        nft_page(ctx, filter(contract, owner, minter)?, first, after).await
+    }
+
+    /// ERC1155 tokens ordered by contract and token id.
+    async fn erc1155s(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract: Option<String>,
+        minter: Option<String>,
+    ) -> Result<Connection<String, MultiToken>> {
+        let filter = filter(contract, None, minter)?;
+        let limit = page_size(first)?;
+        let after = after.as_deref().map(parse_token_cursor).transpose()?;
+        let rows = blocking(ctx.data_unchecked::<DataStore>(), move |store| {
+            store.load_erc1155_page(&filter, after.as_ref(), limit + 1)
+        })
+        .await?;
+        Ok(connection(
+            rows,
+            limit,
+            after.is_some(),
+            |token| token_cursor(&token.id()),
+            MultiToken,
+        ))
+    }
+
+    /// Positive ERC1155 balances ordered by contract, token id and owner.
+    async fn erc1155_balances(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract: Option<String>,
+        owner: Option<String>,
+    ) -> Result<Connection<String, Balance>> {
+        balance_page(ctx, filter(contract, owner, None)?, first, after).await
+    }
+
+    async fn contract(&self, ctx: &Context<'_>, address: String) -> Result<Option<Contract>> {
+        let contract = ctx
+            .data_unchecked::<DataLoader<StoreLoader>>()
+            .load_one(parse_address(&address)?)
+            .await?;
+        Ok(contract.map(Contract))
+    }
+
+    /// Token contracts ordered by address.
+    async fn contracts(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Contract>> {
+        let limit = page_size(first)?;
+        let after = after.as_deref().map(parse_address).transpose()?;
+        let rows = blocking(ctx.data_unchecked::<DataStore>(), move |store| {
+            store.load_contract_page(after, limit + 1)
+        })
+        .await?;
+        Ok(connection(
+            rows,
+            limit,
+            after.is_some(),
+            |contract| hex(contract.address),
+            Contract,
+        ))
+    }
+
+    /// Tokens held by `address`.
+    async fn owner(&self, address: String) -> Result<Owner> {
+        Ok(Owner(parse_address(&address)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sdl() {
+        let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish();
+        let sdl = schema.sdl();
+        for expected in [
+            "type NftConnection",
+            "erc1155Balances(",
+            "type Erc1155Balance",
+            "attributes: [Attribute!]!",
+        ] {
+            assert!(sdl.contains(expected), "missing {expected}");
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments() {
+        let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish();
+        let response = schema
+            .execute(r#"{ owner(address: "0x01") { address } }"#)
+            .await;
+        assert_eq!(response.errors[0].message, "invalid address 0x01");
+        let response = schema
+            .execute(r#"{ nfts(first: 1000) { edges { cursor } } }"#)
+            .await;
+        assert_eq!(
+            response.errors[0].message,
+            "first must be between 1 and 100, got 1000"
+        );
+    }
+}
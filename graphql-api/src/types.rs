@@ -0,0 +1,408 @@
+//! GraphQL objects wrapping the store models. Nested lookups go through the [StoreLoader].
+use crate::{
+    loader::{blocking, AbiId, MetadataId, StoreLoader},
+    pagination::{
+        balance_cursor, connection, hex, page_size, parse_address, parse_balance_cursor,
+        parse_token_cursor, token_cursor,
+    },
+};
+use async_graphql::{
+    connection::Connection, dataloader::DataLoader, Context, Json, Object, Result, SimpleObject,
+};
+use data_store::{
+    models::{Erc1155, Erc1155Owner, Nft, NftMetadata, TokenContract},
+    queries::TokenFilter,
+    store::DataStore,
+};
+use eth::types::{Address, NftId};
+use serde_json::Value;
+
+fn loader<'a>(ctx: &Context<'a>) -> &'a DataLoader<StoreLoader> {
+    ctx.data_unchecked::<DataLoader<StoreLoader>>()
+}
+
+async fn load_metadata(ctx: &Context<'_>, id: &Option<Vec<u8>>) -> Result<Option<Metadata>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    let document = loader(ctx).load_one(MetadataId(id.clone())).await?;
+    Ok(document.map(Metadata))
+}
+
+async fn load_contract(ctx: &Context<'_>, address: Address) -> Result<Option<Contract>> {
+    Ok(loader(ctx).load_one(address).await?.map(Contract))
+}
+
+/// Page of ERC721 tokens matching `filter`.
+pub(crate) async fn nft_page(
+    ctx: &Context<'_>,
+    filter: TokenFilter,
+    first: Option<i32>,
+    after: Option<String>,
+) -> Result<Connection<String, Token>> {
+    let limit = page_size(first)?;
+    let after = after.as_deref().map(parse_token_cursor).transpose()?;
+    let rows = blocking(ctx.data_unchecked::<DataStore>(), move |store| {
+        store.load_nft_page(&filter, after.as_ref(), limit + 1)
+    })
+    .await?;
+    Ok(connection(
+        rows,
+        limit,
+        after.is_some(),
+        |nft| {
+            token_cursor(&NftId {
+                address: nft.contract_address,
+                token_id: nft.token_id.clone().into(),
+            })
+        },
+        Token,
+    ))
+}
+
+/// Page of (positive) ERC1155 balances matching `filter`.
+pub(crate) async fn balance_page(
+    ctx: &Context<'_>,
+    filter: TokenFilter,
+    first: Option<i32>,
+    after: Option<String>,
+) -> Result<Connection<String, Balance>> {
+    let limit = page_size(first)?;
+    let after = after.as_deref().map(parse_balance_cursor).transpose()?;
+    let rows = blocking(ctx.data_unchecked::<DataStore>(), move |store| {
+        let after = after.as_ref().map(|(token, owner)| (token, *owner));
+        store.load_erc1155_owner_page(&filter, after, limit + 1)
+    })
+    .await?;
+    Ok(connection(
+        rows,
+        limit,
+        after.is_some(),
+        |balance| balance_cursor(&Balance::nft_id(balance), balance.owner),
+        Balance,
+    ))
+}
+
+/// ERC721 token.
+pub struct Token(pub Nft);
+
+#[Object(name = "Nft")]
+impl Token {
+    async fn contract_address(&self) -> String {
+        hex(self.0.contract_address)
+    }
+
+    async fn token_id(&self) -> String {
+        self.0.token_id.to_string()
+    }
+
+    async fn token_uri(&self) -> Option<&str> {
+        self.0.token_uri.as_deref()
+    }
+
+    async fn owner(&self) -> String {
+        hex(self.0.owner)
+    }
+
+    async fn minter(&self) -> String {
+        hex(self.0.minter)
+    }
+
+    async fn approved(&self) -> Option<String> {
+        self.0.approved.clone().map(|approved| hex(approved.into()))
+    }
+
+    async fn mint_block(&self) -> i64 {
+        self.0.mint_block
+    }
+
+    async fn last_transfer_block(&self) -> Option<i64> {
+        self.0.last_transfer_block
+    }
+
+    async fn burn_block(&self) -> Option<i64> {
+        self.0.burn_block
+    }
+
+    async fn contract(&self, ctx: &Context<'_>) -> Result<Option<Contract>> {
+        load_contract(ctx, self.0.contract_address).await
+    }
+
+    async fn metadata(&self, ctx: &Context<'_>) -> Result<Option<Metadata>> {
+        load_metadata(ctx, &self.0.metadata_id).await
+    }
+}
+
+/// ERC1155 token.
+pub struct MultiToken(pub Erc1155);
+
+#[Object(name = "Erc1155")]
+impl MultiToken {
+    async fn contract_address(&self) -> String {
+        hex(self.0.contract_address)
+    }
+
+    async fn token_id(&self) -> String {
+        self.0.token_id.to_string()
+    }
+
+    async fn token_uri(&self) -> Option<&str> {
+        self.0.token_uri.as_deref()
+    }
+
+    async fn total_supply(&self) -> String {
+        self.0.total_supply.to_string()
+    }
+
+    async fn creator(&self) -> String {
+        hex(self.0.creator_address)
+    }
+
+    async fn mint_block(&self) -> i64 {
+        self.0.mint_block
+    }
+
+    async fn contract(&self, ctx: &Context<'_>) -> Result<Option<Contract>> {
+        load_contract(ctx, self.0.contract_address).await
+    }
+
+    async fn metadata(&self, ctx: &Context<'_>) -> Result<Option<Metadata>> {
+        load_metadata(ctx, &self.0.metadata_id).await
+    }
+}
+
+/// ERC1155 balance of an owner.
+pub struct Balance(pub Erc1155Owner);
+
+impl Balance {
+    fn nft_id(balance: &Erc1155Owner) -> NftId {
+        NftId {
+            address: balance.contract_address,
+            token_id: balance.token_id.clone().into(),
+        }
+    }
+}
+
+#[Object(name = "Erc1155Balance")]
+impl Balance {
+    async fn contract_address(&self) -> String {
+        hex(self.0.contract_address)
+    }
+
+    async fn token_id(&self) -> String {
+        self.0.token_id.to_string()
+    }
+
+    async fn owner(&self) -> String {
+        hex(self.0.owner)
+    }
+
+    async fn balance(&self) -> String {
+        self.0.balance.to_string()
+    }
+
+    async fn token(&self, ctx: &Context<'_>) -> Result<Option<MultiToken>> {
+        let token = loader(ctx).load_one(Self::nft_id(&self.0)).await?;
+        Ok(token.map(MultiToken))
+    }
+}
+
+pub struct Contract(pub TokenContract);
+
+#[Object]
+impl Contract {
+    async fn address(&self) -> String {
+        hex(self.0.address)
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn symbol(&self) -> Option<&str> {
+        self.0.symbol.as_deref()
+    }
+
+    async fn created_block(&self) -> i64 {
+        self.0.created_block
+    }
+
+    async fn deployer(&self) -> Option<String> {
+        self.0.deployer.clone().map(|deployer| hex(deployer.into()))
+    }
+
+    async fn deployment_block(&self) -> Option<i64> {
+        self.0.deployment_block
+    }
+
+    async fn abi(&self, ctx: &Context<'_>) -> Result<Option<Json<Value>>> {
+        let Some(id) = &self.0.abi_id else {
+            return Ok(None);
+        };
+        let abi = loader(ctx).load_one(AbiId(id.clone())).await?;
+        Ok(abi.map(|abi| Json(abi.abi)))
+    }
+
+    /// ERC721 tokens of the contract (optionally of one owner).
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        owner: Option<String>,
+    ) -> Result<Connection<String, Token>> {
+        let filter = TokenFilter {
+            contract: Some(self.0.address),
+            owner: owner.as_deref().map(parse_address).transpose()?,
+            minter: None,
+        };
+        nft_page(ctx, filter, first, after).await
+    }
+}
+
+/// Tokens held by an address.
+pub struct Owner(pub Address);
+
+#[Object]
+impl Owner {
+    async fn address(&self) -> String {
+        hex(self.0)
+    }
+
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract: Option<String>,
+    ) -> Result<Connection<String, Token>> {
+        let filter = TokenFilter {
+            contract: contract.as_deref().map(parse_address).transpose()?,
+            owner: Some(self.0),
+            minter: None,
+        };
+        nft_page(ctx, filter, first, after).await
+    }
+
+    async fn erc1155_balances(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract: Option<String>,
+    ) -> Result<Connection<String, Balance>> {
+        let filter = TokenFilter {
+            contract: contract.as_deref().map(parse_address).transpose()?,
+            owner: Some(self.0),
+            minter: None,
+        };
+        balance_page(ctx, filter, first, after).await
+    }
+}
+
+#[derive(SimpleObject, Debug, PartialEq)]
+pub struct Attribute {
+    trait_type: Option<String>,
+    /// Strings as is, other JSON values serialized.
+    value: String,
+    display_type: Option<String>,
+}
+
+/// Attributes of an (OpenSea standard) metadata document.
+fn attributes(json: &Value) -> Vec<Attribute> {
+    let text = |value: &Value| match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let Some(Value::Array(attributes)) = json.get("attributes") else {
+        return vec![];
+    };
+    attributes
+        .iter()
+        .filter_map(|attribute| {
+            Some(Attribute {
+                trait_type: attribute.get("trait_type").map(text),
+                value: text(attribute.get("value")?),
+                display_type: attribute.get("display_type").map(text),
+            })
+        })
+        .collect()
+}
+
+pub struct Metadata(pub NftMetadata);
+
+impl Metadata {
+    fn field(&self, name: &str) -> Option<String> {
+        match self.0.json.as_ref()?.get(name)? {
+            Value::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[Object]
+impl Metadata {
+    async fn raw(&self) -> Option<&str> {
+        self.0.raw.as_deref()
+    }
+
+    async fn json(&self) -> Option<Json<Value>> {
+        self.0.json.clone().map(Json)
+    }
+
+    async fn name(&self) -> Option<String> {
+        self.field("name")
+    }
+
+    async fn description(&self) -> Option<String> {
+        self.field("description")
+    }
+
+    async fn image(&self) -> Option<String> {
+        self.field("image")
+    }
+
+    async fn attributes(&self) -> Vec<Attribute> {
+        self.0.json.as_ref().map(attributes).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn metadata_attributes() {
+        let document = json!({
+            "name": "Token",
+            "attributes": [
+                {"trait_type": "Color", "value": "red"},
+                {"trait_type": "Level", "value": 5, "display_type": "number"},
+                {"trait_type": "Missing"},
+                {"value": true},
+            ]
+        });
+        assert_eq!(
+            attributes(&document),
+            vec![
+                Attribute {
+                    trait_type: Some("Color".to_string()),
+                    value: "red".to_string(),
+                    display_type: None,
+                },
+                Attribute {
+                    trait_type: Some("Level".to_string()),
+                    value: "5".to_string(),
+                    display_type: Some("number".to_string()),
+                },
+                Attribute {
+                    trait_type: None,
+                    value: "true".to_string(),
+                    display_type: None,
+                },
+            ]
+        );
+        assert!(attributes(&json!({"attributes": "none"})).is_empty());
+    }
+}
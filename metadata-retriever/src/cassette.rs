@@ -0,0 +1,187 @@
+//! Recorded fetcher responses, so that request handlers can be tested without network access.
+//! In record mode, the live fetcher's responses (including failures) are saved for later runs.
+use crate::routes::{
+    contract::abi::AbiFetching,
+    token::metadata::{FetchedMetadata, MetadataFetching},
+};
+use anyhow::{anyhow, Result};
+use eth::{
+    rpc::cassette::{CassetteFile, CassetteMode},
+    types::{Address, NftId},
+};
+use serde_json::Value;
+use std::{path::PathBuf, sync::Arc};
+
+/// Errors are recorded by message, since they can't be serialized.
+fn record_result<T: serde::Serialize>(
+    file: &CassetteFile,
+    key: String,
+    result: &Result<T>,
+) -> Result<()> {
+    let recorded = match result {
+        Ok(value) => Ok(value),
+        Err(err) => Err(err.to_string()),
+    };
+    file.record([(key, recorded)])
+}
+
+fn replay_result<T: serde::de::DeserializeOwned>(file: &CassetteFile, key: &str) -> Result<T> {
+    file.get::<Result<T, String>>(key)?
+        .map_err(|err| anyhow!("recorded failure: {err}"))
+}
+
+pub struct RecordedMetadata {
+    file: CassetteFile,
+    /// Live fetcher (record mode only).
+    fetcher: Option<Arc<dyn MetadataFetching>>,
+}
+
+impl RecordedMetadata {
+    pub fn new<F>(mode: CassetteMode, path: impl Into<PathBuf>, fetcher: F) -> Result<Self>
+    where
+        F: FnOnce() -> Arc<dyn MetadataFetching>,
+    {
+        Ok(Self {
+            file: CassetteFile::load(path)?,
+            fetcher: (mode == CassetteMode::Record).then(fetcher),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataFetching for RecordedMetadata {
+    async fn get_nft_metadata(&self, token: NftId, uri: Option<String>) -> Result<FetchedMetadata> {
+        let key = format!("metadata/{token}/{}", uri.as_deref().unwrap_or_default());
+        match &self.fetcher {
+            Some(fetcher) => {
+                let result = fetcher.get_nft_metadata(token, uri).await;
+                record_result(&self.file, key, &result)?;
+                result
+            }
+            None => replay_result(&self.file, &key),
+        }
+    }
+}
+
+pub struct RecordedAbi {
+    file: CassetteFile,
+    /// Live fetcher (record mode only).
+    fetcher: Option<Arc<dyn AbiFetching>>,
+}
+
+impl RecordedAbi {
+    pub fn new<F>(mode: CassetteMode, path: impl Into<PathBuf>, fetcher: F) -> Result<Self>
+    where
+        F: FnOnce() -> Arc<dyn AbiFetching>,
+    {
+        Ok(Self {
+            file: CassetteFile::load(path)?,
+            fetcher: (mode == CassetteMode::Record).then(fetcher),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AbiFetching for RecordedAbi {
+    async fn get_contract_abi(&self, address: Address) -> Result<Option<Value>> {
+        let key = format!("abi/{address}");
+        match &self.fetcher {
+            Some(fetcher) => {
+                let result = fetcher.get_contract_abi(address).await;
+                record_result(&self.file, key, &result)?;
+                result
+            }
+            None => replay_result(&self.file, &key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth::types::U256;
+    use serde_json::json;
+
+    struct StaticFetcher;
+
+    #[async_trait::async_trait]
+    impl MetadataFetching for StaticFetcher {
+        async fn get_nft_metadata(&self, _: NftId, uri: Option<String>) -> Result<FetchedMetadata> {
+            match uri {
+                Some(uri) => Ok(FetchedMetadata::error(&uri)),
+                None => Err(anyhow!("no uri")),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AbiFetching for StaticFetcher {
+        async fn get_contract_abi(&self, address: Address) -> Result<Option<Value>> {
+            Ok((address != Address::zero()).then(|| json!([{"type": "function"}])))
+        }
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn metadata_record_and_replay() {
+        let path = cassette_path("metadata_record_and_replay");
+        let token = NftId {
+            address: Address::from(1),
+            token_id: U256::from(2),
+        };
+        let uri = Some("ipfs://QmHash".to_string());
+        let recorder =
+            RecordedMetadata::new(CassetteMode::Record, &path, || Arc::new(StaticFetcher)).unwrap();
+        let fetched = recorder.get_nft_metadata(token, uri.clone()).await.unwrap();
+        assert!(recorder.get_nft_metadata(token, None).await.is_err());
+
+        let player = RecordedMetadata::new(
+            CassetteMode::Replay,
+            &path,
+            || -> Arc<dyn MetadataFetching> { panic!("replay doesn't fetch") },
+        )
+        .unwrap();
+        assert_eq!(player.get_nft_metadata(token, uri).await.unwrap(), fetched);
+        let failure = player.get_nft_metadata(token, None).await.unwrap_err();
+        assert_eq!(failure.to_string(), "recorded failure: no uri");
+        // Unrecorded requests fail.
+        assert!(player
+            .get_nft_metadata(token, Some("https://other".to_string()))
+            .await
+            .is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn abi_record_and_replay() {
+        let path = cassette_path("abi_record_and_replay");
+        let recorder =
+            RecordedAbi::new(CassetteMode::Record, &path, || Arc::new(StaticFetcher)).unwrap();
+        let abi = recorder.get_contract_abi(Address::from(1)).await.unwrap();
+        assert!(abi.is_some());
+        assert_eq!(
+            recorder.get_contract_abi(Address::zero()).await.unwrap(),
+            None
+        );
+
+        let player = RecordedAbi::new(CassetteMode::Replay, &path, || -> Arc<dyn AbiFetching> {
+            panic!("replay doesn't fetch")
+        })
+        .unwrap();
+        assert_eq!(
+            player.get_contract_abi(Address::from(1)).await.unwrap(),
+            abi
+        );
+        assert_eq!(
+            player.get_contract_abi(Address::zero()).await.unwrap(),
+            None
+        );
+        assert!(player.get_contract_abi(Address::from(2)).await.is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,10 +1,38 @@
+use crate::{
+    consumer::ConsumerConfig,
+    routes::token::{metadata::limiter::FetchLimits, UriCacheTtl},
+};
 use anyhow::{Context, Result};
+use std::str::FromStr;
 
 pub struct Config {
     pub store_url: String,
     pub store_schema: String,
     pub etherscan_key: String,
     pub alchemy_key: Option<String>,
+    /// Transport to consume messages from (in addition to the HTTP routes).
+    pub message_consumer: Option<ConsumerConfig>,
+    /// Failed fetches are redelivered (by PubSub push) until they were attempted this many times.
+    pub max_fetch_attempts: i32,
+    /// Expected audience of the OIDC tokens of push deliveries (verification is disabled if unset).
+    pub push_audience: Option<String>,
+    /// Expected service account (email) of the OIDC tokens of push deliveries (any if unset).
+    pub push_service_account: Option<String>,
+    /// Concurrency and rate limits of metadata requests (shared by all workers).
+    pub fetch_limits: FetchLimits,
+    pub uri_cache_ttl: UriCacheTtl,
+}
+
+/// Parsed value of the environment variable `name`, if set.
+fn optional_var<T: FromStr>(name: &str) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    std::env::var(name)
+        .ok()
+        .map(|value| value.parse())
+        .transpose()
+        .with_context(|| format!("invalid {name}"))
 }
 
 impl Config {
@@ -14,6 +42,27 @@ impl Config {
             store_schema: std::env::var("DB_SCHEMA").context("missing DB_SCHEMA")?,
             etherscan_key: std::env::var("ETHERSCAN_KEY").context("missing ETHERSCAN_KEY")?,
             alchemy_key: std::env::var("ALCHEMY_KEY").ok(),
+            message_consumer: std::env::var("MESSAGE_CONSUMER")
+                .ok()
+                .map(|consumer| consumer.parse())
+                .transpose()?,
+            max_fetch_attempts: optional_var("MAX_FETCH_ATTEMPTS")?.unwrap_or(5),
+            push_audience: std::env::var("PUSH_AUDIENCE").ok(),
+            push_service_account: std::env::var("PUSH_SERVICE_ACCOUNT").ok(),
+            fetch_limits: FetchLimits {
+                concurrency: optional_var("FETCH_CONCURRENCY")?
+                    .unwrap_or(FetchLimits::default().concurrency),
+                host_concurrency: optional_var("HOST_CONCURRENCY")?
+                    .unwrap_or(FetchLimits::default().host_concurrency),
+                host_rate: optional_var("HOST_REQUESTS_PER_SEC")?
+                    .or(FetchLimits::default().host_rate),
+            },
+            uri_cache_ttl: UriCacheTtl {
+                mutable_secs: optional_var("URI_CACHE_TTL_SECS")?
+                    .unwrap_or(UriCacheTtl::default().mutable_secs),
+                immutable_secs: optional_var("IMMUTABLE_URI_CACHE_TTL_SECS")?
+                    .unwrap_or(UriCacheTtl::default().immutable_secs),
+            },
         })
     }
 }
@@ -0,0 +1,59 @@
+use crate::app::AppData;
+use anyhow::{anyhow, Result};
+use eth::types::Message;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Maximum number of messages processed at once.
+const BATCH_SIZE: usize = 100;
+
+/// Reads the named in-process channel (see [eth::channel]), of which there is a single consumer.
+pub(super) async fn consume(name: &str, state: AppData) -> Result<()> {
+    let mut receiver =
+        eth::channel::take_receiver(name).ok_or_else(|| anyhow!("channel {name} is consumed"))?;
+    while let Some(messages) = next_batch(&mut receiver).await {
+        state.process_messages(messages).await;
+    }
+    Ok(())
+}
+
+/// Waits for a message, along with those already queued (None once all senders are dropped).
+async fn next_batch(receiver: &mut UnboundedReceiver<Message>) -> Option<Vec<Message>> {
+    let mut messages = vec![receiver.recv().await?];
+    while messages.len() < BATCH_SIZE {
+        match receiver.try_recv() {
+            Ok(message) => messages.push(message),
+            Err(_) => break,
+        }
+    }
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth::types::Address;
+
+    #[tokio::test]
+    async fn channel_batches() {
+        let sender = eth::channel::sender("channel_batches");
+        let messages: Vec<_> = (0..BATCH_SIZE as u64 + 1)
+            .map(|address| Message::Contract {
+                address: Address::from(address),
+            })
+            .collect();
+        for message in &messages {
+            sender.send(message.clone()).unwrap();
+        }
+        let mut receiver = eth::channel::take_receiver("channel_batches").unwrap();
+        assert_eq!(
+            next_batch(&mut receiver).await.unwrap(),
+            messages[..BATCH_SIZE]
+        );
+        assert_eq!(
+            next_batch(&mut receiver).await.unwrap(),
+            messages[BATCH_SIZE..]
+        );
+        // The consumer is unique.
+        assert!(eth::channel::take_receiver("channel_batches").is_none());
+    }
+}
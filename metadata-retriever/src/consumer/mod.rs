@@ -0,0 +1,244 @@
+//! Consumers of the message transports supported by the event-handler (other than
+//! PubSub push and HTTP, which are served as routes).
+use crate::{
+    app::AppData,
+    routes::{Processed, RequestHandler},
+};
+use anyhow::{anyhow, Result};
+use data_store::models::{FetchKey, FetchStatus};
+use eth::types::{Message, NftId};
+use std::{collections::HashSet, str::FromStr};
+
+mod channel;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "redis")]
+mod redis;
+
+/// Consumers of a stream share the load (as a consumer or queue group of this name).
+#[cfg_attr(not(any(feature = "redis", feature = "nats")), allow(dead_code))]
+const CONSUMER_GROUP: &str = "metadata-retriever";
+
+impl AppData {
+    /// Processes messages of any transport (contracts and tokens, including those of
+    /// batch messages, each in one batch).
+    /// Requests whose fetch is done or was given up on are skipped: batches are redelivered
+    /// as a whole when some of their fetches failed, while the event handler marks every
+    /// request it sends as pending.
+    pub async fn process_messages(&self, messages: Vec<Message>) -> Processed {
+        let requests: Vec<_> = messages.into_iter().flat_map(Message::split).collect();
+        let settled = self.settled_fetches(&requests);
+        if !settled.is_empty() {
+            tracing::debug!("skipping {} settled fetches", settled.len());
+        }
+        let mut contracts = vec![];
+        let mut tokens = vec![];
+        for message in requests {
+            if FetchKey::keys(&message)
+                .iter()
+                .all(|key| settled.contains(key))
+            {
+                continue;
+            }
+            match message {
+                Message::Contract { address } => contracts.push(address),
+                Message::Token {
+                    address,
+                    token_id,
+                    token_uri,
+                } => tokens.push((NftId { address, token_id }, token_uri)),
+                batch => unreachable!("split into single requests: {batch:?}"),
+            }
+        }
+        let mut processed = Processed::default();
+        if !contracts.is_empty() {
+            processed = processed.add(self.process_request(&contracts).await);
+        }
+        if !tokens.is_empty() {
+            processed = processed.add(self.process_request(&tokens).await);
+        }
+        processed
+    }
+
+    /// Fetches of `requests` which are done, or failed without attempts left.
+    fn settled_fetches(&self, requests: &[Message]) -> HashSet<FetchKey> {
+        let keys: Vec<_> = requests.iter().flat_map(FetchKey::keys).collect();
+        self.store
+            .lock()
+            .expect("failed to lock mutex")
+            .load_fetch_states(&keys)
+            .into_iter()
+            .filter(|state| match state.status() {
+                Some(FetchStatus::Done) => true,
+                Some(FetchStatus::Failed) => state.attempts >= self.max_fetch_attempts,
+                _ => false,
+            })
+            .map(|state| state.key())
+            .collect()
+    }
+}
+
+/// Parsed from `redis://<host:port>/<stream>`, `nats://<server>/<subject>`
+/// or `channel://<name>` (in-process).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsumerConfig {
+    Redis { url: String, stream: String },
+    Nats { server: String, subject: String },
+    Channel { name: String },
+}
+
+impl FromStr for ConsumerConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (scheme, rest) = s
+            .split_once("://")
+            .ok_or_else(|| anyhow!("invalid message consumer {s}"))?;
+        if scheme == "channel" && !rest.is_empty() {
+            return Ok(Self::Channel {
+                name: rest.to_string(),
+            });
+        }
+        let (address, name) = rest
+            .rsplit_once('/')
+            .filter(|(address, name)| !address.is_empty() && !name.is_empty())
+            .ok_or_else(|| anyhow!("expected {scheme}://<address>/<name>, got {s}"))?;
+        match scheme {
+            "redis" => Ok(Self::Redis {
+                url: format!("redis://{address}"),
+                stream: name.to_string(),
+            }),
+            "nats" => Ok(Self::Nats {
+                server: address.to_string(),
+                subject: name.to_string(),
+            }),
+            _ => Err(anyhow!("unsupported message consumer {scheme}")),
+        }
+    }
+}
+
+impl ConsumerConfig {
+    /// Consumes messages until the connection fails (or the channel is closed).
+    pub async fn run(&self, state: AppData) -> Result<()> {
+        tracing::info!("consuming messages from {self:?}");
+        match self {
+            #[cfg(feature = "redis")]
+            Self::Redis { url, stream } => redis::consume(url, stream, state).await,
+            #[cfg(feature = "nats")]
+            Self::Nats { server, subject } => nats::consume(server, subject, state).await,
+            Self::Channel { name } => channel::consume(name, state).await,
+            #[allow(unreachable_patterns)]
+            unsupported => {
+                let _ = state;
+                Err(anyhow!(
+                    "{unsupported:?} requires building with the redis or nats feature"
+                ))
+            }
+        }
+    }
+}
+
+/// Messages of the payloads, skipping (and logging) invalid ones.
+#[cfg_attr(not(any(feature = "redis", feature = "nats")), allow(dead_code))]
+fn decode<'a>(payloads: impl IntoIterator<Item = &'a [u8]>) -> Vec<Message> {
+    payloads
+        .into_iter()
+        .filter_map(|payload| match serde_json::from_slice::<Message>(payload) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                tracing::warn!("skipping unrecognized message {payload:?}: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::{
+        contract::abi::EtherscanApi, token::metadata::homebrew::Homebrew, Processed,
+    };
+    use eth::types::{Address, TokenRequest, U256};
+    use std::sync::Arc;
+
+    #[test]
+    fn consumer_config() {
+        assert_eq!(
+            "redis://localhost:6379/metadata"
+                .parse::<ConsumerConfig>()
+                .unwrap(),
+            ConsumerConfig::Redis {
+                url: "redis://localhost:6379".to_string(),
+                stream: "metadata".to_string()
+            }
+        );
+        assert_eq!(
+            "nats://localhost:4222/metadata"
+                .parse::<ConsumerConfig>()
+                .unwrap(),
+            ConsumerConfig::Nats {
+                server: "localhost:4222".to_string(),
+                subject: "metadata".to_string()
+            }
+        );
+        assert_eq!(
+            "channel://metadata".parse::<ConsumerConfig>().unwrap(),
+            ConsumerConfig::Channel {
+                name: "metadata".to_string()
+            }
+        );
+        assert!("pubsub://metadata".parse::<ConsumerConfig>().is_err());
+        assert!("nats://localhost:4222".parse::<ConsumerConfig>().is_err());
+    }
+
+    #[test]
+    fn decode_payloads() {
+        let payloads: [&[u8]; 2] = [
+            br#"{"contract":{"address":"0x0000000000000000000000000000000000000001"}}"#,
+            b"not json",
+        ];
+        assert_eq!(
+            decode(payloads),
+            vec![Message::Contract {
+                address: Address::from(1)
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn redelivered_batch() {
+        let app = AppData::with_fetchers(
+            Arc::new(Homebrew::new(5).expect("reqwest client")),
+            Arc::new(EtherscanApi::new("")),
+        );
+        let token = |id: u64| NftId {
+            address: Address::from(0x5e771ed),
+            token_id: U256::from(id),
+        };
+        {
+            let mut store = app.store.lock().unwrap();
+            let keys = [FetchKey::Token(token(1)), FetchKey::Token(token(2))];
+            store.start_fetches(&keys);
+            store.finish_fetches(&[(keys[0], None), (keys[1], Some("timeout".to_string()))]);
+        }
+        let batch = Message::TokenBatch {
+            tokens: [1, 2]
+                .map(|id| TokenRequest {
+                    address: token(id).address,
+                    token_id: token(id).token_id,
+                    token_uri: None,
+                })
+                .to_vec(),
+        };
+        // Only the failed fetch is attempted again (and fails without uri).
+        assert_eq!(
+            app.process_messages(vec![batch]).await,
+            Processed {
+                requested: 1,
+                fetched: 0,
+                retryable: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use super::{decode, CONSUMER_GROUP};
+use crate::app::AppData;
+use anyhow::Result;
+use futures::StreamExt;
+
+/// Messages published without a consumer are lost (use Redis for at-least-once delivery).
+pub(super) async fn consume(server: &str, subject: &str, state: AppData) -> Result<()> {
+    let client = async_nats::connect(server).await?;
+    let mut batches = client
+        .queue_subscribe(subject.to_string(), CONSUMER_GROUP.to_string())
+        .await?
+        .ready_chunks(100);
+    while let Some(batch) = batches.next().await {
+        let messages = decode(batch.iter().map(|message| message.payload.as_ref()));
+        state.process_messages(messages).await;
+    }
+    Ok(())
+}
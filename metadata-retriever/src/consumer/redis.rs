@@ -0,0 +1,96 @@
+use super::{decode, CONSUMER_GROUP};
+use crate::app::AppData;
+use anyhow::Result;
+use redis::{
+    aio::MultiplexedConnection,
+    streams::{StreamClaimReply, StreamId, StreamReadOptions, StreamReadReply},
+    AsyncCommands, FromRedisValue,
+};
+
+/// Field of the stream entries holding the (JSON) message.
+const MESSAGE_FIELD: &str = "message";
+/// Entries left pending this long (by consumers that died before acknowledging them)
+/// are claimed by another consumer.
+const CLAIM_IDLE_MS: u64 = 5 * 60 * 1000;
+
+/// Reads entries as a member of the consumer group, acknowledging them once processed.
+/// The group is created at the start of the stream, so that entries added before any consumer
+/// was running are read too, and the entries of dead consumers are claimed (see XAUTOCLAIM).
+pub(super) async fn consume(url: &str, stream: &str, state: AppData) -> Result<()> {
+    let client = redis::Client::open(url)?;
+    let mut connection = client.get_multiplexed_async_connection().await?;
+    let created: redis::RedisResult<()> = connection
+        .xgroup_create_mkstream(stream, CONSUMER_GROUP, "0")
+        .await;
+    match created {
+        Err(err) if err.code() != Some("BUSYGROUP") => return Err(err.into()),
+        _ => (),
+    }
+    let consumer = std::env::var("HOSTNAME").unwrap_or(CONSUMER_GROUP.to_string());
+    let options = StreamReadOptions::default()
+        .group(CONSUMER_GROUP, &consumer)
+        .count(100)
+        .block(5000);
+    loop {
+        let claimed = claim_idle(&mut connection, stream, &consumer).await?;
+        process(&mut connection, stream, &state, claimed).await?;
+
+        let reply: Option<StreamReadReply> = connection
+            .xread_options(&[stream], &[">"], &options)
+            .await?;
+        let entries = reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect();
+        process(&mut connection, stream, &state, entries).await?;
+    }
+}
+
+/// Claims (for `consumer`) entries left pending by any consumer of the group for too long.
+async fn claim_idle(
+    connection: &mut MultiplexedConnection,
+    stream: &str,
+    consumer: &str,
+) -> Result<Vec<StreamId>> {
+    // Replies with the next cursor, the claimed entries and (since Redis 7) the deleted ids.
+    let reply: Vec<redis::Value> = redis::cmd("XAUTOCLAIM")
+        .arg(stream)
+        .arg(CONSUMER_GROUP)
+        .arg(consumer)
+        .arg(CLAIM_IDLE_MS)
+        .arg("0-0")
+        .arg("COUNT")
+        .arg(100)
+        .query_async(connection)
+        .await?;
+    let claimed = match reply.get(1) {
+        Some(entries) => StreamClaimReply::from_redis_value(entries)?.ids,
+        None => vec![],
+    };
+    if !claimed.is_empty() {
+        tracing::info!("claimed {} idle entries of {stream}", claimed.len());
+    }
+    Ok(claimed)
+}
+
+async fn process(
+    connection: &mut MultiplexedConnection,
+    stream: &str,
+    state: &AppData,
+    entries: Vec<StreamId>,
+) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let payloads: Vec<Vec<u8>> = entries
+        .iter()
+        .map(|entry| entry.get(MESSAGE_FIELD).unwrap_or_default())
+        .collect();
+    state
+        .process_messages(decode(payloads.iter().map(Vec::as_slice)))
+        .await;
+    let ids: Vec<_> = entries.iter().map(|entry| entry.id.as_str()).collect();
+    let _: i64 = connection.xack(stream, CONSUMER_GROUP, &ids).await?;
+    Ok(())
+}
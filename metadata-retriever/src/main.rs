@@ -1,37 +1,83 @@
 mod app;
+#[cfg(test)]
+mod cassette;
 mod config;
+mod consumer;
+mod oidc;
+mod push;
 mod routes;
 
 use actix_web::{
+    http::header,
     web::{self, Data},
-    App, HttpResponse, HttpServer, Responder,
+    App, HttpRequest, HttpResponse, HttpServer, Responder,
 };
+use anyhow::anyhow;
 use app::AppData;
 use config::Config;
-use eth::types::{Message, NftId};
+use eth::types::Message;
+use futures::future::{self, Either};
+use push::Delivery;
 use std::env;
+use tracing::Instrument;
 
-use crate::routes::RequestHandler;
-async fn pubsub_callback(data: web::Bytes, state: Data<AppData>) -> impl Responder {
-    let json_data = serde_json::from_slice::<serde_json::Value>(&data);
-    if let Ok(message) = serde_json::from_slice::<Message>(&data) {
-        match message {
-            Message::Contract { address } => state.process_request(&[address]).await,
-            Message::Token {
-                address,
-                token_id,
-                token_uri,
-            } => {
-                state
-                    .process_request(&[(NftId { address, token_id }, token_uri)])
-                    .await
-            }
+/// PubSub push deliveries (of one message each). Any response other than success makes PubSub
+/// redeliver the message: failed fetches with attempts left are answered with 503, while
+/// unrecognized messages are acknowledged (as redelivering them can't help).
+async fn pubsub_callback(
+    request: HttpRequest,
+    data: web::Bytes,
+    state: Data<AppData>,
+) -> impl Responder {
+    if let Some(verifier) = &state.push_verifier {
+        let authorization = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if let Err(err) = verifier.verify(authorization).await {
+            tracing::warn!("rejected push delivery: {err:?}");
+            return HttpResponse::Unauthorized().body("invalid push token");
+        }
+    }
+    let delivery = match Delivery::decode(&data) {
+        Ok(delivery) => delivery,
+        Err(err) => {
+            tracing::warn!("unrecognized message format {:?}: {err:?}", data);
+            return HttpResponse::Ok().body(format!("skipped unrecognized message: {err}"));
+        }
+    };
+    let span = tracing::info_span!(
+        "push",
+        message_id = delivery.message_id.as_deref(),
+        subscription = delivery.subscription.as_deref(),
+        delivery_attempt = delivery.delivery_attempt,
+        attributes = ?delivery.attributes,
+    );
+    state
+        .process_messages(vec![delivery.message])
+        .instrument(span)
+        .await
+        .push_response()
+}
+
+/// Messages posted directly (i.e. by the event-handler's HTTP transport), as one or an array.
+async fn messages(data: web::Bytes, state: Data<AppData>) -> impl Responder {
+    let messages = serde_json::from_slice::<Vec<Message>>(&data)
+        .or_else(|_| serde_json::from_slice::<Message>(&data).map(|message| vec![message]));
+    match messages {
+        Ok(messages) => {
+            let count = messages.len();
+            // Failures are retried according to the event handler's fetch policy.
+            let processed = state.process_messages(messages).await;
+            HttpResponse::Ok().body(format!(
+                "processed {count} messages (fetched {}/{})",
+                processed.fetched, processed.requested
+            ))
+        }
+        Err(err) => {
+            tracing::warn!("unrecognized messages {:?}", data);
+            HttpResponse::BadRequest().body(format!("Received unrecognized messages: {err}"))
         }
-    } else {
-        tracing::warn!("unrecognized message format {:?}", data);
-        HttpResponse::BadRequest().body(format!(
-            "Received unrecognized message format {json_data:?}"
-        ))
     }
 }
 
@@ -49,16 +95,41 @@ async fn main() -> std::io::Result<()> {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     let config = Config::from_env().expect("Config error!");
+    let message_consumer = config.message_consumer.clone();
     let state = AppData::new(config).await;
-    HttpServer::new(move || {
+    let consumer_task = message_consumer.clone().map(|consumer| {
+        let state = state.clone();
+        actix_web::rt::spawn(async move { consumer.run(state).await })
+    });
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(Data::new(state.clone()))
             // 2Mb
             .app_data(web::PayloadConfig::new(2 * 1024 * 1024))
             .service(web::resource("/pubsub_callback").route(web::post().to(pubsub_callback)))
+            .service(web::resource("/messages").route(web::post().to(messages)))
     })
     .workers(25)
     .bind("0.0.0.0:8080")?
-    .run()
-    .await
+    .run();
+    let Some(consumer_task) = consumer_task else {
+        return server.await;
+    };
+    // Consumers only stop when failing (e.g. on a lost connection): the process then exits,
+    // to be restarted, rather than silently serving the HTTP routes alone.
+    match future::select(server, consumer_task).await {
+        Either::Left((result, _)) => result,
+        Either::Right((result, server)) => {
+            let err = match result {
+                Ok(Ok(())) => anyhow!("stopped"),
+                Ok(Err(err)) => err,
+                Err(err) => anyhow!("panicked: {err}"),
+            };
+            tracing::error!("message consumer {message_consumer:?} failed: {err:?}");
+            server.handle().stop(true).await;
+            Err(std::io::Error::other(format!(
+                "message consumer failed: {err}"
+            )))
+        }
+    }
 }
@@ -0,0 +1,167 @@
+//! Verification of the OIDC tokens attached to authenticated PubSub push deliveries
+//! (`Authorization: Bearer <JWT>` signed by Google).
+use anyhow::{anyhow, Context, Result};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::{
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+const GOOGLE_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+const GOOGLE_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
+/// Google rotates its signing keys every few days (unknown keys trigger a refresh regardless).
+const KEYS_TTL: Duration = Duration::from_secs(3600);
+/// Unknown keys trigger at most one refresh per interval (and are rejected in between), so that
+/// tokens naming arbitrary key ids can't make every request fetch the certificates.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Claims {
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+pub struct OidcVerifier {
+    audience: String,
+    service_account: Option<String>,
+    client: reqwest::Client,
+    certs_url: String,
+    keys: RwLock<Option<(Instant, JwkSet)>>,
+    /// Last refresh triggered by an unknown key.
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl OidcVerifier {
+    pub fn new(audience: &str, service_account: Option<String>) -> Self {
+        Self {
+            audience: audience.to_string(),
+            service_account,
+            client: reqwest::Client::new(),
+            certs_url: GOOGLE_CERTS_URL.to_string(),
+            keys: RwLock::new(None),
+            last_refresh: Mutex::new(None),
+        }
+    }
+
+    /// Claims of the bearer token in `authorization`, once verified.
+    pub async fn verify(&self, authorization: Option<&str>) -> Result<Claims> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| anyhow!("missing bearer token"))?;
+        let kid = decode_header(token)?
+            .kid
+            .ok_or_else(|| anyhow!("token without key id"))?;
+        let key = self.decoding_key(&kid).await?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.audience]);
+        validation.set_issuer(&GOOGLE_ISSUERS);
+        let claims = decode::<Claims>(token, &key, &validation)?.claims;
+        self.check_account(&claims)?;
+        Ok(claims)
+    }
+
+    fn check_account(&self, claims: &Claims) -> Result<()> {
+        let Some(expected) = &self.service_account else {
+            return Ok(());
+        };
+        match &claims.email {
+            Some(email) if email == expected && claims.email_verified => Ok(()),
+            other => Err(anyhow!("unexpected service account {other:?}")),
+        }
+    }
+
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey> {
+        let (cached, fresh) = match &*self.keys.read().expect("poisoned lock") {
+            Some((fetched, keys)) => (keys.find(kid).cloned(), fetched.elapsed() < KEYS_TTL),
+            None => (None, false),
+        };
+        match cached {
+            Some(jwk) if fresh => return Ok(DecodingKey::from_jwk(&jwk)?),
+            None if fresh && !self.refresh_due() => return Err(anyhow!("unknown key id {kid}")),
+            _ => (),
+        }
+        let keys: JwkSet = async {
+            self.client
+                .get(&self.certs_url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        }
+        .await
+        .context("fetch google certificates")?;
+        let key = keys
+            .find(kid)
+            .map(DecodingKey::from_jwk)
+            .transpose()?
+            .ok_or_else(|| anyhow!("unknown key id {kid}"));
+        *self.keys.write().expect("poisoned lock") = Some((Instant::now(), keys));
+        key
+    }
+
+    /// Whether an unknown key may refresh the (fresh) keys, recording the refresh if so.
+    fn refresh_due(&self) -> bool {
+        let mut last_refresh = self.last_refresh.lock().expect("poisoned lock");
+        if last_refresh.is_some_and(|refreshed| refreshed.elapsed() < MIN_REFRESH_INTERVAL) {
+            return false;
+        }
+        *last_refresh = Some(Instant::now());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_tokens() {
+        let verifier = OidcVerifier::new("https://retriever/pubsub_callback", None);
+        for authorization in [None, Some("Basic abc"), Some("Bearer not.a.jwt")] {
+            assert!(verifier.verify(authorization).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_key_refresh() {
+        let verifier = OidcVerifier {
+            // Refreshes fail to connect.
+            certs_url: "http://127.0.0.1:1/certs".to_string(),
+            ..OidcVerifier::new("aud", None)
+        };
+        *verifier.keys.write().unwrap() = Some((Instant::now(), JwkSet { keys: vec![] }));
+        let refreshed = |result: Result<DecodingKey>| match result {
+            Err(err) => err.to_string() == "fetch google certificates",
+            Ok(_) => panic!("unexpected key"),
+        };
+
+        // The first unknown key triggers a refresh, those that follow are rejected.
+        assert!(refreshed(verifier.decoding_key("a").await));
+        assert!(!refreshed(verifier.decoding_key("b").await));
+
+        *verifier.last_refresh.lock().unwrap() = Some(Instant::now() - MIN_REFRESH_INTERVAL);
+        assert!(refreshed(verifier.decoding_key("b").await));
+    }
+
+    #[test]
+    fn service_account() {
+        let claims = |email: &str, email_verified| Claims {
+            email: Some(email.to_string()),
+            email_verified,
+        };
+        let verifier = OidcVerifier::new("aud", None);
+        assert!(verifier.check_account(&claims("any", false)).is_ok());
+
+        let verifier = OidcVerifier::new("aud", Some("pusher@project.iam".to_string()));
+        assert!(verifier
+            .check_account(&claims("pusher@project.iam", true))
+            .is_ok());
+        assert!(verifier
+            .check_account(&claims("pusher@project.iam", false))
+            .is_err());
+        assert!(verifier.check_account(&claims("other", true)).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! PubSub push deliveries: the published message is wrapped in an envelope
+//! (`{"message":{"data":"<base64>","attributes":{..},..},"subscription":".."}`).
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use eth::types::Message;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PushEnvelope {
+    pub message: PushMessage,
+    pub subscription: String,
+    /// Only set for subscriptions with a dead letter policy.
+    #[serde(rename = "deliveryAttempt")]
+    pub delivery_attempt: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PushMessage {
+    /// Base64 encoded payload.
+    #[serde(default)]
+    pub data: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+    #[serde(rename = "messageId", default)]
+    pub message_id: String,
+}
+
+/// Pushed message along with its delivery details.
+#[derive(Debug, PartialEq)]
+pub struct Delivery {
+    pub message: Message,
+    pub attributes: HashMap<String, String>,
+    pub message_id: Option<String>,
+    pub subscription: Option<String>,
+    pub delivery_attempt: Option<i32>,
+}
+
+impl Delivery {
+    /// Decodes a push envelope or (as posted by hand or by older publishers) a bare message.
+    pub fn decode(body: &[u8]) -> Result<Self> {
+        match serde_json::from_slice::<PushEnvelope>(body) {
+            Ok(envelope) => {
+                let data = STANDARD
+                    .decode(&envelope.message.data)
+                    .context("invalid base64 data")?;
+                Ok(Self {
+                    message: serde_json::from_slice(&data).context("invalid message data")?,
+                    attributes: envelope.message.attributes,
+                    message_id: Some(envelope.message.message_id),
+                    subscription: Some(envelope.subscription),
+                    delivery_attempt: envelope.delivery_attempt,
+                })
+            }
+            Err(_) => Ok(Self {
+                message: serde_json::from_slice(body).context("unrecognized push body")?,
+                attributes: HashMap::new(),
+                message_id: None,
+                subscription: None,
+                delivery_attempt: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth::types::Address;
+
+    const CONTRACT: &str =
+        r#"{"contract":{"address":"0x0000000000000000000000000000000000000001"}}"#;
+
+    #[test]
+    fn push_envelope() {
+        let body = serde_json::json!({
+            "message": {
+                "data": STANDARD.encode(CONTRACT),
+                "attributes": {"kind": "contract"},
+                "messageId": "42",
+                "publishTime": "2024-03-27T09:00:00Z",
+            },
+            "subscription": "projects/indexer/subscriptions/metadata",
+            "deliveryAttempt": 2,
+        });
+        let delivery = Delivery::decode(body.to_string().as_bytes()).unwrap();
+        assert_eq!(
+            delivery,
+            Delivery {
+                message: Message::Contract {
+                    address: Address::from(1)
+                },
+                attributes: HashMap::from([("kind".to_string(), "contract".to_string())]),
+                message_id: Some("42".to_string()),
+                subscription: Some("projects/indexer/subscriptions/metadata".to_string()),
+                delivery_attempt: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn bare_message() {
+        let delivery = Delivery::decode(CONTRACT.as_bytes()).unwrap();
+        assert_eq!(
+            delivery.message,
+            Message::Contract {
+                address: Address::from(1)
+            }
+        );
+        assert!(delivery.message_id.is_none());
+    }
+
+    #[test]
+    fn invalid_bodies() {
+        let envelope = |data: &str| {
+            serde_json::json!({"message": {"data": data}, "subscription": "s"}).to_string()
+        };
+        assert!(Delivery::decode(envelope("not base64!").as_bytes()).is_err());
+        assert!(Delivery::decode(envelope(&STANDARD.encode("{}")).as_bytes()).is_err());
+        assert!(Delivery::decode(b"not json").is_err());
+    }
+}
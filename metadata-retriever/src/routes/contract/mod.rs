@@ -1,19 +1,23 @@
-use actix_web::HttpResponse;
-use data_store::models::ContractAbi;
+use data_store::models::{ContractAbi, FetchKey};
 use eth::types::Address;
 use futures::stream::{self, StreamExt};
 
 use crate::app::AppData;
 
-use super::RequestHandler;
+use super::{Processed, RequestHandler};
 use async_trait;
 
 pub mod abi;
 
 #[async_trait::async_trait]
 impl RequestHandler<Address> for AppData {
-    async fn process_request(&self, addresses: &[Address]) -> HttpResponse {
-        let abis: Vec<_> = stream::iter(addresses)
+    async fn process_request(&self, addresses: &[Address]) -> Processed {
+        let keys: Vec<_> = addresses.iter().copied().map(FetchKey::Contract).collect();
+        self.store
+            .lock()
+            .expect("failed to lock mutex")
+            .start_fetches(&keys);
+        let results: Vec<Result<_, String>> = stream::iter(addresses)
             .then(|address| {
                 let app_ref = self.clone();
                 async move {
@@ -21,34 +25,103 @@ impl RequestHandler<Address> for AppData {
                         Ok(possible_abi) => match possible_abi {
                             Some(abi) => {
                                 tracing::debug!("found contract abi for {address}");
-                                Some((*address, ContractAbi::from(abi)))
+                                Ok((*address, ContractAbi::from(abi)))
                             }
-                            None => None,
+                            // Retried according to the event handler's fetch policy
+                            // (contracts may be verified later).
+                            None => Err("no verified abi".to_string()),
                         },
                         Err(err) => {
                             // TODO - Set Empty if nothing.
                             //  https://github.com/Mintbase/evm-indexer/issues/155
                             tracing::error!("failed abi fetch for {address}: {err:?}");
-                            None
+                            Err(format!("{err:?}"))
                         }
                     }
                 }
             })
-            .collect::<Vec<_>>()
-            .await
-            .iter()
-            .filter_map(|option| option.as_ref().cloned())
+            .collect()
+            .await;
+
+        let outcomes: Vec<_> = keys
+            .into_iter()
+            .zip(&results)
+            .map(|(key, result)| (key, result.as_ref().err().cloned()))
             .collect();
+        let abis: Vec<_> = results.into_iter().filter_map(Result::ok).collect();
 
+        let requested = addresses.len();
+        let fetched = abis.len();
         self.store
             .lock()
             .expect("failed to lock mutex")
             .insert_contract_abis(&abis);
+        let retryable = self.finish_fetches(&outcomes);
+        tracing::info!("added {fetched}/{requested} abi files");
+        Processed {
+            requested,
+            fetched,
+            retryable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cassette::RecordedAbi, routes::contract::abi::EtherscanApi,
+        routes::token::metadata::homebrew::Homebrew,
+    };
+    use data_store::models::TokenContract;
+    use eth::rpc::cassette::CassetteMode;
+    use event_retriever::db_reader::models::EventBase;
+    use std::{str::FromStr, sync::Arc};
+
+    /// Etherscan responses are replayed from a cassette. Re-record it by running
+    /// `RECORD_CASSETTES=1 ETHERSCAN_KEY=<key> cargo test -p metadata-retriever process_contract_request`.
+    #[tokio::test]
+    async fn process_contract_request() {
+        let fetcher = RecordedAbi::new(
+            CassetteMode::from_env(),
+            "test_data/cassettes/contract_request.json",
+            || {
+                let key = std::env::var("ETHERSCAN_KEY").expect("ETHERSCAN_KEY to record");
+                Arc::new(EtherscanApi::new(&key))
+            },
+        )
+        .unwrap();
+        let app = AppData::with_fetchers(
+            Arc::new(Homebrew::new(5).expect("reqwest client")),
+            Arc::new(fetcher),
+        );
+        let verified = Address::from_str("0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d").unwrap();
+        let unverified = Address::from(0xc0de);
+        {
+            let mut store = app.store.lock().unwrap();
+            for address in [verified, unverified] {
+                let base = EventBase {
+                    block_number: 1,
+                    log_index: 0,
+                    transaction_index: 0,
+                    contract_address: address,
+                };
+                store.save_contract(TokenContract::from_event_base(&base), None);
+            }
+        }
 
-        return HttpResponse::Ok().body(format!(
-            "added {}/{} abi files",
-            abis.len(),
-            addresses.len()
-        ));
+        // Contracts without verified source are retried (they may be verified later).
+        let processed = app.process_request(&[verified, unverified]).await;
+        assert_eq!(
+            processed,
+            Processed {
+                requested: 2,
+                fetched: 1,
+                retryable: 1,
+            }
+        );
+        let mut store = app.store.lock().unwrap();
+        assert!(store.load_contract(verified).unwrap().abi_id.is_some());
+        assert_eq!(store.load_contract(unverified).unwrap().abi_id, None);
     }
 }
@@ -1,10 +1,89 @@
+use crate::app::AppData;
 use actix_web::HttpResponse;
+use data_store::models::FetchKey;
 
 pub mod contract;
 pub mod token;
 
 #[async_trait::async_trait]
 pub trait RequestHandler<M> {
-    // TODO - use better return type (impl Responder)
-    async fn process_request(&self, messages: &[M]) -> HttpResponse;
+    async fn process_request(&self, messages: &[M]) -> Processed;
+}
+
+/// Outcome of a batch of fetch requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Processed {
+    pub requested: usize,
+    pub fetched: usize,
+    /// Failed fetches with attempts left (i.e. worth redelivering).
+    pub retryable: usize,
+}
+
+impl Processed {
+    pub fn add(self, other: Self) -> Self {
+        Self {
+            requested: self.requested + other.requested,
+            fetched: self.fetched + other.fetched,
+            retryable: self.retryable + other.retryable,
+        }
+    }
+
+    /// Response to push deliveries: any status other than success makes PubSub redeliver
+    /// the message (with the subscription's backoff).
+    pub fn push_response(&self) -> HttpResponse {
+        if self.retryable > 0 {
+            HttpResponse::ServiceUnavailable().body(format!(
+                "{} of {} fetches failed (retry)",
+                self.retryable, self.requested
+            ))
+        } else {
+            HttpResponse::Ok().body(format!("fetched {}/{}", self.fetched, self.requested))
+        }
+    }
+}
+
+impl AppData {
+    /// Records the fetch `outcomes` (errors of failed fetches), counting failures with attempts left.
+    pub(crate) fn finish_fetches(&self, outcomes: &[(FetchKey, Option<String>)]) -> usize {
+        let failed: Vec<_> = outcomes
+            .iter()
+            .filter(|(_, error)| error.is_some())
+            .map(|(key, _)| *key)
+            .collect();
+        let mut store = self.store.lock().expect("failed to lock mutex");
+        store.finish_fetches(outcomes);
+        if failed.is_empty() {
+            return 0;
+        }
+        store
+            .load_fetch_states(&failed)
+            .iter()
+            .filter(|state| state.attempts < self.max_fetch_attempts)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_response_status() {
+        let processed = Processed {
+            requested: 2,
+            fetched: 1,
+            retryable: 0,
+        };
+        assert!(processed.push_response().status().is_success());
+        let processed = processed.add(Processed {
+            requested: 1,
+            fetched: 0,
+            retryable: 1,
+        });
+        assert_eq!(processed.requested, 3);
+        assert_eq!(
+            processed.push_response().status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
 }
@@ -52,6 +52,17 @@ pub enum UriType {
     Json(Value),
 }
 
+impl UriType {
+    /// True for uris whose content can't change (IPFS, Arweave and inline data).
+    pub fn is_content_addressed(&self) -> bool {
+        match self {
+            Self::Ipfs(_) | Self::Data(_) | Self::Json(_) => true,
+            Self::Url(url) => url.host_str() == Some("arweave.net"),
+            Self::InvalidUrl(_) => false,
+        }
+    }
+}
+
 impl FromStr for UriType {
     type Err = anyhow::Error;
 
@@ -0,0 +1,230 @@
+//! Protection against server side request forgery and resource abuse by tokenURIs, which are
+//! chosen by arbitrary contracts: only http(s) URLs of public hosts are fetched (checked again
+//! for every redirect and for every resolved address), with bounded redirects and body sizes.
+//! Blocked requests are recorded as (permanent) errors rather than failing the fetch.
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    redirect::{Action, Attempt, Policy},
+    Response,
+};
+use std::{
+    error::Error,
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+use url::{Host, Url};
+
+/// Maximum number of redirects followed per request.
+pub const MAX_REDIRECTS: usize = 5;
+/// Maximum size of response bodies (metadata documents are a few KB).
+pub const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedUrl(pub String);
+
+impl fmt::Display for BlockedUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blocked request: {}", self.0)
+    }
+}
+
+impl Error for BlockedUrl {}
+
+/// True for addresses which are not publicly routable: private, loopback, link-local (including
+/// cloud metadata endpoints such as 169.254.169.254), shared, multicast and reserved ranges.
+pub fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_blocked_ipv4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_blocked_ipv4(mapped);
+            }
+            let segments = ip.segments();
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10).
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                // NAT64 (64:ff9b::/96) translates to the embedded IPv4 address.
+                || (segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                    && is_blocked_ipv4(Ipv4Addr::from(
+                        (u32::from(segments[6]) << 16) | u32::from(segments[7]),
+                    )))
+                // Documentation (2001:db8::/32).
+                || segments[..2] == [0x2001, 0xdb8]
+        }
+    }
+}
+
+fn is_blocked_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network" (0.0.0.0/8) and shared address space (100.64.0.0/10).
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments (192.0.0.0/24) and benchmarking (198.18.0.0/15).
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved (240.0.0.0/4).
+        || a >= 240
+}
+
+/// Checks the scheme and (literal IP) host of `url`. Hosts given by name are checked by
+/// [GuardedResolver] once resolved.
+pub fn check_url(url: &Url) -> Result<(), BlockedUrl> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(BlockedUrl(format!("unsupported scheme {}", url.scheme())));
+    }
+    let ip = match url.host() {
+        None => return Err(BlockedUrl("missing host".to_string())),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            if domain == "localhost" || domain.ends_with(".localhost") {
+                return Err(BlockedUrl(format!("private host {domain}")));
+            }
+            return Ok(());
+        }
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+    };
+    if is_blocked_ip(ip) {
+        return Err(BlockedUrl(format!("private address {ip}")));
+    }
+    Ok(())
+}
+
+/// Follows at most [MAX_REDIRECTS] redirects, to URLs passing [check_url].
+pub fn redirect_policy() -> Policy {
+    Policy::custom(|attempt: Attempt| -> Action {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error(BlockedUrl(format!("more than {MAX_REDIRECTS} redirects")));
+        }
+        match check_url(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(blocked) => attempt.error(blocked),
+        }
+    })
+}
+
+/// System DNS resolution, failing for hosts resolving to any blocked address. Checking the
+/// addresses actually connected to (rather than resolving up front) prevents DNS rebinding.
+pub struct GuardedResolver;
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(resolve_public(name.as_str().to_string()))
+    }
+}
+
+async fn resolve_public(host: String) -> Result<Addrs, Box<dyn Error + Send + Sync>> {
+    // The port is replaced by the connector.
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+    if let Some(addr) = addrs.iter().find(|addr| is_blocked_ip(addr.ip())) {
+        let reason = format!("{host} resolves to private address {}", addr.ip());
+        return Err(BlockedUrl(reason).into());
+    }
+    Ok(Box::new(addrs.into_iter()))
+}
+
+/// The [BlockedUrl] causing a request error, if any.
+pub fn blocked_cause<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a BlockedUrl> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(blocked) = err.downcast_ref::<BlockedUrl>() {
+            return Some(blocked);
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Reads the body of `response`, failing (with [BlockedUrl]) once it exceeds `max_bytes`.
+pub async fn read_limited(mut response: Response, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+    let too_large = || BlockedUrl(format!("response body exceeds {max_bytes} bytes")).into();
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_ips() {
+        for ip in [
+            "10.0.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "172.16.0.1",
+            "192.168.1.1",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(is_blocked_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "8.8.8.8",
+            "104.16.0.1",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+        ] {
+            assert!(!is_blocked_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn checked_urls() {
+        for url in [
+            "https://ipfs.io/ipfs/Qm",
+            "http://api.example.com:8080/token/1",
+            "https://8.8.8.8/",
+        ] {
+            assert_eq!(check_url(&Url::parse(url).unwrap()), Ok(()), "{url}");
+        }
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com/1",
+            "http://localhost:8080/",
+            "http://api.localhost/",
+            "http://169.254.169.254/computeMetadata/v1/",
+            "http://[::1]/",
+            "http://2130706433/",
+        ] {
+            assert!(check_url(&Url::parse(url).unwrap()).is_err(), "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resolved_private_hosts() {
+        let name: Name = "localhost".parse().unwrap();
+        let err = GuardedResolver.resolve(name).await.err().unwrap();
+        assert!(blocked_cause(err.as_ref()).is_some());
+    }
+}
@@ -1,48 +1,128 @@
-use crate::routes::token::metadata::{data_url::UriType, util::ENS_URI};
+use crate::routes::token::metadata::{
+    data_url::UriType,
+    guard::{blocked_cause, check_url, redirect_policy, GuardedResolver},
+    limiter::{rate_limit_backoff, FetchLimiter, FetchLimits},
+    util::ENS_URI,
+};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use eth::types::{NftId, ENS_ADDRESS};
-use std::{str::FromStr, time::Duration};
+use reqwest::{
+    header::{IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    StatusCode,
+};
+use std::{str::FromStr, sync::Arc, time::Duration};
 use url::Url;
 
-use super::{FetchedMetadata, MetadataFetching};
+use super::{Fetch, FetchedMetadata, MetadataFetching, Validators};
+
+/// Rate limited requests are retried this many times (when told to wait at most
+/// [MAX_RETRY_WAIT]), before failing the fetch.
+const MAX_RATE_LIMITED_RETRIES: usize = 2;
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
 
 pub struct Homebrew {
     client: reqwest::Client,
+    limiter: FetchLimiter,
 }
 
 impl Homebrew {
+    #[cfg(test)]
     pub fn new(timeout_seconds: u64) -> Result<Self> {
+        Self::with_limits(timeout_seconds, FetchLimits::default())
+    }
+
+    pub fn with_limits(timeout_seconds: u64, limits: FetchLimits) -> Result<Self> {
         Ok(Self {
             client: reqwest::Client::builder()
                 .timeout(Duration::from_secs(timeout_seconds))
+                .redirect(redirect_policy())
+                .dns_resolver(Arc::new(GuardedResolver))
                 .build()?,
+            limiter: FetchLimiter::new(limits),
         })
     }
 
-    async fn url_request(&self, url: Url) -> Result<FetchedMetadata> {
-        let result = self.client.get(url).send().await;
-        match result {
-            Ok(response) => FetchedMetadata::from_response(response).await,
-            Err(err) => {
-                let err_string = err.to_string();
-                if err_string.contains("error trying to connect: ") {
-                    // Known errors can be recorded and handled properly.
-                    let message = err_string
-                        .split("error trying to connect: ")
-                        .last()
-                        .expect("message after");
-                    return Ok(FetchedMetadata::error(message));
-                }
-                Err(anyhow!(err_string))
+    async fn conditional_request(&self, url: Url, validators: &Validators) -> Result<Fetch> {
+        if let Err(blocked) = check_url(&url) {
+            tracing::warn!("{blocked} ({url})");
+            return Ok(Self::modified(FetchedMetadata::error(&blocked.to_string())));
+        }
+        let host = url.host_str().unwrap_or_default().to_string();
+        let mut retries = 0;
+        loop {
+            let _permit = self.limiter.acquire(&host).await;
+            let mut request = self.client.get(url.clone());
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) => return Self::request_error(err).map(Self::modified),
+            };
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(Fetch::NotModified);
+            }
+            let Some(delay) = rate_limit_backoff(&response) else {
+                let validators = Validators::from_response(&response);
+                let metadata = FetchedMetadata::from_response(response).await?;
+                return Ok(Fetch::Modified(metadata, validators));
+            };
+            // Rate limits are transient: the fetch fails (to be retried) rather than
+            // recording the response as the token's metadata.
+            tracing::info!("rate limited by {host}, backing off for {delay:?}");
+            self.limiter.back_off(&host, delay);
+            if retries == MAX_RATE_LIMITED_RETRIES || delay > MAX_RETRY_WAIT {
+                return Err(anyhow!("rate limited by {host} (retry after {delay:?})"));
             }
+            retries += 1;
+        }
+    }
+
+    fn modified(metadata: FetchedMetadata) -> Fetch {
+        Fetch::Modified(metadata, Validators::default())
+    }
+
+    fn request_error(err: reqwest::Error) -> Result<FetchedMetadata> {
+        if let Some(blocked) = blocked_cause(&err) {
+            // Recorded as the (permanent) outcome of the fetch.
+            tracing::warn!("{blocked} ({:?})", err.url().map(Url::as_str));
+            return Ok(FetchedMetadata::error(&blocked.to_string()));
+        }
+        let err_string = err.to_string();
+        if err_string.contains("error trying to connect: ") {
+            // Known errors can be recorded and handled properly.
+            let message = err_string
+                .split("error trying to connect: ")
+                .last()
+                .expect("message after");
+            return Ok(FetchedMetadata::error(message));
         }
+        Err(anyhow!(err_string))
     }
 }
 
 #[async_trait]
 impl MetadataFetching for Homebrew {
     async fn get_nft_metadata(&self, token: NftId, uri: Option<String>) -> Result<FetchedMetadata> {
+        let fetch = self
+            .fetch_if_modified(token, uri, &Validators::default())
+            .await?;
+        match fetch {
+            Fetch::Modified(metadata, _) => Ok(metadata),
+            Fetch::NotModified => Err(anyhow!("unexpected 304 without validators")),
+        }
+    }
+
+    async fn fetch_if_modified(
+        &self,
+        token: NftId,
+        uri: Option<String>,
+        validators: &Validators,
+    ) -> Result<Fetch> {
         let uri = match token.address {
             // If ENS --> We know the URI.
             ENS_ADDRESS => Some(format!("{ENS_URI}/{}", token.token_id)),
@@ -60,28 +140,28 @@ impl MetadataFetching for Homebrew {
             UriType::Url(url) => {
                 tracing::debug!("Url Type for {token}");
                 // If ERC1155 we (may) need to do a replacement on the url.
-                self.url_request(url).await
+                self.conditional_request(url, validators).await
             }
             UriType::Ipfs(path) => {
                 tracing::debug!("IPFS Type for {token}");
-                self.url_request(Url::from(path)).await
+                self.conditional_request(Url::from(path), validators).await
             }
             UriType::Data(content) => {
                 tracing::debug!("Data Type for {token}");
-                Ok(FetchedMetadata::from_str(&content)?)
+                Ok(Self::modified(FetchedMetadata::from_str(&content)?))
             }
-            UriType::Json(value) => Ok(FetchedMetadata {
+            UriType::Json(value) => Ok(Self::modified(FetchedMetadata {
                 hash: md5::compute(value.to_string().as_bytes()).to_vec(),
                 raw: None,
                 json: Some(value),
-            }),
+            })),
             UriType::InvalidUrl(err) => {
                 let message = format!("invalid tokenUri ({}) do not retry", err);
-                Ok(FetchedMetadata {
+                Ok(Self::modified(FetchedMetadata {
                     hash: md5::compute(message.as_bytes()).to_vec(),
                     raw: Some(message),
                     json: None,
-                })
+                }))
             }
         };
     }
@@ -96,6 +176,18 @@ mod tests {
 
     use super::*;
 
+    impl Homebrew {
+        async fn url_request(&self, url: Url) -> Result<FetchedMetadata> {
+            match self
+                .conditional_request(url, &Validators::default())
+                .await?
+            {
+                Fetch::Modified(metadata, _) => Ok(metadata),
+                Fetch::NotModified => Err(anyhow!("unexpected 304 without validators")),
+            }
+        }
+    }
+
     fn get_fetcher() -> Homebrew {
         Homebrew::new(5).unwrap()
     }
@@ -238,6 +330,18 @@ mod tests {
             .all(|x| x.raw.as_ref().unwrap().contains("internal error")));
     }
 
+    #[tokio::test]
+    async fn url_request_blocked() {
+        let urls = [
+            "http://169.254.169.254/computeMetadata/v1/",
+            "http://[::1]:8080/token/1",
+            "http://localhost:8080/token/1",
+        ];
+        for result in get_results_for_urls(&urls).await {
+            assert!(result.raw.unwrap().starts_with("blocked request: "));
+        }
+    }
+
     #[tokio::test]
     async fn url_request_400_status_errors() {
         // 400 Bad Request
@@ -0,0 +1,205 @@
+//! Politeness of metadata fetching: a global bound on concurrent requests and, per host, a bound
+//! on concurrent requests, a minimum interval between them and back-off when rate limited.
+//! The limiter is shared by all workers (through the fetcher of [crate::app::AppData]).
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+/// Back-off of rate limited hosts not telling for how long (via `Retry-After`).
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FetchLimits {
+    /// Maximum number of concurrent requests (to all hosts).
+    pub concurrency: usize,
+    /// Maximum number of concurrent requests to a single host.
+    pub host_concurrency: usize,
+    /// Maximum number of requests per second to a single host (unlimited if unset or 0).
+    pub host_rate: Option<f64>,
+}
+
+impl Default for FetchLimits {
+    fn default() -> Self {
+        Self {
+            concurrency: 50,
+            host_concurrency: 4,
+            host_rate: Some(10.0),
+        }
+    }
+}
+
+struct Host {
+    permits: Arc<Semaphore>,
+    /// Earliest time of the next request.
+    next_request: Mutex<Instant>,
+}
+
+pub struct FetchLimiter {
+    limits: FetchLimits,
+    global: Arc<Semaphore>,
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+/// Permission to send a request, held until its response is read.
+pub struct FetchPermit {
+    _global: OwnedSemaphorePermit,
+    _host: OwnedSemaphorePermit,
+}
+
+impl FetchLimiter {
+    pub fn new(limits: FetchLimits) -> Self {
+        Self {
+            limits,
+            global: Arc::new(Semaphore::new(limits.concurrency.max(1))),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn host(&self, host: &str) -> Arc<Host> {
+        self.hosts
+            .lock()
+            .expect("poisoned lock")
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                Arc::new(Host {
+                    permits: Arc::new(Semaphore::new(self.limits.host_concurrency.max(1))),
+                    next_request: Mutex::new(Instant::now()),
+                })
+            })
+            .clone()
+    }
+
+    /// Waits for a request slot of `host`, then for a global one (so that slow or rate limited
+    /// hosts don't hold up requests to others).
+    pub async fn acquire(&self, host: &str) -> FetchPermit {
+        let state = self.host(host);
+        let host_permit = state
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore never closed");
+        let interval = self
+            .limits
+            .host_rate
+            .filter(|rate| *rate > 0.0)
+            .map_or(Duration::ZERO, |rate| Duration::from_secs_f64(1.0 / rate));
+        let slot = {
+            let mut next_request = state.next_request.lock().expect("poisoned lock");
+            let slot = (*next_request).max(Instant::now());
+            *next_request = slot + interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+        let global_permit = self
+            .global
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore never closed");
+        FetchPermit {
+            _global: global_permit,
+            _host: host_permit,
+        }
+    }
+
+    /// Delays further requests to `host` by `delay`.
+    pub fn back_off(&self, host: &str, delay: Duration) {
+        let state = self.host(host);
+        let mut next_request = state.next_request.lock().expect("poisoned lock");
+        *next_request = (*next_request).max(Instant::now() + delay);
+    }
+}
+
+/// Back-off requested by a rate limiting response (`429 Too Many Requests` or
+/// `503 Service Unavailable` with `Retry-After`), if any.
+pub fn rate_limit_backoff(response: &Response) -> Option<Duration> {
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, SystemTime::now()));
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => Some(retry_after.unwrap_or(DEFAULT_BACKOFF)),
+        StatusCode::SERVICE_UNAVAILABLE => retry_after,
+        _ => None,
+    }
+}
+
+/// Parses `Retry-After` values: either seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(host_concurrency: usize, host_rate: Option<f64>) -> FetchLimiter {
+        FetchLimiter::new(FetchLimits {
+            concurrency: 10,
+            host_concurrency,
+            host_rate,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn host_concurrency() {
+        let limiter = limiter(2, None);
+        let _first = limiter.acquire("a.com").await;
+        let _second = limiter.acquire("a.com").await;
+        let third = tokio::time::timeout(Duration::from_secs(1), limiter.acquire("a.com"));
+        assert!(third.await.is_err());
+        // Other hosts are not held up.
+        let other = tokio::time::timeout(Duration::from_secs(1), limiter.acquire("b.com"));
+        assert!(other.await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn host_rate_and_back_off() {
+        let limiter = limiter(10, Some(2.0));
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire("a.com").await;
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        limiter.back_off("a.com", Duration::from_secs(30));
+        limiter.acquire("a.com").await;
+        assert!(start.elapsed() >= Duration::from_secs(31));
+        // Backed off hosts don't hold up others.
+        let start = Instant::now();
+        limiter.acquire("b.com").await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = httpdate::parse_http_date("Wed, 27 Mar 2024 09:00:00 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 27 Mar 2024 09:01:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 27 Mar 2024 08:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
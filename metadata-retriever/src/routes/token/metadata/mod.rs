@@ -3,20 +3,69 @@ use anyhow::{anyhow, Context, Result};
 use async_trait;
 use data_store::models::NftMetadata;
 use eth::types::NftId;
-use reqwest::Response;
+use guard::{read_limited, BlockedUrl, MAX_BODY_BYTES};
+use reqwest::{
+    header::{ETAG, LAST_MODIFIED},
+    Response,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 pub(crate) mod data_url;
+pub mod guard;
 pub mod homebrew;
 mod ipfs;
+pub mod limiter;
 mod util;
 
 #[async_trait::async_trait]
 pub trait MetadataFetching: Send + Sync {
     async fn get_nft_metadata(&self, token: NftId, uri: Option<String>) -> Result<FetchedMetadata>;
+
+    /// Fetches the metadata unless unchanged since an earlier fetch with `validators`
+    /// (sent as conditional request headers), along with the validators of the response.
+    async fn fetch_if_modified(
+        &self,
+        token: NftId,
+        uri: Option<String>,
+        _validators: &Validators,
+    ) -> Result<Fetch> {
+        let metadata = self.get_nft_metadata(token, uri).await?;
+        Ok(Fetch::Modified(metadata, Validators::default()))
+    }
+}
+
+/// HTTP validators (`ETag` and `Last-Modified`) of a fetched document.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
+pub enum Fetch {
+    Modified(FetchedMetadata, Validators),
+    /// The document is unchanged (`304 Not Modified`).
+    NotModified,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct FetchedMetadata {
     hash: Vec<u8>,
     raw: Option<String>,
@@ -56,6 +105,7 @@ fn try_parse_raw_and_json(bytes: Bytes) -> (Option<String>, Option<Value>) {
 }
 
 impl FetchedMetadata {
+    /// Reads `response` (bodies larger than [MAX_BODY_BYTES] are recorded as errors).
     pub async fn from_response(response: Response) -> Result<Self> {
         // Handle Status errors first.
         if let Err(status_error) = response.error_for_status_ref() {
@@ -70,7 +120,11 @@ impl FetchedMetadata {
             .unwrap_or_default();
 
         let url = response.url().clone();
-        let response_bytes = response.bytes().await?;
+        let response_bytes = match read_limited(response, MAX_BODY_BYTES).await {
+            Ok(bytes) => Bytes::from(bytes),
+            Err(err) if err.is::<BlockedUrl>() => return Ok(Self::error(&err.to_string())),
+            Err(err) => return Err(err),
+        };
         let hash = md5::compute(&response_bytes).0.to_vec();
         if is_try_json_type(content_type) {
             // Handle JSON
@@ -99,6 +153,12 @@ impl FetchedMetadata {
         }
     }
 
+    /// Metadata documents are cached per uri, while errors and other responses aren't
+    /// (gateway errors are transient, even for content addressed uris).
+    pub fn is_cacheable(&self) -> bool {
+        self.json.is_some()
+    }
+
     pub fn error(text: &str) -> Self {
         Self {
             hash: md5::compute(text.as_bytes()).0.to_vec(),
@@ -114,7 +174,7 @@ mod tests {
     use csv::ReaderBuilder;
     use eth::{
         rpc::{ethers::Client, EthNodeReading},
-        types::{Address, NftId, U256},
+        types::{Address, BlockTag, NftId, U256},
     };
     use flate2::read::GzDecoder;
     use rand::{seq::SliceRandom, thread_rng};
@@ -201,7 +261,7 @@ mod tests {
         let token = NftId::from_str("0xC36442B4A4522E871399CD717ABDD847AB11FE88/257999").unwrap();
         let eth_rpc = Client::new("https://rpc.ankr.com/eth").unwrap();
         let uri = eth_rpc
-            .get_uris(&[token])
+            .get_uris(&[token], BlockTag::Latest)
             .await
             .get(&token)
             .unwrap()
@@ -1,48 +1,290 @@
-use actix_web::HttpResponse;
-use eth::types::NftId;
+use eth::types::{NftId, ENS_ADDRESS};
 use futures::stream::{self, StreamExt};
 pub mod metadata;
-use crate::{app::AppData, routes::RequestHandler};
+use crate::{
+    app::AppData,
+    routes::{Processed, RequestHandler},
+};
 use async_trait;
-use data_store::models::NftMetadata;
+use data_store::{
+    models::{FetchKey, NftMetadata, UriCacheEntry},
+    uri_cache::CachedUri,
+};
+use metadata::{data_url::UriType, Fetch, Validators};
+use std::{collections::HashMap, str::FromStr};
+
+/// How long metadata fetched from a uri is linked to further tokens with that uri.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UriCacheTtl {
+    /// Of uris whose content may change (i.e. HTTP APIs), revalidated once expired.
+    pub mutable_secs: i64,
+    /// Of content addressed uris (IPFS, Arweave and data).
+    pub immutable_secs: i64,
+}
+
+impl Default for UriCacheTtl {
+    fn default() -> Self {
+        Self {
+            mutable_secs: 3600,
+            immutable_secs: 30 * 86_400,
+        }
+    }
+}
+
+impl UriCacheTtl {
+    pub fn secs(&self, uri: &str) -> i64 {
+        match UriType::from_str(uri) {
+            Ok(uri_type) if uri_type.is_content_addressed() => self.immutable_secs,
+            _ => self.mutable_secs,
+        }
+    }
+}
+
+/// Outcome of a uri fetch, shared by all tokens with the uri.
+enum UriOutcome {
+    Fetched(NftMetadata),
+    /// Cached (or unchanged) metadata.
+    Linked(Vec<u8>),
+    Failed(String),
+}
+
+impl AppData {
+    /// Fetches the metadata of `uri` (for `token`, one of the tokens with the uri) unless the
+    /// `cached` fetch is fresh, revalidating it otherwise. Returns the cache entry to save, if any.
+    async fn fetch_uri(
+        &self,
+        token: NftId,
+        uri: Option<&str>,
+        cached: Option<UriCacheEntry>,
+    ) -> (UriOutcome, Option<CachedUri>) {
+        if let Some(entry) = cached.as_ref().filter(|entry| entry.is_fresh()) {
+            return (UriOutcome::Linked(entry.metadata_id.clone()), None);
+        }
+        let validators = cached
+            .as_ref()
+            .map(|entry| Validators {
+                etag: entry.etag.clone(),
+                last_modified: entry.last_modified.clone(),
+            })
+            .unwrap_or_default();
+        let cache_entry = |metadata_id: &[u8], validators: Validators| {
+            uri.map(|uri| CachedUri {
+                uri: uri.to_string(),
+                metadata_id: metadata_id.to_vec(),
+                etag: validators.etag,
+                last_modified: validators.last_modified,
+                ttl_secs: self.uri_cache_ttl.secs(uri),
+            })
+        };
+        let fetch = self
+            .metadata_fetcher
+            .fetch_if_modified(token, uri.map(str::to_string), &validators)
+            .await;
+        match (fetch, cached) {
+            (Ok(Fetch::NotModified), Some(entry)) => {
+                tracing::debug!("metadata at {} not modified", entry.uri);
+                let refreshed = cache_entry(&entry.metadata_id, validators);
+                (UriOutcome::Linked(entry.metadata_id), refreshed)
+            }
+            (Ok(Fetch::NotModified), None) => (
+                UriOutcome::Failed("unexpected 304 without validators".to_string()),
+                None,
+            ),
+            (Ok(Fetch::Modified(metadata, validators)), _) => {
+                let cacheable = metadata.is_cacheable();
+                let metadata: NftMetadata = metadata.into();
+                let entry = if cacheable {
+                    cache_entry(&metadata.uid, validators)
+                } else {
+                    None
+                };
+                (UriOutcome::Fetched(metadata), entry)
+            }
+            (Err(err), _) => {
+                tracing::warn!("metadata for {token} not found ({err:?}). Using None");
+                (UriOutcome::Failed(format!("{err:?}")), None)
+            }
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl RequestHandler<(NftId, Option<String>)> for AppData {
-    async fn process_request(&self, tokens: &[(NftId, Option<String>)]) -> HttpResponse {
-        let possible_content: Vec<Option<_>> = stream::iter(tokens)
-            .then(|(token, uri)| {
-                let app_ref = self.clone();
-                async move {
-                    match app_ref
-                        .metadata_fetcher
-                        .get_nft_metadata(*token, uri.clone())
-                        .await
-                    {
-                        Ok(metadata) => Some(metadata),
-                        Err(err) => {
-                            tracing::warn!("metadata for {token} not found ({err:?}). Using None");
-                            None
-                        }
-                    }
+    async fn process_request(&self, tokens: &[(NftId, Option<String>)]) -> Processed {
+        let keys: Vec<_> = tokens
+            .iter()
+            .map(|(token, _)| FetchKey::Token(*token))
+            .collect();
+        self.store
+            .lock()
+            .expect("failed to lock mutex")
+            .start_fetches(&keys);
+
+        // Tokens sharing a uri are fetched once (ENS tokens, whose uri is derived from
+        // the token, and tokens without uri individually).
+        let mut groups: Vec<(Option<&str>, Vec<NftId>)> = vec![];
+        let mut group_index: HashMap<&str, usize> = HashMap::new();
+        for (token, uri) in tokens {
+            match uri.as_deref().filter(|_| token.address != ENS_ADDRESS) {
+                Some(uri) => {
+                    let index = *group_index.entry(uri).or_insert_with(|| {
+                        groups.push((Some(uri), vec![]));
+                        groups.len() - 1
+                    });
+                    groups[index].1.push(*token);
                 }
+                None => groups.push((None, vec![*token])),
+            }
+        }
+        let uris: Vec<_> = groups.iter().filter_map(|(uri, _)| *uri).collect();
+        let mut cached = self
+            .store
+            .lock()
+            .expect("failed to lock mutex")
+            .load_uri_cache(&uris);
+        // Buffered (rather than unordered) to keep the results in the order of their groups.
+        let fetches: Vec<_> = groups
+            .iter()
+            .map(|(uri, group)| {
+                let entry = uri.and_then(|uri| cached.remove(uri));
+                self.fetch_uri(group[0], *uri, entry)
             })
+            .collect();
+        let results: Vec<_> = stream::iter(fetches)
+            .buffered(self.fetch_concurrency)
             .collect()
             .await;
 
-        let updates: Vec<(NftId, NftMetadata)> = tokens
-            .iter()
-            .zip(possible_content)
-            .filter_map(|(token, content)| content.map(|value| ((token.0), value.into())))
+        let mut updates: Vec<(NftId, NftMetadata)> = vec![];
+        let mut links: Vec<(NftId, Vec<u8>)> = vec![];
+        let mut cache_entries = vec![];
+        let mut errors: HashMap<NftId, String> = HashMap::new();
+        for ((_, group), (outcome, cache_entry)) in groups.iter().zip(results) {
+            cache_entries.extend(cache_entry);
+            match outcome {
+                UriOutcome::Fetched(metadata) => {
+                    updates.extend(group.iter().map(|token| (*token, metadata.clone())))
+                }
+                UriOutcome::Linked(uid) => {
+                    links.extend(group.iter().map(|token| (*token, uid.clone())))
+                }
+                UriOutcome::Failed(err) => {
+                    errors.extend(group.iter().map(|token| (*token, err.clone())))
+                }
+            }
+        }
+        let outcomes: Vec<_> = keys
+            .into_iter()
+            .zip(tokens)
+            .map(|(key, (token, _))| (key, errors.get(token).cloned()))
             .collect();
 
-        self.store
-            .lock()
-            .expect("failed to lock mutex")
-            .insert_metadata_batch(&updates);
-        return HttpResponse::Ok().body(format!(
-            "added {}/{} token metadata files",
-            updates.len(),
-            tokens.len()
-        ));
+        let requested = tokens.len();
+        let fetched = updates.len() + links.len();
+        {
+            let mut store = self.store.lock().expect("failed to lock mutex");
+            store.insert_metadata_batch(&updates);
+            store.link_metadata(&links);
+            // After the metadata, which cache entries refer to.
+            store.save_uri_cache(&cache_entries);
+        }
+        let retryable = self.finish_fetches(&outcomes);
+        tracing::info!(
+            "added {fetched}/{requested} token metadata files ({} linked from {} uris)",
+            links.len(),
+            uris.len()
+        );
+        Processed {
+            requested,
+            fetched,
+            retryable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cassette::RecordedMetadata, routes::contract::abi::EtherscanApi,
+        routes::token::metadata::homebrew::Homebrew,
+    };
+    use data_store::models::Nft;
+    use eth::{
+        rpc::cassette::CassetteMode,
+        types::{Address, TxDetails, U256},
+    };
+    use event_retriever::db_reader::models::EventBase;
+    use std::sync::Arc;
+
+    /// Fetches are replayed from a cassette. Re-record it (on an empty store) by running
+    /// `RECORD_CASSETTES=1 cargo test -p metadata-retriever process_token_request`.
+    #[tokio::test]
+    async fn process_token_request() {
+        let fetcher = RecordedMetadata::new(
+            CassetteMode::from_env(),
+            "test_data/cassettes/token_request.json",
+            || Arc::new(Homebrew::new(5).expect("reqwest client")),
+        )
+        .unwrap();
+        let app = AppData::with_fetchers(Arc::new(fetcher), Arc::new(EtherscanApi::new("")));
+        let uri = "data:application/json;base64,eyJuYW1lIjogIjEifQ==";
+        let token = |id: u64| NftId {
+            address: Address::from(0x7e57),
+            token_id: U256::from(id),
+        };
+        let requests = [
+            (token(1), Some(uri.to_string())),
+            (token(2), Some(uri.to_string())),
+            (token(3), None),
+        ];
+        {
+            let mut store = app.store.lock().unwrap();
+            for (id, (token, uri)) in requests.iter().enumerate() {
+                let base = EventBase {
+                    block_number: 1,
+                    log_index: id as u64,
+                    transaction_index: 0,
+                    contract_address: token.address,
+                };
+                let nft = Nft {
+                    token_uri: uri.clone(),
+                    ..Nft::new(&base, token, &TxDetails::default())
+                };
+                store.save_nft(nft, None);
+            }
+        }
+
+        // Tokens sharing a uri get the same metadata, the one without uri is to be retried.
+        let processed = app.process_request(&requests).await;
+        assert_eq!(
+            processed,
+            Processed {
+                requested: 3,
+                fetched: 2,
+                retryable: 1,
+            }
+        );
+        let mut store = app.store.lock().unwrap();
+        let metadata_id = store.load_nft(&token(1)).unwrap().metadata_id;
+        assert!(metadata_id.is_some());
+        assert_eq!(store.load_nft(&token(2)).unwrap().metadata_id, metadata_id);
+        assert_eq!(store.load_nft(&token(3)).unwrap().metadata_id, None);
+    }
+
+    #[test]
+    fn uri_cache_ttl() {
+        let ttl = UriCacheTtl::default();
+        for uri in [
+            "ipfs://QmTy8w65yBXgyfG2ZBg5TrfB2hPjrDQH3RCQFJGkARStJb/1.json",
+            "ar://hwU8DqHJ3ywc0Y3Ewq7bvPE2nvzMqSYTRzm5Ko5e3h4/1",
+            "data:application/json;base64,eyJuYW1lIjogIjEifQ==",
+        ] {
+            assert_eq!(ttl.secs(uri), ttl.immutable_secs, "{uri}");
+        }
+        assert_eq!(
+            ttl.secs("https://api.example.com/token/1"),
+            ttl.mutable_secs
+        );
     }
 }